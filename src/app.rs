@@ -1,26 +1,177 @@
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
 use thruster::context::hyper_request::HyperRequest;
 use thruster::errors::ThrusterError as Error;
 use thruster::App;
 use thruster::{async_middleware, map_try, middleware_fn};
-use thruster::{MiddlewareNext, MiddlewareResult};
-use tokio::sync::RwLock;
+use thruster::{Context, MiddlewareChain, MiddlewareNext, MiddlewareResult};
+use uuid::Uuid;
 
-use crate::context::Ctx;
+use crate::battle;
+#[cfg(not(test))]
+use crate::config::Config;
+use crate::context::{AppState, Ctx};
+use crate::coverage;
+use crate::day_care::Egg;
 use crate::errors::ErrorSet;
-use crate::pokemon::Pokemon;
-use crate::pokemon_api::get_pokemon;
-use crate::storage::{Storage, StorageDestination, StorageError};
+use crate::evolution;
+use crate::forms::{self, FormError};
+use crate::instance::{Instance, InstanceChanges};
+use crate::items::{Bag, Item, MAX_ITEM_COUNT};
+use crate::logging::{current_request_id, REQUEST_ID};
+use crate::moves::{self, LearnableMove};
+use crate::nuzlocke::Encounter;
+use crate::openapi;
+use crate::pokedex::{self, Completion, DexEntry};
+use crate::pokemon::{Pokemon, Stats};
+use crate::pokemon_api::{DEFAULT_PREFETCH_CONCURRENCY, MAX_PREFETCH_CONCURRENCY};
+use crate::router::Router;
+use crate::rules::Rules;
+use crate::station::{Listing, Search, Wanted};
+use crate::stats;
+use crate::storage::{Location, Storage, StorageDestination, StorageError};
+use crate::trading::{Closed, Confirmation, Offer, Received, Trade, TradeEvolutions};
+use crate::trainers::{current_trainer, TrainerError, DEFAULT_TRAINER, TRAINER};
+use crate::wild::{self, Throw, WildEncounter};
 
 // -- Util-ish stuff
-fn generate_context(request: HyperRequest, state: &Arc<RwLock<Storage>>, _path: &str) -> Ctx {
+fn generate_context(request: HyperRequest, state: &Arc<AppState>, _path: &str) -> Ctx {
     Ctx::new(request, state.clone())
 }
 
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Caller supplied ids end up in our logs and response headers, so only accept ones that are
+// reasonably sized and made of printable characters.
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// Reads `name` from the request's query string. Values are used as-is, without percent-decoding.
+fn query_param(context: &Ctx, name: &str) -> Option<String> {
+    context
+        .hyper_request
+        .as_ref()?
+        .request
+        .uri()
+        .query()?
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+
+            Some((parts.next()?, parts.next().unwrap_or("")))
+        })
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+#[middleware_fn]
+async fn request_id(context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let request_id = context
+        .hyper_request
+        .as_ref()
+        .unwrap()
+        .request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut context = match REQUEST_ID.scope(request_id.clone(), next(context)).await {
+        Ok(context) => context,
+        Err(e) => e.context,
+    };
+    context.set(REQUEST_ID_HEADER, &request_id);
+
+    Ok(context)
+}
+
+/// Routes orchestrators and tooling reach without an API key.
+const PUBLIC_PATHS: [&str; 3] = ["/healthz", "/readyz", "/openapi.json"];
+
+/// Compares every byte whatever the first difference, so response times don't give away how
+/// much of a key was right.
+fn keys_match(given: &str, key: &str) -> bool {
+    given.len() == key.len()
+        && given
+            .bytes()
+            .zip(key.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[middleware_fn]
+async fn authenticate(context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let request = &context.hyper_request.as_ref().unwrap().request;
+    if context.extra.api_keys.is_empty() || PUBLIC_PATHS.contains(&request.uri().path()) {
+        return next(context).await;
+    }
+
+    let given = request
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .to_string();
+    if !context
+        .extra
+        .api_keys
+        .iter()
+        .any(|key| keys_match(&given, key))
+    {
+        let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+        return Err(Error::unauthorized_error(default_context));
+    }
+
+    next(context).await
+}
+
+pub const TRAINER_HEADER: &str = "X-Trainer";
+
+/// Serves the request for the trainer it names, or the default trainer if it doesn't name one.
+/// Handlers reach that trainer's storage through `AppState::storage`.
+#[middleware_fn]
+async fn trainer(context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let name = match context
+        .hyper_request
+        .as_ref()
+        .unwrap()
+        .request
+        .headers()
+        .get(TRAINER_HEADER)
+    {
+        Some(value) => value.to_str().unwrap_or_default().to_string(),
+        None => DEFAULT_TRAINER.to_string(),
+    };
+    if !context.extra.trainers.read().await.contains(&name) {
+        let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+        return Err(Error {
+            context: default_context,
+            message: format!(
+                "There's no trainer called {:?}, add them with POST /trainers",
+                name
+            ),
+            status: 404,
+            cause: None,
+        });
+    }
+
+    TRAINER.scope(name, next(context)).await
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
 #[middleware_fn]
 async fn profiling(mut context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
     let start_time = Instant::now();
@@ -44,7 +195,18 @@ async fn profiling(mut context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareRes
 
     context = match next(context).await {
         Ok(context) => context,
-        Err(e) => e.context,
+        Err(e) => {
+            let mut context = e.context;
+            let body = serde_json::to_string(&ErrorResponse {
+                error: e.message,
+                request_id: current_request_id(),
+            })
+            .unwrap();
+
+            context.status(e.status);
+            context.body(&body);
+            context
+        }
     };
 
     let elapsed_time = start_time.elapsed();
@@ -62,7 +224,7 @@ async fn profiling(mut context: Ctx, next: MiddlewareNext<Ctx>) -> MiddlewareRes
 pub async fn log_storage(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
     let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     // let hyper_request = context.hyper_request.unwrap().request;
-    let storage = context.extra.read().await;
+    let storage = context.extra.storage().await;
 
     info!("Storage: {:#?}", storage);
 
@@ -71,6 +233,106 @@ pub async fn log_storage(context: Ctx, _next: MiddlewareNext<Ctx>) -> Middleware
     Ok(default_context)
 }
 
+#[derive(Serialize)]
+struct HealthzResponse {
+    status: &'static str,
+}
+#[middleware_fn]
+pub async fn healthz(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let body = serde_json::to_string(&HealthzResponse { status: "ok" }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn readyz(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let readiness = context
+        .extra
+        .health
+        .readiness(&context.extra.persistence, &context.extra.pokeapi)
+        .await;
+    if !readiness.ready {
+        default_context.status(503);
+    }
+
+    let body = serde_json::to_string(&readiness).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrefetchRequest {
+    /// PokeAPI pokemon ids, e.g. a generation's worth of species.
+    ids: Vec<u32>,
+    /// How many to fetch at once.
+    concurrency: Option<usize>,
+}
+#[derive(Serialize)]
+struct PrefetchResponse {
+    fetched: usize,
+    failed: Vec<u32>,
+}
+#[middleware_fn]
+pub async fn prefetch_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let mut request = map_try!(serde_json::from_str::<PrefetchRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    let concurrency = request.concurrency.unwrap_or(DEFAULT_PREFETCH_CONCURRENCY);
+    if !(1..=MAX_PREFETCH_CONCURRENCY).contains(&concurrency) {
+        return Err(Error::invalid_request(
+            default_context,
+            &format!(
+                "concurrency must be between 1 and {}",
+                MAX_PREFETCH_CONCURRENCY
+            ),
+        ));
+    }
+    request.ids.sort_unstable();
+    request.ids.dedup();
+
+    let failed = context
+        .extra
+        .pokeapi
+        .prefetch(&request.ids, concurrency)
+        .await;
+    if !failed.is_empty() {
+        error!("Failed to prefetch pokemon {:?} from PokeAPI", failed);
+    }
+
+    let body = serde_json::to_string(&PrefetchResponse {
+        fetched: request.ids.len() - failed.len(),
+        failed,
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn openapi_spec(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let body = serde_json::to_string(&openapi::spec()).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
 // -- Actual middleware
 #[derive(Serialize)]
 struct CreateBoxResponse {
@@ -80,7 +342,7 @@ struct CreateBoxResponse {
 pub async fn create_box(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
     let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     // let hyper_request = context.hyper_request.unwrap().request;
-    let mut storage = context.extra.write().await;
+    let mut storage = context.extra.storage_mut().await;
 
     let box_id = (*storage).add_box().unwrap();
 
@@ -99,7 +361,7 @@ struct GetBoxResponse<'a> {
 pub async fn get_box(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
     let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     // let hyper_request = context.hyper_request.unwrap().request;
-    let storage = context.extra.read().await;
+    let storage = context.extra.storage().await;
 
     let id = map_try!(match context.params.unwrap().get("id") {
         Some(val) => val.parse::<usize>(),
@@ -125,7 +387,7 @@ pub async fn get_box(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResu
 pub async fn get_party(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
     let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     // let hyper_request = context.hyper_request.unwrap().request;
-    let storage = context.extra.read().await;
+    let storage = context.extra.storage().await;
 
     let pokemon = map_try!(storage.get_party(), Err(_e) => {
         Error::not_found_error(default_context)
@@ -138,27 +400,335 @@ pub async fn get_party(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareRe
     Ok(default_context)
 }
 
+#[middleware_fn]
+pub async fn analyse_party(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let party = map_try!(storage.get_party(), Err(_e) => {
+        Error::not_found_error(default_context)
+    });
+
+    let body = serde_json::to_string(&coverage::analyse(&party, &storage.boxed_pokemon())).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValidatePartyRequest {
+    /// Ids of stored pokemon making up the proposed party.
+    pokemon: Vec<u32>,
+}
+#[derive(Serialize)]
+struct ValidatePartyResponse<'a> {
+    legal: bool,
+    violations: Vec<String>,
+    rules: &'a Rules,
+}
+#[middleware_fn]
+pub async fn validate_party(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+    let storage = context.extra.storage().await;
+
+    let request = map_try!(serde_json::from_str::<ValidatePartyRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    if request.pokemon.len() > storage.max_party_size() {
+        return Err(Error::invalid_request(
+            default_context,
+            &format!("A party holds at most {} pokemon", storage.max_party_size()),
+        ));
+    }
+    let mut ids = request.pokemon.clone();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() != request.pokemon.len() {
+        return Err(Error::invalid_request(
+            default_context,
+            "A pokemon can only be in the party once",
+        ));
+    }
+
+    let violations = map_try!(storage.validate_party(&request.pokemon), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&ValidatePartyResponse {
+        legal: violations.is_empty(),
+        violations: violations
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect(),
+        rules: storage.rules(),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BattleRequest {
+    /// Ids of stored pokemon in the order they're sent out, the party if left out.
+    player: Option<Vec<u32>>,
+    opponent: Vec<u32>,
+    /// Picked at random if left out, and returned so the battle can be replayed.
+    seed: Option<u64>,
+}
+#[middleware_fn]
+pub async fn simulate_battle(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<BattleRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    // Each side with the names and PP of its pokemon's moves, fetched below.
+    let mut sides = vec![];
+    {
+        let storage = context.extra.storage().await;
+        let player = match request.player {
+            Some(player) => player,
+            None => map_try!(storage.get_party(), Err(e) => {
+                Error::storage_error(default_context, e)
+            })
+            .iter()
+            .map(|pokemon| pokemon.id())
+            .collect(),
+        };
+
+        let mut ids = vec![];
+        for side in [&player, &request.opponent].iter() {
+            if side.is_empty() || side.len() > storage.max_party_size() {
+                return Err(Error::invalid_request(
+                    default_context,
+                    &format!(
+                        "Each side needs between 1 and {} pokemon",
+                        storage.max_party_size()
+                    ),
+                ));
+            }
+            ids.extend(side.iter().copied());
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() != player.len() + request.opponent.len() {
+            return Err(Error::invalid_request(
+                default_context,
+                "A pokemon can only battle once",
+            ));
+        }
+
+        for side in [player, request.opponent].iter() {
+            let mut combatants = vec![];
+            for id in side {
+                let pokemon = map_try!(storage.get_pokemon(*id), Err(e) => {
+                    Error::storage_error(default_context, e)
+                });
+
+                combatants.push((
+                    battle::Combatant::new(pokemon),
+                    pokemon.instance.moves.clone(),
+                ));
+            }
+            sides.push(combatants);
+        }
+    }
+
+    let mut names = sides
+        .iter()
+        .flatten()
+        .flat_map(|(_, moves)| moves.iter().map(|slot| slot.name.clone()))
+        .collect::<Vec<String>>();
+    names.sort();
+    names.dedup();
+    let mut moves = std::collections::HashMap::new();
+    for name in names {
+        let move_ = map_try!(context.extra.pokeapi.get_battle_move(&name).await, Err(e) => {
+            error!("Failed to fetch move {} from PokeAPI: {}", name, e);
+            Error::generic_error(default_context)
+        });
+
+        moves.insert(name, move_);
+    }
+
+    let mut sides = sides.into_iter().map(|combatants| {
+        combatants
+            .into_iter()
+            .map(|(mut combatant, slots)| {
+                combatant.moves = slots
+                    .iter()
+                    .map(|slot| battle::BattleMove {
+                        pp: slot.max_pp,
+                        ..moves[&slot.name].clone()
+                    })
+                    .collect();
+
+                combatant
+            })
+            .collect::<Vec<battle::Combatant>>()
+    });
+    let player = sides.next().unwrap();
+    let opponent = sides.next().unwrap();
+
+    let battle = battle::simulate(player, opponent, request.seed.unwrap_or_else(rand::random));
+
+    let body = serde_json::to_string(&battle).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
 #[derive(Deserialize)]
+#[allow(non_snake_case)]
 struct MovePokemonRequest {
-    pokeAPI_id: u32,
+    /// Moves a pokemon that has already been caught.
+    id: Option<u32>,
+    /// Catches a new pokemon of this species.
+    pokeAPI_id: Option<u32>,
+    /// Catches a new pokemon by species or form name instead, see `requested_pokemon`.
+    name: Option<String>,
+    /// Everything else describes the pokemon being caught, see `InstanceChanges`.
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
 }
 #[derive(Serialize)]
 struct MovePokemonResponse<'a> {
     pokemon: &'a Pokemon,
 }
+#[derive(Serialize)]
+struct StorePokemonResponse<'a> {
+    pokemon: &'a Pokemon,
+    /// Where it ended up, which for `StorageDestination::Auto` is up to storage.
+    location: Location,
+}
+impl<'a> StorePokemonResponse<'a> {
+    fn of(storage: &'a Storage, pokemon_id: u32) -> Result<Self, StorageError> {
+        Ok(StorePokemonResponse {
+            pokemon: storage.get_pokemon(pokemon_id)?,
+            location: storage.location(pokemon_id)?,
+        })
+    }
+}
+/// Works out which pokemon a request means, whether it gave a `pokeAPI_id` or a `name` such as
+/// `Kabutops` or `giratina-origin`. Misspelled names get suggestions for what was meant.
+async fn requested_pokemon(
+    state: &Arc<AppState>,
+    pokeapi_id: Option<u32>,
+    name: Option<&str>,
+) -> Result<Option<u32>, Error<Ctx>> {
+    let error_context = || Ctx::new(HyperRequest::default(), state.clone());
+
+    match (pokeapi_id, name) {
+        (Some(_), Some(_)) => Err(Error::invalid_request(
+            error_context(),
+            "Give either a pokeAPI_id or a name, not both",
+        )),
+        (Some(id), None) => Ok(Some(id)),
+        (None, Some(name)) => {
+            let index = state.pokeapi.get_name_index().await.map_err(|e| {
+                error!("Failed to fetch pokemon names from PokeAPI: {}", e);
+                Error::generic_error(error_context())
+            })?;
+
+            index
+                .find(name)
+                .map(Some)
+                .map_err(|e| Error::unknown_name(error_context(), e))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+async fn move_or_catch_pokemon(
+    mut default_context: Ctx,
+    state: &Arc<AppState>,
+    content: &str,
+    destination: StorageDestination,
+) -> MiddlewareResult<Ctx> {
+    let request = map_try!(serde_json::from_str::<MovePokemonRequest>(content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    let species_id = requested_pokemon(state, request.pokeAPI_id, request.name.as_deref()).await?;
+
+    match (request.id, species_id) {
+        (Some(pokemon_id), None) => {
+            if !request.details.is_empty() {
+                return Err(Error::invalid_request(
+                    default_context,
+                    "Details can only be set when catching a pokemon, use PATCH /pokemon/:id to edit one",
+                ));
+            }
+
+            let mut storage = state.storage_mut().await;
+            map_try!(storage.move_pokemon(pokemon_id, destination), Err(e) => {
+                Error::storage_error(default_context, e)
+            });
+            let response = map_try!(StorePokemonResponse::of(&storage, pokemon_id), Err(e) => {
+                Error::storage_error(default_context, e)
+            });
+
+            let body = serde_json::to_string(&response).unwrap();
+
+            default_context.body(&body);
+        }
+        (None, Some(species_id)) => {
+            let mut pokemon = map_try!(state.pokeapi.get_pokemon(species_id).await, Err(e) => {
+                error!("Failed to fetch pokemon {} from PokeAPI: {}", species_id, e);
+                Error::generic_error(default_context)
+            });
+            let details = map_try!(
+                serde_json::from_value::<InstanceChanges>(serde_json::Value::Object(request.details)),
+                Err(e) => {
+                    Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+                }
+            );
+            pokemon.instance = map_try!(
+                Instance::generate(&pokemon, details, &mut rand::thread_rng()),
+                Err(e) => {
+                    Error::invalid_request(default_context, &e.to_string())
+                }
+            );
+
+            let mut storage = state.storage_mut().await;
+            let pokemon_id = map_try!(storage.add_pokemon(pokemon, destination), Err(e) => {
+                Error::storage_error(default_context, e)
+            })
+            .id();
+            let response = map_try!(StorePokemonResponse::of(&storage, pokemon_id), Err(e) => {
+                Error::storage_error(default_context, e)
+            });
+
+            let body = serde_json::to_string(&response).unwrap();
+
+            default_context.body(&body);
+        }
+        _ => {
+            return Err(Error::invalid_request(
+                default_context,
+                "Must include either an id, or a pokeAPI_id or name, in your request",
+            ));
+        }
+    }
+
+    Ok(default_context)
+}
+
 #[middleware_fn]
 pub async fn move_pokemon_to_box(
     context: Ctx,
     _next: MiddlewareNext<Ctx>,
 ) -> MiddlewareResult<Ctx> {
-    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     let (content, context) = context.get_body().await.unwrap();
-    let mut storage = context.extra.write().await;
-
-    let pokemon_id = map_try!(serde_json::from_str::<MovePokemonRequest>(&content), Err(_e) => {
-        Error::parsing_error(default_context, "Must include a pokeAPI_id in your request")
-    })
-    .pokeAPI_id;
 
     let id = map_try!(match context.params.unwrap().get("id") {
         Some(val) => val.parse::<usize>(),
@@ -169,48 +739,13 @@ pub async fn move_pokemon_to_box(
         Error::parsing_error(default_context, "Must include an id")
     });
 
-    match storage.move_pokemon(pokemon_id, StorageDestination::Box(id)) {
-        Ok(pokemon) => {
-            let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
-
-            default_context.body(&body);
-        }
-        Err(e) => match e {
-            StorageError::BoxDoesNotExist => {
-                return Err(Error::not_found_error(default_context));
-            }
-            StorageError::ContainerIsFull => {
-                return Err(Error::container_is_full(default_context));
-            }
-            StorageError::PokemonNotFound => {
-                let pokemon = map_try!(get_pokemon(pokemon_id).await, Err(_e) => {
-                    Error::generic_error(default_context)
-                });
-
-                match storage.add_pokemon(pokemon, StorageDestination::Box(id)) {
-                    Ok(pokemon) => {
-                        let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
-
-                        default_context.body(&body);
-                    }
-                    Err(e) => match e {
-                        StorageError::BoxDoesNotExist => {
-                            return Err(Error::not_found_error(default_context));
-                        }
-                        StorageError::ContainerIsFull => {
-                            return Err(Error::container_is_full(default_context));
-                        }
-                        StorageError::PokemonNotFound => {
-                            error!("Yikes, we tried to make a pokemon, inserted it, but then still couldn't find it");
-                            return Err(Error::not_found_error(default_context));
-                        }
-                    },
-                }
-            }
-        },
-    };
-
-    Ok(default_context)
+    move_or_catch_pokemon(
+        default_context,
+        &context.extra,
+        &content,
+        StorageDestination::Box(id),
+    )
+    .await
 }
 
 #[middleware_fn]
@@ -218,99 +753,2403 @@ pub async fn move_pokemon_to_party(
     context: Ctx,
     _next: MiddlewareNext<Ctx>,
 ) -> MiddlewareResult<Ctx> {
-    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
     let (content, context) = context.get_body().await.unwrap();
-    let mut storage = context.extra.write().await;
 
-    let pokemon_id = map_try!(serde_json::from_str::<MovePokemonRequest>(&content), Err(_e) => {
-        Error::parsing_error(default_context, "Must include a pokeAPI_id in your request")
-    })
-    .pokeAPI_id;
+    move_or_catch_pokemon(
+        default_context,
+        &context.extra,
+        &content,
+        StorageDestination::Party,
+    )
+    .await
+}
 
-    match storage.move_pokemon(pokemon_id, StorageDestination::Party) {
-        Ok(pokemon) => {
-            let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+#[middleware_fn]
+pub async fn store_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
 
-            default_context.body(&body);
+    move_or_catch_pokemon(
+        default_context,
+        &context.extra,
+        &content,
+        StorageDestination::Auto,
+    )
+    .await
+}
+
+#[derive(Serialize)]
+struct GetPokemonResponse<'a> {
+    pokemon: &'a Pokemon,
+    /// Actual stats at the pokemon's current level, see `stats::calculate`.
+    stats: Stats,
+}
+#[middleware_fn]
+pub async fn get_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let id = map_try!(match context.params.unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
         }
-        Err(e) => match e {
-            StorageError::BoxDoesNotExist => {
-                error!("Yikes, we tried to make a pokemon, and somehow got box doesn't exist");
-                return Err(Error::generic_error(default_context));
-            }
-            StorageError::ContainerIsFull => {
-                return Err(Error::container_is_full(default_context));
-            }
-            StorageError::PokemonNotFound => {
-                let pokemon = map_try!(get_pokemon(pokemon_id).await, Err(_e) => {
-                    Error::generic_error(default_context)
-                });
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
 
-                match storage.add_pokemon(pokemon, StorageDestination::Party) {
-                    Ok(pokemon) => {
-                        let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
-
-                        default_context.body(&body);
-                    }
-                    Err(e) => match e {
-                        StorageError::BoxDoesNotExist => {
-                            return Err(Error::not_found_error(default_context));
-                        }
-                        StorageError::ContainerIsFull => {
-                            return Err(Error::container_is_full(default_context));
-                        }
-                        StorageError::PokemonNotFound => {
-                            error!("Yikes, we tried to make a pokemon, inserted it, but then still couldn't find it");
-                            return Err(Error::not_found_error(default_context));
-                        }
-                    },
-                }
-            }
-        },
-    };
+    let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&GetPokemonResponse {
+        pokemon,
+        stats: stats::calculate(pokemon),
+    })
+    .unwrap();
+
+    default_context.body(&body);
 
     Ok(default_context)
 }
 
-pub async fn create() -> App<HyperRequest, Ctx, Arc<RwLock<Storage>>> {
-    let storage = Storage::default();
+#[middleware_fn]
+pub async fn edit_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+    let mut storage = context.extra.storage_mut().await;
 
-    let mut app = App::<HyperRequest, Ctx, Arc<RwLock<Storage>>>::create(
-        generate_context,
-        Arc::new(RwLock::new(storage)),
-    );
+    let id = map_try!(match context.params.unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
 
-    app.use_middleware("/", async_middleware!(Ctx, [profiling]));
-    app.post("/boxes", async_middleware!(Ctx, [create_box]));
-    app.get("/boxes/:id", async_middleware!(Ctx, [get_box]));
-    app.get("/parties", async_middleware!(Ctx, [get_party]));
-    app.post(
-        "/boxes/:id/pokemon",
-        async_middleware!(Ctx, [move_pokemon_to_box]),
-    );
-    app.post(
-        "/parties/pokemon",
-        async_middleware!(Ctx, [move_pokemon_to_party]),
-    );
-    app.get("/info", async_middleware!(Ctx, [log_storage]));
+    let changes = map_try!(serde_json::from_str::<InstanceChanges>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
 
-    app
-}
+    let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
 
-#[cfg(not(test))]
-pub async fn init() {
-    use dotenv::dotenv;
-    use std::env;
-    use thruster::HyperServer;
-    use thruster::ThrusterServer;
+    // Validation needs the species data alongside the instance being edited.
+    let mut instance = pokemon.instance.clone();
+    map_try!(instance.apply(pokemon, changes), Err(e) => {
+        Error::invalid_request(default_context, &e.to_string())
+    });
+
+    let pokemon = map_try!(storage.edit_pokemon(id, instance), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct LearnableMovesResponse {
+    version_group: String,
+    moves: Vec<LearnableMove>,
+}
+#[middleware_fn]
+pub async fn get_learnable_moves(
+    context: Ctx,
+    _next: MiddlewareNext<Ctx>,
+) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let version_group = match query_param(&context, "version_group") {
+        Some(version_group) => version_group,
+        None => {
+            return Err(Error::parsing_error(
+                default_context,
+                "Must include a version_group",
+            ));
+        }
+    };
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let species_id = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        pokemon.pokeAPI_id
+    };
+
+    let moves = map_try!(context.extra.pokeapi.get_learnset(species_id, &version_group).await, Err(e) => {
+        error!("Failed to fetch learnset for {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+
+    let body = serde_json::to_string(&LearnableMovesResponse {
+        version_group,
+        moves,
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TeachMoveRequest {
+    #[serde(rename = "move")]
+    move_: String,
+    version_group: String,
+    /// The known move to forget to make room, needed once a pokemon knows `moves::MAX_MOVES`.
+    replace: Option<String>,
+}
+#[middleware_fn]
+pub async fn teach_move(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let request = map_try!(serde_json::from_str::<TeachMoveRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let (species_id, form, level) = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        (
+            pokemon.pokeAPI_id,
+            pokemon.instance.form.clone(),
+            pokemon.instance.level,
+        )
+    };
+
+    let learnset = map_try!(context.extra.pokeapi.get_learnset(species_id, &request.version_group).await, Err(e) => {
+        error!("Failed to fetch learnset for {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+    map_try!(moves::check_learnable(&learnset, &request.move_, level), Err(e) => {
+        Error::invalid_request(default_context, &e.to_string())
+    });
+
+    let slot = map_try!(context.extra.pokeapi.get_move(&request.move_).await, Err(e) => {
+        error!("Failed to fetch move {} from PokeAPI: {}", request.move_, e);
+        Error::generic_error(default_context)
+    });
+
+    // The pokemon may have evolved, changed form or been edited while we were at PokeAPI.
+    let mut storage = context.extra.storage_mut().await;
+    let pokemon = map_try!(storage.teach_move(id, species_id, form.as_deref(), &learnset, slot, request.replace.as_deref()), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn forget_move(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+    let params = context.params.unwrap();
+
+    let id = map_try!(match params.get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+    let move_name = match params.get("move") {
+        Some(val) => val,
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include a move"));
+        }
+    };
+
+    let pokemon = map_try!(storage.forget_move(id, move_name), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+fn default_trigger() -> String {
+    "level-up".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EvolvePokemonRequest {
+    /// PokeAPI's evolution trigger, e.g. `level-up` or `use-item`.
+    #[serde(default = "default_trigger")]
+    trigger: String,
+    /// The species to evolve into, needed when more than one evolution is possible.
+    into: Option<String>,
+    /// The item from the bag to use for `use-item`.
+    item: Option<String>,
+}
+#[middleware_fn]
+pub async fn evolve_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let request = map_try!(serde_json::from_str::<EvolvePokemonRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    if request.trigger == "trade" {
+        return Err(Error::invalid_request(
+            default_context,
+            "pokemon evolve by trading when a trade they're in completes, see POST /trades",
+        ));
+    }
+
+    let (species_id, name, form) = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        (
+            pokemon.pokeAPI_id,
+            pokemon.species_name().to_string(),
+            pokemon.instance.form.clone(),
+        )
+    };
+
+    let evolutions = map_try!(context.extra.pokeapi.get_evolutions(species_id).await, Err(e) => {
+        error!("Failed to fetch evolutions for {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+
+    let trigger = evolution::Trigger {
+        name: &request.trigger,
+        item: request.item.as_deref(),
+        traded_for: None,
+    };
+    let (target, _) = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        map_try!(evolution::choose(&evolutions, pokemon, request.into.as_deref(), &trigger), Err(e) => {
+            Error::invalid_request(default_context, &e.to_string())
+        })
+    };
+
+    // A regional form evolves into the same region's form of the new species, where it has one.
+    let pokemon_id = match form.as_deref() {
+        Some(form) if forms::is_regional(&name, form) => {
+            let varieties = map_try!(context.extra.pokeapi.get_varieties(target.species_id).await, Err(e) => {
+                error!("Failed to fetch the forms of {} from PokeAPI: {}", target.species_id, e);
+                Error::generic_error(default_context)
+            });
+
+            forms::regional_counterpart(&name, form, &target.species, &varieties)
+                .map(|variety| variety.pokemon_id)
+                .unwrap_or(target.species_id)
+        }
+        _ => target.species_id,
+    };
+    let species = map_try!(context.extra.pokeapi.get_pokemon(pokemon_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", pokemon_id, e);
+        Error::generic_error(default_context)
+    });
+
+    let mut storage = context.extra.storage_mut().await;
+    let pokemon = map_try!(
+        storage.evolve_pokemon(
+            id,
+            species_id,
+            &evolutions,
+            request.into.as_deref(),
+            &trigger,
+            species,
+        ),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChangeFormRequest {
+    /// The form to change into, e.g. `origin` or `giratina-origin`, or null for the species'
+    /// default.
+    form: Option<String>,
+}
+#[middleware_fn]
+pub async fn change_form(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let request = map_try!(serde_json::from_str::<ChangeFormRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let (species_id, species, current) = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        (
+            pokemon.pokeAPI_id,
+            pokemon.species_name().to_string(),
+            pokemon.instance.form.clone(),
+        )
+    };
+
+    let varieties = map_try!(context.extra.pokeapi.get_varieties(species_id).await, Err(e) => {
+        error!("Failed to fetch the forms of {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+    let target = map_try!(
+        forms::find_change(&species, &varieties, current.as_deref(), request.form.as_deref()),
+        Err(e) => {
+            Error::invalid_request(default_context, &e.to_string())
+        }
+    );
+
+    let battle_only = map_try!(context.extra.pokeapi.is_battle_only(&target.name).await, Err(e) => {
+        error!("Failed to fetch form {} from PokeAPI: {}", target.name, e);
+        Error::generic_error(default_context)
+    });
+    if battle_only {
+        return Err(Error::invalid_request(
+            default_context,
+            &FormError::BattleOnly(target.name.clone()).to_string(),
+        ));
+    }
+
+    let form = map_try!(context.extra.pokeapi.get_pokemon(target.pokemon_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", target.pokemon_id, e);
+        Error::generic_error(default_context)
+    });
+
+    let mut storage = context.extra.storage_mut().await;
+    let pokemon = map_try!(storage.change_form(id, form), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct ReleasePokemonResponse {
+    pokemon: Pokemon,
+    /// The item it was holding, now back in the bag.
+    returned_item: Option<Item>,
+}
+#[middleware_fn]
+pub async fn release_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+
+    let id = map_try!(match context.params.unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let (pokemon, returned_item) = map_try!(storage.release_pokemon(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&ReleasePokemonResponse {
+        pokemon,
+        returned_item,
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct GetBagResponse<'a> {
+    bag: &'a Bag,
+}
+#[middleware_fn]
+pub async fn get_bag(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let body = serde_json::to_string(&GetBagResponse { bag: storage.bag() }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+fn one() -> u32 {
+    1
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddItemRequest {
+    item: String,
+    #[serde(default = "one")]
+    count: u32,
+}
+#[derive(Serialize)]
+struct AddItemResponse<'a> {
+    item: Item,
+    count: u32,
+    bag: &'a Bag,
+}
+#[middleware_fn]
+pub async fn add_item(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<AddItemRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    if request.count == 0 {
+        return Err(Error::invalid_request(
+            default_context,
+            "count must be at least 1",
+        ));
+    }
+
+    if request.count > MAX_ITEM_COUNT {
+        return Err(Error::invalid_request(
+            default_context,
+            &format!("count can't be more than {}", MAX_ITEM_COUNT),
+        ));
+    }
+
+    let item = map_try!(context.extra.pokeapi.get_item(&request.item).await, Err(e) => {
+        error!("Failed to fetch item {} from PokeAPI: {}", request.item, e);
+        Error::generic_error(default_context)
+    });
+    let item = match item {
+        Some(item) => item,
+        None => {
+            return Err(Error::invalid_request(
+                default_context,
+                &format!("{} isn't a known item", request.item),
+            ))
+        }
+    };
+
+    let mut storage = context.extra.storage_mut().await;
+    let count = map_try!(storage.bag_mut().add(&item, request.count), Err(e) => {
+        Error::invalid_request(default_context, &e.to_string())
+    });
+
+    let body = serde_json::to_string(&AddItemResponse {
+        item,
+        count,
+        bag: storage.bag(),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GiveItemRequest {
+    item: String,
+}
+async fn give_item(mut default_context: Ctx, context: Ctx, swap: bool) -> MiddlewareResult<Ctx> {
+    let (content, context) = context.get_body().await.unwrap();
+    let mut storage = context.extra.storage_mut().await;
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let request = map_try!(serde_json::from_str::<GiveItemRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let pokemon = map_try!(storage.give_item(id, &request.item, swap), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn give_held_item(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    give_item(default_context, context, false).await
+}
+
+#[middleware_fn]
+pub async fn swap_held_item(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    give_item(default_context, context, true).await
+}
+
+#[middleware_fn]
+pub async fn take_held_item(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+
+    let id = map_try!(match context.params.unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let pokemon = map_try!(storage.take_item(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct GenerationCompletion<'a> {
+    generation: &'a str,
+    #[serde(flatten)]
+    completion: Completion,
+}
+#[derive(Serialize)]
+struct GetPokedexResponse<'a> {
+    seen: usize,
+    caught: usize,
+    national: Completion,
+    generations: Vec<GenerationCompletion<'a>>,
+}
+#[middleware_fn]
+pub async fn get_pokedex(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let pokeapi = &context.extra.pokeapi;
+    let (national, generations) =
+        tokio::join!(pokeapi.get_pokedex("national"), pokeapi.get_generations());
+    let national = map_try!(national, Err(e) => {
+        error!("Failed to fetch the national pokedex from PokeAPI: {}", e);
+        Error::pokeapi_error(default_context)
+    });
+    let national = match national {
+        Some(national) => national,
+        None => {
+            error!("PokeAPI has no national pokedex");
+            return Err(Error::pokeapi_error(default_context));
+        }
+    };
+    let generations = map_try!(generations, Err(e) => {
+        error!("Failed to fetch generations from PokeAPI: {}", e);
+        Error::pokeapi_error(default_context)
+    });
+
+    let storage = context.extra.storage().await;
+    let pokedex = storage.pokedex();
+
+    let body = serde_json::to_string(&GetPokedexResponse {
+        seen: pokedex.seen_count(),
+        caught: pokedex.caught_count(),
+        national: pokedex::completion(pokedex, national.iter().map(|entry| entry.species_id)),
+        generations: generations
+            .iter()
+            .map(|generation| GenerationCompletion {
+                generation: &generation.name,
+                completion: pokedex::completion(pokedex, generation.species_ids.iter().copied()),
+            })
+            .collect(),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct GetDexCompletionResponse<'a> {
+    pokedex: &'a str,
+    #[serde(flatten)]
+    completion: Completion,
+    missing: Vec<&'a DexEntry>,
+}
+#[middleware_fn]
+pub async fn get_dex_completion(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let params = context.params.as_ref().unwrap();
+
+    let name = match params.get("dex") {
+        Some(val) => val,
+        None => {
+            return Err(Error::parsing_error(
+                default_context,
+                "Must include a pokedex",
+            ));
+        }
+    };
+
+    let entries = map_try!(context.extra.pokeapi.get_pokedex(name).await, Err(e) => {
+        error!("Failed to fetch pokedex {} from PokeAPI: {}", name, e);
+        Error::pokeapi_error(default_context)
+    });
+    let entries = match entries {
+        Some(entries) => entries,
+        None => return Err(Error::not_found_error(default_context)),
+    };
+
+    let storage = context.extra.storage().await;
+    let pokedex = storage.pokedex();
+
+    let body = serde_json::to_string(&GetDexCompletionResponse {
+        pokedex: name,
+        completion: pokedex::completion(pokedex, entries.iter().map(|entry| entry.species_id)),
+        missing: pokedex::missing(pokedex, &entries),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+fn yes() -> bool {
+    true
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StartNuzlockeRequest {
+    #[serde(default = "yes")]
+    dupes_clause: bool,
+}
+#[middleware_fn]
+pub async fn start_nuzlocke(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+    let mut storage = context.extra.storage_mut().await;
+
+    // An empty body starts a run with the defaults.
+    let content = if content.trim().is_empty() {
+        "{}"
+    } else {
+        &content
+    };
+    let request = map_try!(serde_json::from_str::<StartNuzlockeRequest>(content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let status = map_try!(storage.start_nuzlocke(request.dupes_clause), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&status).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_nuzlocke(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let status = map_try!(storage.nuzlocke_status(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&status).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn end_nuzlocke(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+
+    // Respond with how the run finished.
+    let status = map_try!(storage.nuzlocke_status(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+    let body = serde_json::to_string(&status).unwrap();
+
+    map_try!(storage.end_nuzlocke(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct RecordEncounterRequest {
+    /// PokeAPI's location area name, or any name the run uses for the area.
+    area: String,
+    pokeAPI_id: Option<u32>,
+    /// The species or form name, instead of `pokeAPI_id`.
+    name: Option<String>,
+    caught: bool,
+    /// Where a caught pokemon goes, the party unless a box is given.
+    #[serde(rename = "box")]
+    box_: Option<usize>,
+    /// Instance details for a caught pokemon, as when catching one normally.
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
+}
+#[derive(Serialize)]
+struct RecordEncounterResponse<'a> {
+    encounter: Encounter,
+    pokemon: Option<&'a Pokemon>,
+}
+#[middleware_fn]
+pub async fn record_encounter(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<RecordEncounterRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    if !request.caught && !request.details.is_empty() {
+        return Err(Error::invalid_request(
+            default_context,
+            "Details can only be set for a pokemon that was caught",
+        ));
+    }
+    let details = map_try!(
+        serde_json::from_value::<InstanceChanges>(serde_json::Value::Object(request.details)),
+        Err(e) => {
+            Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+        }
+    );
+
+    let species_id =
+        match requested_pokemon(&context.extra, request.pokeAPI_id, request.name.as_deref()).await?
+        {
+            Some(species_id) => species_id,
+            None => {
+                return Err(Error::invalid_request(
+                    default_context,
+                    "Must include either a pokeAPI_id or a name in your request",
+                ));
+            }
+        };
+
+    let mut pokemon = map_try!(context.extra.pokeapi.get_pokemon(species_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+    pokemon.instance = map_try!(
+        Instance::generate(&pokemon, details, &mut rand::thread_rng()),
+        Err(e) => {
+            Error::invalid_request(default_context, &e.to_string())
+        }
+    );
+
+    let destination = match request.box_ {
+        Some(i) => StorageDestination::Box(i),
+        None => StorageDestination::Party,
+    };
+
+    let mut storage = context.extra.storage_mut().await;
+    let (encounter, pokemon) = map_try!(
+        storage.record_encounter(&request.area, pokemon, request.caught, destination),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&RecordEncounterResponse { encounter, pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn faint_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+
+    let id = map_try!(match context.params.unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    let pokemon = map_try!(storage.faint_pokemon(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_day_care(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let body = serde_json::to_string(&storage.day_care()).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DepositPokemonRequest {
+    id: u32,
+}
+#[middleware_fn]
+pub async fn deposit_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<DepositPokemonRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let species_id = {
+        let storage = context.extra.storage().await;
+        let pokemon = map_try!(storage.get_pokemon(request.id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        pokemon.pokeAPI_id
+    };
+
+    let breeding = map_try!(context.extra.pokeapi.get_breeding(species_id).await, Err(e) => {
+        error!("Failed to fetch breeding data for {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+
+    let mut storage = context.extra.storage_mut().await;
+    let pokemon = map_try!(
+        storage.move_pokemon(request.id, StorageDestination::DayCare(breeding)),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct EggResponse<'a> {
+    egg: &'a Egg,
+}
+#[middleware_fn]
+pub async fn collect_egg(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut storage = context.extra.storage_mut().await;
+
+    let egg = map_try!(storage.collect_egg(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&EggResponse { egg }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct GetEggsResponse<'a> {
+    eggs: &'a [Egg],
+}
+#[middleware_fn]
+pub async fn get_eggs(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let body = serde_json::to_string(&GetEggsResponse {
+        eggs: storage.eggs(),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WalkRequest {
+    steps: u32,
+}
+#[middleware_fn]
+pub async fn walk(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+    let mut storage = context.extra.storage_mut().await;
+
+    let request = map_try!(serde_json::from_str::<WalkRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let body = serde_json::to_string(&GetEggsResponse {
+        eggs: storage.walk(request.steps),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HatchEggRequest {
+    /// Where the hatched pokemon goes, the party unless a box is given.
+    #[serde(rename = "box")]
+    box_: Option<usize>,
+}
+#[middleware_fn]
+pub async fn hatch_egg(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = map_try!(match context.params.as_ref().unwrap().get("id") {
+        Some(val) => val.parse::<u32>(),
+        None => {
+            return Err(Error::parsing_error(default_context, "Must include an id"));
+        }
+    }, Err(_e) => {
+        Error::parsing_error(default_context, "Must include an id")
+    });
+
+    // An empty body hatches it into the party.
+    let content = if content.trim().is_empty() {
+        "{}"
+    } else {
+        &content
+    };
+    let request = map_try!(serde_json::from_str::<HatchEggRequest>(content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let species_id = {
+        let storage = context.extra.storage().await;
+        let egg = map_try!(storage.get_egg(id), Err(e) => {
+            Error::storage_error(default_context, e)
+        });
+
+        egg.species_id
+    };
+
+    let mut pokemon = map_try!(context.extra.pokeapi.get_pokemon(species_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", species_id, e);
+        Error::generic_error(default_context)
+    });
+    let details = InstanceChanges {
+        level: Some(1),
+        ..InstanceChanges::default()
+    };
+    pokemon.instance = map_try!(
+        Instance::generate(&pokemon, details, &mut rand::thread_rng()),
+        Err(e) => {
+            Error::invalid_request(default_context, &e.to_string())
+        }
+    );
+
+    let destination = match request.box_ {
+        Some(i) => StorageDestination::Box(i),
+        None => StorageDestination::Party,
+    };
+    let mut storage = context.extra.storage_mut().await;
+    let pokemon = map_try!(storage.hatch_egg(id, pokemon, destination), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&MovePokemonResponse { pokemon }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WildEncounterRequest {
+    /// PokeAPI's location area name, e.g. `mt-moon-b1f`.
+    area: String,
+    /// The game version whose encounters to use, e.g. `red`.
+    version: String,
+    /// How the pokemon is found, `wild::DEFAULT_METHOD` unless given.
+    method: Option<String>,
+}
+#[derive(Serialize)]
+struct WildEncounterResponse<'a> {
+    encounter: &'a WildEncounter,
+}
+#[middleware_fn]
+pub async fn encounter_wild(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<WildEncounterRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    let WildEncounterRequest {
+        area,
+        version,
+        method,
+    } = request;
+    let method = method.unwrap_or_else(|| wild::DEFAULT_METHOD.to_string());
+
+    let slots = map_try!(context.extra.pokeapi.get_encounters(&area).await, Err(e) => {
+        error!("Failed to fetch encounters for {} from PokeAPI: {}", area, e);
+        Error::generic_error(default_context)
+    });
+    let slots = slots
+        .into_iter()
+        .filter(|slot| slot.version == version && slot.method == method)
+        .collect::<Vec<wild::EncounterSlot>>();
+    let (pokemon_id, level) = match wild::choose(&slots, &mut rand::thread_rng()) {
+        Some((slot, level)) => (slot.pokemon_id, level),
+        None => {
+            return Err(Error::invalid_request(
+                default_context,
+                &format!(
+                    "No pokemon can be found by {} in {} in {}",
+                    method, area, version
+                ),
+            ));
+        }
+    };
+
+    let mut pokemon = map_try!(context.extra.pokeapi.get_pokemon(pokemon_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", pokemon_id, e);
+        Error::generic_error(default_context)
+    });
+    let details = InstanceChanges {
+        level: Some(level),
+        ..InstanceChanges::default()
+    };
+    pokemon.instance = map_try!(
+        Instance::generate(&pokemon, details, &mut rand::thread_rng()),
+        Err(e) => {
+            Error::invalid_request(default_context, &e.to_string())
+        }
+    );
+
+    let mut storage = context.extra.storage_mut().await;
+    let encounter = map_try!(
+        storage.encounter_wild(WildEncounter {
+            area,
+            version,
+            method,
+            pokemon,
+        }),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&WildEncounterResponse { encounter }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_wild_encounter(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let storage = context.extra.storage().await;
+
+    let encounter = map_try!(storage.wild_encounter(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&WildEncounterResponse { encounter }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatchRequest {
+    /// The ball to throw, which must be in the bag.
+    ball: String,
+    /// Given to the pokemon if it's caught. Required during a nuzlocke run.
+    nickname: Option<String>,
+}
+#[derive(Serialize)]
+struct CatchResponse<'a> {
+    throw: Throw,
+    pokemon: Option<&'a Pokemon>,
+    location: Option<Location>,
+}
+#[middleware_fn]
+pub async fn catch_wild(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+    let mut storage = context.extra.storage_mut().await;
+
+    let request = map_try!(serde_json::from_str::<CatchRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let wild = map_try!(storage.wild_encounter(), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+    let mut instance = wild.pokemon.instance.clone();
+    let changes = InstanceChanges {
+        nickname: request.nickname,
+        ..InstanceChanges::default()
+    };
+    map_try!(instance.apply(&wild.pokemon, changes), Err(e) => {
+        Error::invalid_request(default_context, &e.to_string())
+    });
+
+    let (throw, pokemon) = map_try!(
+        storage.catch_wild(&request.ball, instance, &mut rand::thread_rng()),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+    let pokemon_id = pokemon.map(Pokemon::id);
+
+    let response = CatchResponse {
+        throw,
+        pokemon: pokemon_id.and_then(|id| storage.get_pokemon(id).ok()),
+        location: pokemon_id.and_then(|id| storage.location(id).ok()),
+    };
+    let body = serde_json::to_string(&response).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct TrainersResponse<'a> {
+    trainers: Vec<&'a str>,
+}
+#[middleware_fn]
+pub async fn get_trainers(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let trainers = context.extra.trainers.read().await;
+
+    let body = serde_json::to_string(&TrainersResponse {
+        trainers: trainers.names().collect(),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddTrainerRequest {
+    name: String,
+}
+#[derive(Serialize)]
+struct AddTrainerResponse<'a> {
+    name: &'a str,
+}
+#[middleware_fn]
+pub async fn add_trainer(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<AddTrainerRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let mut trainers = context.extra.trainers.write().await;
+    map_try!(trainers.add(&request.name), Err(e) => {
+        match e {
+            TrainerError::AlreadyExists(_) => Error {
+                context: default_context,
+                message: e.to_string(),
+                status: 409,
+                cause: None,
+            },
+            TrainerError::InvalidName(_) => Error::invalid_request(default_context, &e.to_string()),
+        }
+    });
+
+    let body = serde_json::to_string(&AddTrainerResponse {
+        name: &request.name,
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct TradeResponse<'a> {
+    trade: Trade<'a>,
+}
+#[derive(Serialize)]
+struct TradesResponse<'a> {
+    trades: Vec<&'a Offer>,
+}
+#[derive(Serialize)]
+struct TradeHistoryResponse<'a> {
+    trades: Vec<&'a Closed>,
+}
+
+fn id_param(context: &Ctx) -> Option<u32> {
+    context.params.as_ref()?.get("id")?.parse::<u32>().ok()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OfferTradeRequest {
+    /// The trainer the offer is for.
+    to: String,
+    /// Ids of the requesting trainer's pokemon on offer.
+    offered: Vec<u32>,
+    /// Ids of the other trainer's pokemon wanted for them.
+    requested: Vec<u32>,
+    /// How long the offer stays open, a day if not given.
+    expires_in_secs: Option<i64>,
+}
+#[middleware_fn]
+pub async fn offer_trade(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<OfferTradeRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let mut trainers = context.extra.trainers.write().await;
+    let offer = map_try!(
+        trainers.offer_trade(
+            &current_trainer(),
+            &request.to,
+            request.offered,
+            request.requested,
+            request.expires_in_secs,
+        ),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&TradeResponse {
+        trade: Trade::Open(offer),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_trades(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut trainers = context.extra.trainers.write().await;
+
+    let body = serde_json::to_string(&TradesResponse {
+        trades: trainers.open_trades(&current_trainer()),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_trade_history(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let mut trainers = context.extra.trainers.write().await;
+
+    let body = serde_json::to_string(&TradeHistoryResponse {
+        trades: trainers.trade_history(&current_trainer()),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_trade(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+
+    let mut trainers = context.extra.trainers.write().await;
+    let trade = map_try!(trainers.trade(&current_trainer(), id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&TradeResponse { trade }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+/// Looks up how every species in a trade evolves, and what into when that's by trading, so the
+/// swap needn't wait on PokeAPI.
+async fn trade_evolutions(
+    state: &Arc<AppState>,
+    species_ids: Vec<u32>,
+) -> Result<TradeEvolutions, anyhow::Error> {
+    let mut evolutions = TradeEvolutions::default();
+    for species_id in species_ids {
+        if evolutions.evolutions.contains_key(&species_id) {
+            continue;
+        }
+
+        let choices = state.pokeapi.get_evolutions(species_id).await?;
+        for choice in &choices {
+            let by_trade = choice
+                .methods
+                .iter()
+                .any(|method| method.trigger == "trade");
+            if by_trade && !evolutions.species.contains_key(&choice.species_id) {
+                let species = state.pokeapi.get_pokemon(choice.species_id).await?;
+                evolutions.species.insert(choice.species_id, species);
+            }
+        }
+        evolutions.evolutions.insert(species_id, choices);
+    }
+
+    Ok(evolutions)
+}
+
+#[middleware_fn]
+pub async fn confirm_trade(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+    let content = if content.trim().is_empty() {
+        "{}"
+    } else {
+        &content
+    };
+    let confirmation = map_try!(serde_json::from_str::<Confirmation>(content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let trainer = current_trainer();
+    let species_ids = {
+        let trainers = context.extra.trainers.read().await;
+        map_try!(trainers.trade_species(&trainer, id), Err(e) => {
+            Error::storage_error(default_context, e)
+        })
+    };
+    let evolutions = map_try!(trade_evolutions(&context.extra, species_ids).await, Err(e) => {
+        error!("Failed to fetch trade evolutions from PokeAPI: {}", e);
+        Error::pokeapi_error(default_context)
+    });
+
+    let mut trainers = context.extra.trainers.write().await;
+    let trade = map_try!(trainers.confirm_trade(&trainer, id, confirmation, &evolutions), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&TradeResponse { trade }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn cancel_trade(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+
+    let mut trainers = context.extra.trainers.write().await;
+    let closed = map_try!(trainers.cancel_trade(&current_trainer(), id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&TradeResponse {
+        trade: Trade::Closed(closed),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Serialize)]
+struct ListingResponse<'a> {
+    listing: &'a Listing,
+}
+#[derive(Serialize)]
+struct ListingsResponse<'a> {
+    listings: Vec<&'a Listing>,
+}
+#[derive(Serialize)]
+struct FulfilListingResponse {
+    received: Vec<Received>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WantedRequest {
+    pokeAPI_id: Option<u32>,
+    name: Option<String>,
+    min_level: Option<u32>,
+    max_level: Option<u32>,
+}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListPokemonRequest {
+    /// A boxed pokemon of the requesting trainer's.
+    pokemon_id: u32,
+    wanted: WantedRequest,
+}
+#[middleware_fn]
+pub async fn list_pokemon(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let request = map_try!(serde_json::from_str::<ListPokemonRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+    let wanted = request.wanted;
+    let species_id =
+        match requested_pokemon(&context.extra, wanted.pokeAPI_id, wanted.name.as_deref()).await? {
+            Some(species_id) => species_id,
+            None => {
+                return Err(Error::invalid_request(
+                    default_context,
+                    "Must include a pokeAPI_id or name for the wanted pokemon",
+                ));
+            }
+        };
+    let species = map_try!(context.extra.pokeapi.get_pokemon(species_id).await, Err(e) => {
+        error!("Failed to fetch pokemon {} from PokeAPI: {}", species_id, e);
+        Error::pokeapi_error(default_context)
+    });
+    let wanted = map_try!(Wanted::new(&species, wanted.min_level, wanted.max_level), Err(e) => {
+        Error::invalid_request(default_context, &e.to_string())
+    });
+
+    let mut trainers = context.extra.trainers.write().await;
+    let listing = map_try!(trainers.deposit(&current_trainer(), request.pokemon_id, wanted), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&ListingResponse { listing }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_listings(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+
+    let mut levels = vec![];
+    for name in ["min_level", "max_level"] {
+        levels.push(match query_param(&context, name) {
+            Some(level) => Some(map_try!(level.parse::<u32>(), Err(_) => {
+                Error::parsing_error(default_context, &format!("{} must be a number", name))
+            })),
+            None => None,
+        });
+    }
+    let search = Search {
+        species: query_param(&context, "species"),
+        wanted: query_param(&context, "wanted"),
+        min_level: levels[0],
+        max_level: levels[1],
+        trainer: query_param(&context, "trainer"),
+    };
+
+    let trainers = context.extra.trainers.read().await;
+    let body = serde_json::to_string(&ListingsResponse {
+        listings: trainers.listings(&search),
+    })
+    .unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn get_listing(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+
+    let trainers = context.extra.trainers.read().await;
+    let listing = map_try!(trainers.listing(id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&ListingResponse { listing }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FulfilListingRequest {
+    /// The requesting trainer's pokemon to trade for the listed one.
+    pokemon_id: u32,
+    /// Where the listed pokemon goes, wherever there's room if not given.
+    #[serde(rename = "box")]
+    box_: Option<usize>,
+}
+#[middleware_fn]
+pub async fn fulfil_listing(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let (content, context) = context.get_body().await.unwrap();
+
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+    let request = map_try!(serde_json::from_str::<FulfilListingRequest>(&content), Err(e) => {
+        Error::invalid_request(default_context, &format!("Invalid request body: {}", e))
+    });
+
+    let trainer = current_trainer();
+    let species_ids = {
+        let trainers = context.extra.trainers.read().await;
+        map_try!(trainers.listing_species(&trainer, id, request.pokemon_id), Err(e) => {
+            Error::storage_error(default_context, e)
+        })
+    };
+    let evolutions = map_try!(trade_evolutions(&context.extra, species_ids).await, Err(e) => {
+        error!("Failed to fetch trade evolutions from PokeAPI: {}", e);
+        Error::pokeapi_error(default_context)
+    });
+
+    let mut trainers = context.extra.trainers.write().await;
+    let received = map_try!(
+        trainers.fulfil_listing(
+            &trainer,
+            id,
+            request.pokemon_id,
+            Confirmation { box_: request.box_ },
+            &evolutions,
+        ),
+        Err(e) => {
+            Error::storage_error(default_context, e)
+        }
+    );
+
+    let body = serde_json::to_string(&FulfilListingResponse { received }).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+#[middleware_fn]
+pub async fn withdraw_listing(context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let mut default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let id = match id_param(&context) {
+        Some(id) => id,
+        None => return Err(Error::parsing_error(default_context, "Must include an id")),
+    };
+
+    let trainer = current_trainer();
+    let mut trainers = context.extra.trainers.write().await;
+    let pokemon_id = map_try!(trainers.withdraw_listing(&trainer, id), Err(e) => {
+        Error::storage_error(default_context, e)
+    })
+    .id();
+    let storage = trainers
+        .get(&trainer)
+        .expect("the trainer middleware checked they exist");
+    let response = map_try!(StorePokemonResponse::of(storage, pokemon_id), Err(e) => {
+        Error::storage_error(default_context, e)
+    });
+
+    let body = serde_json::to_string(&response).unwrap();
+
+    default_context.body(&body);
+
+    Ok(default_context)
+}
+
+fn router() -> &'static Router {
+    static ROUTER: OnceLock<Router> = OnceLock::new();
+
+    ROUTER.get_or_init(|| Router::new(routes()))
+}
+
+#[middleware_fn]
+async fn dispatch(mut context: Ctx, _next: MiddlewareNext<Ctx>) -> MiddlewareResult<Ctx> {
+    let default_context = Ctx::new(HyperRequest::default(), context.extra.clone());
+    let request = &context.hyper_request.as_ref().unwrap().request;
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+
+    match router().resolve(&method, &path) {
+        Some(matched) => {
+            // `get_body` rebuilds the context with the request's params, so set both.
+            context.hyper_request.as_mut().unwrap().params = Some(matched.params.clone());
+            context.params = Some(matched.params);
+
+            matched.middleware.run(context).await
+        }
+        None => {
+            let allowed = router().allowed_methods(&path);
+            if allowed.is_empty() {
+                return Err(Error::not_found_error(default_context));
+            }
+
+            let mut context = default_context;
+            context.set("Allow", &allowed.join(", "));
+            Err(Error {
+                context,
+                message: format!(
+                    "{} isn't allowed here, use {}",
+                    method,
+                    allowed.join(" or ")
+                ),
+                status: 405,
+                cause: None,
+            })
+        }
+    }
+}
+
+/// Every route the server exposes as (method, path, handler). `create` registers exactly these, and
+/// each one must also be described in `openapi::ROUTES`.
+pub fn routes() -> Vec<(&'static str, &'static str, MiddlewareChain<Ctx>)> {
+    vec![
+        ("POST", "/boxes", async_middleware!(Ctx, [create_box])),
+        ("GET", "/boxes/:id", async_middleware!(Ctx, [get_box])),
+        ("GET", "/parties", async_middleware!(Ctx, [get_party])),
+        (
+            "GET",
+            "/parties/analysis",
+            async_middleware!(Ctx, [analyse_party]),
+        ),
+        (
+            "POST",
+            "/parties/validate",
+            async_middleware!(Ctx, [validate_party]),
+        ),
+        (
+            "POST",
+            "/battles",
+            async_middleware!(Ctx, [simulate_battle]),
+        ),
+        (
+            "POST",
+            "/boxes/:id/pokemon",
+            async_middleware!(Ctx, [move_pokemon_to_box]),
+        ),
+        (
+            "POST",
+            "/parties/pokemon",
+            async_middleware!(Ctx, [move_pokemon_to_party]),
+        ),
+        ("POST", "/pokemon", async_middleware!(Ctx, [store_pokemon])),
+        ("GET", "/pokemon/:id", async_middleware!(Ctx, [get_pokemon])),
+        (
+            "PATCH",
+            "/pokemon/:id",
+            async_middleware!(Ctx, [edit_pokemon]),
+        ),
+        (
+            "DELETE",
+            "/pokemon/:id",
+            async_middleware!(Ctx, [release_pokemon]),
+        ),
+        (
+            "POST",
+            "/pokemon/:id/item",
+            async_middleware!(Ctx, [give_held_item]),
+        ),
+        (
+            "PUT",
+            "/pokemon/:id/item",
+            async_middleware!(Ctx, [swap_held_item]),
+        ),
+        (
+            "DELETE",
+            "/pokemon/:id/item",
+            async_middleware!(Ctx, [take_held_item]),
+        ),
+        (
+            "GET",
+            "/pokemon/:id/moves/learnable",
+            async_middleware!(Ctx, [get_learnable_moves]),
+        ),
+        (
+            "POST",
+            "/pokemon/:id/moves",
+            async_middleware!(Ctx, [teach_move]),
+        ),
+        (
+            "DELETE",
+            "/pokemon/:id/moves/:move",
+            async_middleware!(Ctx, [forget_move]),
+        ),
+        (
+            "POST",
+            "/pokemon/:id/evolve",
+            async_middleware!(Ctx, [evolve_pokemon]),
+        ),
+        (
+            "POST",
+            "/pokemon/:id/form",
+            async_middleware!(Ctx, [change_form]),
+        ),
+        ("GET", "/bag", async_middleware!(Ctx, [get_bag])),
+        ("POST", "/bag/items", async_middleware!(Ctx, [add_item])),
+        ("GET", "/pokedex", async_middleware!(Ctx, [get_pokedex])),
+        (
+            "GET",
+            "/pokedex/:dex",
+            async_middleware!(Ctx, [get_dex_completion]),
+        ),
+        (
+            "POST",
+            "/nuzlocke",
+            async_middleware!(Ctx, [start_nuzlocke]),
+        ),
+        ("GET", "/nuzlocke", async_middleware!(Ctx, [get_nuzlocke])),
+        (
+            "DELETE",
+            "/nuzlocke",
+            async_middleware!(Ctx, [end_nuzlocke]),
+        ),
+        (
+            "POST",
+            "/nuzlocke/encounters",
+            async_middleware!(Ctx, [record_encounter]),
+        ),
+        (
+            "POST",
+            "/pokemon/:id/faint",
+            async_middleware!(Ctx, [faint_pokemon]),
+        ),
+        ("GET", "/day-care", async_middleware!(Ctx, [get_day_care])),
+        (
+            "POST",
+            "/day-care/pokemon",
+            async_middleware!(Ctx, [deposit_pokemon]),
+        ),
+        (
+            "POST",
+            "/day-care/egg",
+            async_middleware!(Ctx, [collect_egg]),
+        ),
+        ("GET", "/eggs", async_middleware!(Ctx, [get_eggs])),
+        ("POST", "/eggs/walk", async_middleware!(Ctx, [walk])),
+        (
+            "POST",
+            "/eggs/:id/hatch",
+            async_middleware!(Ctx, [hatch_egg]),
+        ),
+        ("GET", "/wild", async_middleware!(Ctx, [get_wild_encounter])),
+        ("POST", "/wild", async_middleware!(Ctx, [encounter_wild])),
+        ("POST", "/wild/catch", async_middleware!(Ctx, [catch_wild])),
+        ("GET", "/trainers", async_middleware!(Ctx, [get_trainers])),
+        ("POST", "/trainers", async_middleware!(Ctx, [add_trainer])),
+        ("GET", "/trades", async_middleware!(Ctx, [get_trades])),
+        ("POST", "/trades", async_middleware!(Ctx, [offer_trade])),
+        (
+            "GET",
+            "/trades/history",
+            async_middleware!(Ctx, [get_trade_history]),
+        ),
+        ("GET", "/trades/:id", async_middleware!(Ctx, [get_trade])),
+        (
+            "DELETE",
+            "/trades/:id",
+            async_middleware!(Ctx, [cancel_trade]),
+        ),
+        (
+            "POST",
+            "/trades/:id/confirm",
+            async_middleware!(Ctx, [confirm_trade]),
+        ),
+        ("GET", "/station", async_middleware!(Ctx, [get_listings])),
+        ("POST", "/station", async_middleware!(Ctx, [list_pokemon])),
+        ("GET", "/station/:id", async_middleware!(Ctx, [get_listing])),
+        (
+            "DELETE",
+            "/station/:id",
+            async_middleware!(Ctx, [withdraw_listing]),
+        ),
+        (
+            "POST",
+            "/station/:id/fulfil",
+            async_middleware!(Ctx, [fulfil_listing]),
+        ),
+        ("GET", "/info", async_middleware!(Ctx, [log_storage])),
+        ("GET", "/healthz", async_middleware!(Ctx, [healthz])),
+        ("GET", "/readyz", async_middleware!(Ctx, [readyz])),
+        (
+            "POST",
+            "/pokeapi/prefetch",
+            async_middleware!(Ctx, [prefetch_pokemon]),
+        ),
+        (
+            "GET",
+            "/openapi.json",
+            async_middleware!(Ctx, [openapi_spec]),
+        ),
+    ]
+}
+
+pub async fn create(state: Arc<AppState>) -> App<HyperRequest, Ctx, Arc<AppState>> {
+    let mut app = App::<HyperRequest, Ctx, Arc<AppState>>::create(generate_context, state);
+
+    // Every request goes through one catch-all route per method and is routed by `dispatch`.
+    let chain = || {
+        async_middleware!(
+            Ctx,
+            [request_id, profiling, authenticate, trainer, dispatch]
+        )
+    };
+    app.get("/*", chain());
+    app.post("/*", chain());
+    app.put("/*", chain());
+    app.patch("/*", chain());
+    app.delete("/*", chain());
+
+    app
+}
+
+/// Runs the server until it's shut down. Anything that stops it starting is returned, so the
+/// process can exit with an error.
+#[cfg(not(test))]
+pub async fn init(config: Config) -> Result<(), anyhow::Error> {
+    use crate::config::PokeApiMode;
+    use crate::dataset::Dataset;
+    use crate::health::{Health, Phase};
+    use crate::persistence::Persistence;
+    use crate::pokemon_api::PokeApi;
+    use crate::server::{serve, shutdown_signal};
+    use crate::storage::Storage;
+    use crate::trainers::Trainers;
+    use anyhow::anyhow;
+    use log::warn;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use tokio::sync::RwLock;
+
+    info!(
+        "Starting server at {}:{}",
+        config.server.host, config.server.port
+    );
+
+    // Replay the last snapshot before accepting any requests, so nothing they change is lost
+    // when the restored storage takes over.
+    let persistence = Persistence::new(config.storage.snapshot_path.clone());
+    let mut trainers = match persistence.load() {
+        Ok(Some(trainers)) => {
+            info!("Restored storage from snapshot");
+            trainers
+        }
+        Ok(None) => Trainers::new(Storage::new(
+            config.storage.max_party_size,
+            config.storage.max_box_size,
+        )),
+        Err(e) => return Err(anyhow!("failed to restore storage from snapshot: {}", e)),
+    };
+    let limits = (config.storage.max_party_size, config.storage.max_box_size);
+    for (name, storage) in trainers.storages_mut() {
+        if (storage.max_party_size(), storage.max_box_size()) != limits {
+            warn!(
+                "The snapshot's party and box sizes for {} ({} and {}) differ from the configured ones, using {} and {}",
+                name,
+                storage.max_party_size(),
+                storage.max_box_size(),
+                limits.0,
+                limits.1
+            );
+        }
+        storage.set_limits(limits.0, limits.1);
+        storage.set_rules(config.rules.clone());
+        storage.set_auto_create_boxes(config.storage.auto_create_boxes);
+    }
+
+    let mut pokeapi = PokeApi::new(&config.pokeapi.base_url);
+    pokeapi.set_cache(
+        config.pokeapi.cache_size,
+        config.pokeapi.cache_ttl_secs.map(Duration::from_secs),
+    );
+    if let (PokeApiMode::Offline | PokeApiMode::Fallback, Some(path)) =
+        (config.pokeapi.mode, &config.pokeapi.dataset_path)
+    {
+        match Dataset::load(path) {
+            Ok(dataset) => {
+                info!(
+                    "Loaded {} pokemon from the offline dataset",
+                    dataset.pokemon_count()
+                );
+                pokeapi.set_dataset(config.pokeapi.mode, dataset);
+            }
+            Err(e) => return Err(anyhow!("failed to load the offline dataset: {}", e)),
+        }
+    }
+
+    let state = Arc::new(AppState {
+        trainers: RwLock::new(trainers),
+        health: Health::default(),
+        persistence,
+        pokeapi,
+        api_keys: config.auth.api_keys.clone(),
+    });
+    let app = create(state.clone()).await;
+
+    let listener = match TcpListener::bind((config.server.host.as_str(), config.server.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            return Err(anyhow!(
+                "could not bind to {}:{}: {}",
+                config.server.host,
+                config.server.port,
+                e
+            ))
+        }
+    };
+    let shutdown_state = state.clone();
+    let server = tokio::spawn(serve(
+        app,
+        listener,
+        async move { shutdown_state.health.drain_on(shutdown_signal()).await },
+        Duration::from_secs(config.server.shutdown_timeout_secs),
+    ));
+
+    state.health.set_phase(Phase::Ready);
+
+    match server.await {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => error!("Server error: {}", e),
+        Err(e) => error!("Server task failed: {}", e),
+    }
+
+    let trainers = state.trainers.read().await;
+    match state.persistence.save(&trainers) {
+        Ok(()) => info!("Shutdown complete"),
+        Err(e) => error!("Failed to write final snapshot: {}", e),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::testing;
+    use reqwest::Response;
+
+    async fn get(server: &testing::TestServer, path: &str, request_id: Option<&str>) -> Response {
+        let mut request = reqwest::Client::new().get(&server.url(path));
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+
+        request.send().await.unwrap()
+    }
+
+    /// Sends `body` as `trainer`, returning the status and the JSON that came back.
+    async fn send(
+        server: &testing::TestServer,
+        method: reqwest::Method,
+        path: &str,
+        trainer: &str,
+        body: serde_json::Value,
+    ) -> (u16, serde_json::Value) {
+        let response = reqwest::Client::new()
+            .request(method, &server.url(path))
+            .header(TRAINER_HEADER, trainer)
+            .body(body.to_string())
+            .send()
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+
+        (
+            status,
+            serde_json::from_str(&response.text().await.unwrap()).unwrap(),
+        )
+    }
+
+    fn request_id_of(response: &Response) -> String {
+        response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn responses_echo_the_request_id_or_get_a_new_one() {
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
+
+        let response = get(&server, "/healthz", Some("abc-123")).await;
+        assert_eq!(request_id_of(&response), "abc-123");
+
+        let response = get(&server, "/healthz", None).await;
+        assert!(Uuid::parse_str(&request_id_of(&response)).is_ok());
+
+        // Ids we won't put in our logs are replaced rather than echoed.
+        let response = get(&server, "/healthz", Some(&"a".repeat(129))).await;
+        assert!(Uuid::parse_str(&request_id_of(&response)).is_ok());
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn error_bodies_carry_the_request_id() {
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
+
+        let response = get(&server, "/no-such-route", Some("abc-123")).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(request_id_of(&response), "abc-123");
+        let body: serde_json::Value =
+            serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["request_id"], "abc-123");
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn the_wrong_method_is_told_which_to_use() {
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
+
+        let response = reqwest::Client::new()
+            .delete(&server.url("/parties"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 405);
+        assert_eq!(response.headers()["Allow"], "GET");
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn trainers_trade_pokemon_once_both_confirm() {
+        use reqwest::Method;
+        use serde_json::json;
+
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
+
+        let (status, _) = send(&server, Method::GET, "/parties", "misty", json!({})).await;
+        assert_eq!(status, 404);
+        let (status, _) = send(
+            &server,
+            Method::POST,
+            "/trainers",
+            DEFAULT_TRAINER,
+            json!({ "name": "misty" }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        for trainer in &[DEFAULT_TRAINER, "misty"] {
+            let (status, _) = send(
+                &server,
+                Method::POST,
+                "/pokemon",
+                trainer,
+                json!({ "pokeAPI_id": 141, "nickname": *trainer }),
+            )
+            .await;
+            assert_eq!(status, 200);
+        }
+
+        let (status, body) = send(
+            &server,
+            Method::POST,
+            "/trades",
+            DEFAULT_TRAINER,
+            json!({ "to": "misty", "offered": [1], "requested": [1] }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let confirm = format!("/trades/{}/confirm", body["trade"]["id"]);
+        let (status, _) = send(
+            &server,
+            Method::DELETE,
+            "/pokemon/1",
+            DEFAULT_TRAINER,
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, 409);
+
+        let (_, body) = send(&server, Method::POST, &confirm, DEFAULT_TRAINER, json!({})).await;
+        assert!(body["trade"]["outcome"].is_null());
+        let (status, body) = send(&server, Method::POST, &confirm, "misty", json!({})).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["trade"]["outcome"], "completed");
+
+        let (_, body) = send(&server, Method::GET, "/pokemon/2", "misty", json!({})).await;
+        assert_eq!(body["pokemon"]["nickname"], DEFAULT_TRAINER);
+        assert_eq!(body["pokemon"]["original_trainer"], DEFAULT_TRAINER);
+        let (_, body) = send(
+            &server,
+            Method::GET,
+            "/trades/history",
+            DEFAULT_TRAINER,
+            json!({}),
+        )
+        .await;
+        assert_eq!(body["trades"].as_array().unwrap().len(), 1);
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn listings_on_the_station_are_fulfilled_with_what_they_want() {
+        use reqwest::Method;
+        use serde_json::json;
+
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
+
+        let (status, _) = send(
+            &server,
+            Method::POST,
+            "/trainers",
+            DEFAULT_TRAINER,
+            json!({ "name": "misty" }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        send(&server, Method::POST, "/boxes", DEFAULT_TRAINER, json!({})).await;
+        let (status, _) = send(
+            &server,
+            Method::POST,
+            "/boxes/0/pokemon",
+            DEFAULT_TRAINER,
+            json!({ "pokeAPI_id": 141, "level": 40 }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        for level in &[20, 30] {
+            send(
+                &server,
+                Method::POST,
+                "/pokemon",
+                "misty",
+                json!({ "pokeAPI_id": 141, "level": level }),
+            )
+            .await;
+        }
+
+        let (status, body) = send(
+            &server,
+            Method::POST,
+            "/station",
+            DEFAULT_TRAINER,
+            json!({ "pokemon_id": 1, "wanted": { "name": "Kabutops", "min_level": 25 } }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(body["listing"]["wanted"]["species"], "kabutops");
+        let listing = format!("/station/{}", body["listing"]["id"]);
+        let (status, _) = send(
+            &server,
+            Method::GET,
+            "/pokemon/1",
+            DEFAULT_TRAINER,
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, 404);
+
+        let (_, body) = send(
+            &server,
+            Method::GET,
+            "/station?wanted=kabutops&min_level=35",
+            "misty",
+            json!({}),
+        )
+        .await;
+        assert_eq!(body["listings"].as_array().unwrap().len(), 1);
+        let (status, _) = send(&server, Method::DELETE, &listing, "misty", json!({})).await;
+        assert_eq!(status, 403);
+
+        let fulfil = format!("{}/fulfil", listing);
+        let (status, _) = send(
+            &server,
+            Method::POST,
+            &fulfil,
+            "misty",
+            json!({ "pokemon_id": 1 }),
+        )
+        .await;
+        assert_eq!(status, 400);
+        let (status, body) = send(
+            &server,
+            Method::POST,
+            &fulfil,
+            "misty",
+            json!({ "pokemon_id": 2 }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(body["received"].as_array().unwrap().len(), 2);
+
+        let (_, body) = send(&server, Method::GET, "/station", "misty", json!({})).await;
+        assert!(body["listings"].as_array().unwrap().is_empty());
+        let (_, body) = send(&server, Method::GET, "/pokemon/3", "misty", json!({})).await;
+        assert_eq!(body["pokemon"]["level"], 40);
+        let (_, body) = send(
+            &server,
+            Method::GET,
+            "/pokemon/2",
+            DEFAULT_TRAINER,
+            json!({}),
+        )
+        .await;
+        assert_eq!(body["pokemon"]["original_trainer"], "misty");
+        let (_, body) = send(&server, Method::GET, "/boxes/0", DEFAULT_TRAINER, json!({})).await;
+        assert_eq!(body["pokemon"][0]["id"], 2);
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn only_pokedexes_pokeapi_lacks_are_not_found() {
+        let (server, shutdown) = testing::start(Arc::new(AppState::default())).await;
 
-    let _ = dotenv();
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    info!("Starting server at {}:{}", host, port);
+        let response = get(&server, "/pokedex/national", None).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["total"], 6);
+        assert_eq!(body["missing"][0]["species"], "bulbasaur");
 
-    let app = create().await;
+        let response = get(&server, "/pokedex/johto-but-wrong", None).await;
+        assert_eq!(response.status().as_u16(), 404);
 
-    let server = HyperServer::new(app);
-    server.build(&host, port.parse::<u16>().unwrap()).await
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
 }
@@ -0,0 +1,632 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::pokemon::{Pokemon, Stats};
+use crate::stats;
+use crate::types::{parse_types, Type};
+
+/// Battles still going after this many turns end in a draw.
+pub const MAX_TURNS: u32 = 500;
+/// One in this many hits is a critical hit, as of generation VII.
+const CRITICAL_ODDS: u32 = 24;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageClass {
+    Physical,
+    Special,
+    Status,
+}
+
+/// What the battle engine needs to know about a move.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BattleMove {
+    pub name: String,
+    /// `None` for typeless moves such as struggle.
+    #[serde(rename = "type")]
+    pub type_: Option<Type>,
+    /// `None` for moves that don't deal damage directly.
+    pub power: Option<u32>,
+    /// `None` for moves that never miss.
+    pub accuracy: Option<u32>,
+    pub pp: u32,
+    pub priority: i32,
+    pub damage_class: DamageClass,
+}
+
+impl BattleMove {
+    /// Used when a pokemon has no damaging moves with PP left. It hurts the user too.
+    fn struggle() -> Self {
+        BattleMove {
+            name: "struggle".to_string(),
+            type_: None,
+            power: Some(50),
+            accuracy: None,
+            pp: 0,
+            priority: 0,
+            damage_class: DamageClass::Physical,
+        }
+    }
+
+    /// Whether the engine can simulate the move. Status moves and moves without a fixed power,
+    /// such as seismic toss, aren't.
+    fn deals_damage(&self) -> bool {
+        self.damage_class != DamageClass::Status && self.power.unwrap_or(0) > 0
+    }
+}
+
+/// A pokemon entering a battle, at full HP and PP.
+#[derive(Clone, Debug)]
+pub struct Combatant {
+    pub id: u32,
+    pub name: String,
+    pub types: Vec<Type>,
+    pub level: u32,
+    pub stats: Stats,
+    pub moves: Vec<BattleMove>,
+}
+
+impl Combatant {
+    /// A combatant for a stored pokemon, without any moves yet.
+    pub fn new(pokemon: &Pokemon) -> Self {
+        Combatant {
+            id: pokemon.id(),
+            name: pokemon
+                .instance
+                .nickname
+                .clone()
+                .unwrap_or_else(|| pokemon.name.clone()),
+            types: parse_types(&pokemon.types),
+            level: pokemon.instance.level,
+            stats: stats::calculate(pokemon),
+            moves: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Player,
+    Opponent,
+}
+
+/// Something that happened in a battle. `pokemon` is always the id of the stored pokemon.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    SendOut {
+        side: Side,
+        pokemon: u32,
+        name: String,
+        hp: u32,
+    },
+    UseMove {
+        side: Side,
+        pokemon: u32,
+        #[serde(rename = "move")]
+        move_: String,
+    },
+    Miss {
+        side: Side,
+        pokemon: u32,
+    },
+    /// The move couldn't affect the target because of its types.
+    NoEffect {
+        side: Side,
+        pokemon: u32,
+    },
+    Damage {
+        side: Side,
+        pokemon: u32,
+        amount: u32,
+        /// Left afterwards.
+        hp: u32,
+        critical: bool,
+        effectiveness: f64,
+    },
+    Recoil {
+        side: Side,
+        pokemon: u32,
+        amount: u32,
+        hp: u32,
+    },
+    Faint {
+        side: Side,
+        pokemon: u32,
+    },
+}
+
+/// Turn 0 is the opening send outs.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Turn {
+    pub number: u32,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Battle {
+    /// Simulating the same teams with the same seed replays the same battle.
+    pub seed: u64,
+    /// `None` for a draw: both sides fainted on the same turn, or `MAX_TURNS` ran out.
+    pub winner: Option<Side>,
+    pub turns: Vec<Turn>,
+}
+
+/// A combatant's state during the battle.
+struct Battler {
+    combatant: Combatant,
+    hp: u32,
+    pp: Vec<u32>,
+}
+
+impl Battler {
+    fn new(combatant: Combatant) -> Self {
+        Battler {
+            hp: combatant.stats.hp,
+            pp: combatant.moves.iter().map(|move_| move_.pp).collect(),
+            combatant,
+        }
+    }
+
+    fn fainted(&self) -> bool {
+        self.hp == 0
+    }
+
+    /// Picks the usable move expected to do the most damage to `target`, preferring the first of
+    /// equals, and the index of its PP. Falls back on struggle.
+    fn choose_move(&self, target: &Battler) -> (BattleMove, Option<usize>) {
+        let mut best: Option<(usize, f64)> = None;
+
+        for (index, move_) in self.combatant.moves.iter().enumerate() {
+            if self.pp[index] == 0 || !move_.deals_damage() {
+                continue;
+            }
+
+            let expected = f64::from(move_.power.unwrap_or(0))
+                * stab(move_, &self.combatant.types)
+                * effectiveness(move_, &target.combatant.types)
+                * f64::from(move_.accuracy.unwrap_or(100))
+                / 100.0;
+            if best.is_none_or(|(_, best)| expected > best) {
+                best = Some((index, expected));
+            }
+        }
+
+        match best {
+            Some((index, _)) => (self.combatant.moves[index].clone(), Some(index)),
+            None => (BattleMove::struggle(), None),
+        }
+    }
+}
+
+fn stab(move_: &BattleMove, types: &[Type]) -> f64 {
+    match move_.type_ {
+        Some(type_) if types.contains(&type_) => 1.5,
+        _ => 1.0,
+    }
+}
+
+fn effectiveness(move_: &BattleMove, defending: &[Type]) -> f64 {
+    match move_.type_ {
+        Some(type_) => type_.effectiveness_against(defending),
+        None => 1.0,
+    }
+}
+
+/// The multipliers applied to a move's base damage.
+#[derive(Clone, Copy, Debug)]
+pub struct Modifiers {
+    pub critical: bool,
+    /// The 85-100 roll.
+    pub random: u32,
+    pub stab: f64,
+    pub effectiveness: f64,
+}
+
+/// The damage a move does, using the mainline (generation V onwards) formula. Each modifier rounds
+/// down, in the order the games apply them.
+pub fn damage(level: u32, power: u32, attack: u32, defense: u32, modifiers: Modifiers) -> u32 {
+    let base = (2 * level / 5 + 2) * power * attack / defense.max(1) / 50 + 2;
+
+    let mut damage = if modifiers.critical {
+        base * 3 / 2
+    } else {
+        base
+    };
+    damage = damage * modifiers.random / 100;
+    damage = (f64::from(damage) * modifiers.stab).floor() as u32;
+    damage = (f64::from(damage) * modifiers.effectiveness).floor() as u32;
+
+    if modifiers.effectiveness > 0.0 {
+        damage.max(1)
+    } else {
+        0
+    }
+}
+
+struct Team {
+    side: Side,
+    battlers: Vec<Battler>,
+    active: usize,
+}
+
+impl Team {
+    fn active(&self) -> &Battler {
+        &self.battlers[self.active]
+    }
+
+    fn active_mut(&mut self) -> &mut Battler {
+        &mut self.battlers[self.active]
+    }
+
+    fn defeated(&self) -> bool {
+        self.battlers.iter().all(Battler::fainted)
+    }
+
+    /// Sends out the next pokemon that can still battle, in team order.
+    fn send_out_next(&mut self, events: &mut Vec<Event>) {
+        if let Some(next) = self.battlers.iter().position(|battler| !battler.fainted()) {
+            self.active = next;
+            events.push(self.send_out());
+        }
+    }
+
+    fn send_out(&self) -> Event {
+        let battler = self.active();
+
+        Event::SendOut {
+            side: self.side,
+            pokemon: battler.combatant.id,
+            name: battler.combatant.name.clone(),
+            hp: battler.hp,
+        }
+    }
+}
+
+/// Has the active pokemon of `attacker` use `move_` on the active pokemon of `defender`.
+fn use_move(
+    attacker: &mut Team,
+    defender: &mut Team,
+    move_: &BattleMove,
+    pp_index: Option<usize>,
+    rng: &mut StdRng,
+    events: &mut Vec<Event>,
+) {
+    let side = attacker.side;
+    let user = attacker.active_mut();
+    let user_id = user.combatant.id;
+    if let Some(index) = pp_index {
+        user.pp[index] -= 1;
+    }
+    events.push(Event::UseMove {
+        side,
+        pokemon: user_id,
+        move_: move_.name.clone(),
+    });
+
+    if let Some(accuracy) = move_.accuracy {
+        if rng.gen_range(0..100) >= accuracy {
+            events.push(Event::Miss {
+                side,
+                pokemon: user_id,
+            });
+            return;
+        }
+    }
+
+    let target = defender.active();
+    let effectiveness = effectiveness(move_, &target.combatant.types);
+    if effectiveness == 0.0 {
+        events.push(Event::NoEffect {
+            side: defender.side,
+            pokemon: target.combatant.id,
+        });
+        return;
+    }
+
+    let (attack, defense) = match move_.damage_class {
+        DamageClass::Special => (
+            user.combatant.stats.special_attack,
+            target.combatant.stats.special_defense,
+        ),
+        _ => (user.combatant.stats.attack, target.combatant.stats.defense),
+    };
+    let critical = rng.gen_range(0..CRITICAL_ODDS) == 0;
+    let amount = damage(
+        user.combatant.level,
+        move_.power.unwrap_or(0),
+        attack,
+        defense,
+        Modifiers {
+            critical,
+            random: rng.gen_range(85..=100),
+            stab: stab(move_, &user.combatant.types),
+            effectiveness,
+        },
+    );
+
+    let target_side = defender.side;
+    let target = defender.active_mut();
+    let amount = amount.min(target.hp);
+    target.hp -= amount;
+    events.push(Event::Damage {
+        side: target_side,
+        pokemon: target.combatant.id,
+        amount,
+        hp: target.hp,
+        critical,
+        effectiveness,
+    });
+    if target.fainted() {
+        events.push(Event::Faint {
+            side: target_side,
+            pokemon: target.combatant.id,
+        });
+    }
+
+    if pp_index.is_none() {
+        let user = attacker.active_mut();
+        let recoil = (user.combatant.stats.hp / 4).max(1).min(user.hp);
+        user.hp -= recoil;
+        events.push(Event::Recoil {
+            side,
+            pokemon: user_id,
+            amount: recoil,
+            hp: user.hp,
+        });
+        if user.fainted() {
+            events.push(Event::Faint {
+                side,
+                pokemon: user_id,
+            });
+        }
+    }
+}
+
+/// Simulates a singles battle between two teams, each sending out its pokemon in order. Every
+/// turn both active pokemon use the move expected to hurt the other most; higher priority moves
+/// go first, then the faster pokemon, with speed ties decided at random. All randomness comes from
+/// `seed`.
+pub fn simulate(player: Vec<Combatant>, opponent: Vec<Combatant>, seed: u64) -> Battle {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let team = |side: Side, combatants: Vec<Combatant>| Team {
+        side,
+        battlers: combatants.into_iter().map(Battler::new).collect(),
+        active: 0,
+    };
+    let mut player = team(Side::Player, player);
+    let mut opponent = team(Side::Opponent, opponent);
+
+    let mut turns = vec![];
+    let mut events = vec![];
+    for team in [&mut player, &mut opponent].iter_mut() {
+        if !team.defeated() {
+            events.push(team.send_out());
+        }
+    }
+    turns.push(Turn { number: 0, events });
+
+    let mut number = 1;
+    while number <= MAX_TURNS && !player.defeated() && !opponent.defeated() {
+        let mut events = vec![];
+        let player_move = player.active().choose_move(opponent.active());
+        let opponent_move = opponent.active().choose_move(player.active());
+
+        let player_speed = player.active().combatant.stats.speed;
+        let opponent_speed = opponent.active().combatant.stats.speed;
+        let player_first = (player_move.0.priority, player_speed)
+            > (opponent_move.0.priority, opponent_speed)
+            || ((player_move.0.priority, player_speed)
+                == (opponent_move.0.priority, opponent_speed)
+                && rng.gen_bool(0.5));
+
+        let order = if player_first {
+            [(Side::Player, player_move), (Side::Opponent, opponent_move)]
+        } else {
+            [(Side::Opponent, opponent_move), (Side::Player, player_move)]
+        };
+        for (side, (move_, pp_index)) in order.iter() {
+            let (attacker, defender) = match side {
+                Side::Player => (&mut player, &mut opponent),
+                Side::Opponent => (&mut opponent, &mut player),
+            };
+            // Fainting before its turn comes round means it doesn't get to move.
+            if attacker.active().fainted() || defender.active().fainted() {
+                continue;
+            }
+
+            use_move(attacker, defender, move_, *pp_index, &mut rng, &mut events);
+        }
+
+        for team in [&mut player, &mut opponent].iter_mut() {
+            if team.active().fainted() {
+                team.send_out_next(&mut events);
+            }
+        }
+
+        turns.push(Turn { number, events });
+        number += 1;
+    }
+
+    let winner = match (player.defeated(), opponent.defeated()) {
+        (false, true) => Some(Side::Player),
+        (true, false) => Some(Side::Opponent),
+        _ => None,
+    };
+
+    Battle {
+        seed,
+        winner,
+        turns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tackle() -> BattleMove {
+        BattleMove {
+            name: "tackle".to_string(),
+            type_: Some(Type::Normal),
+            power: Some(40),
+            accuracy: Some(100),
+            pp: 35,
+            priority: 0,
+            damage_class: DamageClass::Physical,
+        }
+    }
+
+    fn combatant(id: u32, types: &[Type], speed: u32, moves: Vec<BattleMove>) -> Combatant {
+        Combatant {
+            id,
+            name: format!("pokemon-{}", id),
+            types: types.to_vec(),
+            level: 50,
+            stats: Stats {
+                hp: 120,
+                attack: 70,
+                defense: 70,
+                special_attack: 70,
+                special_defense: 70,
+                speed,
+            },
+            moves,
+        }
+    }
+
+    fn teams() -> (Vec<Combatant>, Vec<Combatant>) {
+        let water_gun = BattleMove {
+            name: "water-gun".to_string(),
+            type_: Some(Type::Water),
+            power: Some(40),
+            accuracy: Some(100),
+            pp: 25,
+            priority: 0,
+            damage_class: DamageClass::Special,
+        };
+        let rock_slide = BattleMove {
+            name: "rock-slide".to_string(),
+            type_: Some(Type::Rock),
+            power: Some(75),
+            accuracy: Some(90),
+            pp: 10,
+            priority: 0,
+            damage_class: DamageClass::Physical,
+        };
+
+        (
+            vec![
+                combatant(1, &[Type::Water], 60, vec![tackle(), water_gun]),
+                combatant(2, &[Type::Normal], 80, vec![tackle()]),
+            ],
+            vec![
+                combatant(3, &[Type::Rock], 60, vec![rock_slide, tackle()]),
+                combatant(4, &[Type::Ghost], 90, vec![]),
+            ],
+        )
+    }
+
+    #[test]
+    fn matches_the_damage_formula() {
+        let modifiers = |critical: bool, random: u32, stab: f64, effectiveness: f64| Modifiers {
+            critical,
+            random,
+            stab,
+            effectiveness,
+        };
+
+        // Bulbapedia's worked example: a level 75 Glaceon's ice fang against a Garchomp.
+        assert_eq!(
+            damage(75, 65, 123, 163, modifiers(false, 85, 1.5, 4.0)),
+            168
+        );
+        assert_eq!(
+            damage(75, 65, 123, 163, modifiers(false, 100, 1.5, 4.0)),
+            196
+        );
+        assert_eq!(
+            damage(75, 65, 123, 163, modifiers(true, 100, 1.5, 4.0)),
+            292
+        );
+        // Never less than 1, unless the target is immune.
+        assert_eq!(damage(1, 10, 5, 500, modifiers(false, 85, 1.0, 0.25)), 1);
+        assert_eq!(damage(100, 150, 500, 5, modifiers(false, 100, 1.0, 0.0)), 0);
+    }
+
+    #[test]
+    fn replays_the_same_battle_from_the_same_seed() {
+        let (player, opponent) = teams();
+        let battle = simulate(player.clone(), opponent.clone(), 42);
+
+        assert_eq!(battle, simulate(player.clone(), opponent.clone(), 42));
+        assert!(battle.winner.is_some());
+        assert!((0..20).any(|seed| simulate(player.clone(), opponent.clone(), seed) != battle));
+    }
+
+    #[test]
+    fn battles_until_a_side_has_fainted() {
+        let (player, opponent) = teams();
+        let battle = simulate(player, opponent, 7);
+        let loser = match battle.winner {
+            Some(Side::Player) => Side::Opponent,
+            _ => Side::Player,
+        };
+
+        let fainted = battle
+            .turns
+            .iter()
+            .flat_map(|turn| turn.events.iter())
+            .filter(|event| matches!(event, Event::Faint { side, .. } if *side == loser))
+            .count();
+        assert_eq!(fainted, 2);
+        // The water type picks its super effective move against the rock type.
+        assert!(battle.turns[1].events.contains(&Event::UseMove {
+            side: Side::Player,
+            pokemon: 1,
+            move_: "water-gun".to_string()
+        }));
+    }
+
+    #[test]
+    fn struggles_without_usable_moves() {
+        let player = vec![combatant(1, &[Type::Normal], 50, vec![tackle()])];
+        let opponent = vec![combatant(2, &[Type::Ghost], 60, vec![])];
+        let battle = simulate(player, opponent, 1);
+
+        // Tackle can't touch the ghost, but struggling's recoil wears it down.
+        assert_eq!(
+            battle.turns[1].events,
+            vec![
+                Event::UseMove {
+                    side: Side::Opponent,
+                    pokemon: 2,
+                    move_: "struggle".to_string()
+                },
+                match &battle.turns[1].events[1] {
+                    damage @ Event::Damage { .. } => damage.clone(),
+                    other => panic!("expected damage, got {:?}", other),
+                },
+                Event::Recoil {
+                    side: Side::Opponent,
+                    pokemon: 2,
+                    amount: 30,
+                    hp: 90
+                },
+                Event::UseMove {
+                    side: Side::Player,
+                    pokemon: 1,
+                    move_: "tackle".to_string()
+                },
+                Event::NoEffect {
+                    side: Side::Opponent,
+                    pokemon: 2
+                },
+            ]
+        );
+        assert_eq!(battle.winner, Some(Side::Player));
+        assert_eq!(battle.turns.len(), 5);
+    }
+}
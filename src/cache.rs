@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeps upstream data around, up to `capacity` entries for at most `ttl` each. Once full, the
+/// oldest entry makes room for a new one. A capacity of 0 turns caching off.
+#[derive(Debug)]
+pub struct Cache<K, V> {
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+    capacity: usize,
+    ttl: Option<Duration>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> {
+    pub fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        Cache {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+        }
+    }
+
+    fn is_fresh(&self, stored_at: Instant) -> bool {
+        self.ttl.is_none_or(|ttl| stored_at.elapsed() < ttl)
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .get(key)
+            .filter(|(stored_at, _)| self.is_fresh(*stored_at))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Whether there's nothing in the cache that's still fresh.
+    pub fn is_empty(&self) -> bool {
+        let entries = self.entries.lock().unwrap();
+
+        !entries
+            .values()
+            .any(|(stored_at, _)| self.is_fresh(*stored_at))
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            entries.retain(|_, (stored_at, _)| self.is_fresh(*stored_at));
+        }
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_oldest_entry_once_full() {
+        let cache = Cache::new(2, None);
+        cache.insert(1, "kabuto");
+        cache.insert(2, "kabutops");
+        cache.insert(1, "kabuto");
+        cache.insert(3, "omanyte");
+
+        // Storing 1 again made 2 the oldest.
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("kabuto"));
+        assert_eq!(cache.get(&3), Some("omanyte"));
+
+        let off = Cache::new(0, None);
+        off.insert(1, "kabuto");
+        assert_eq!(off.get(&1), None);
+    }
+
+    #[test]
+    fn expires_entries_after_their_ttl() {
+        let cache = Cache::new(10, Some(Duration::from_millis(20)));
+        assert!(cache.is_empty());
+        cache.insert(1, "kabuto");
+        assert_eq!(cache.get(&1), Some("kabuto"));
+        assert!(!cache.is_empty());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&1), None);
+        assert!(cache.is_empty());
+    }
+}
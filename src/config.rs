@@ -0,0 +1,617 @@
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::pokemon_api::DEFAULT_CACHE_SIZE;
+use crate::rules::Rules;
+
+#[cfg(not(test))]
+const DEFAULT_CONFIG_PATH: &str = "zed.toml";
+const LOG_LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+const MIN_API_KEY_LENGTH: usize = 16;
+
+// Every overridable setting as (TOML key, environment variable, command line flag).
+const SETTINGS: &[(&str, &str, &str)] = &[
+    ("server.host", "HOST", "--host"),
+    ("server.port", "PORT", "--port"),
+    (
+        "server.shutdown_timeout_secs",
+        "SHUTDOWN_TIMEOUT_SECS",
+        "--shutdown-timeout-secs",
+    ),
+    (
+        "storage.max_party_size",
+        "MAX_PARTY_SIZE",
+        "--max-party-size",
+    ),
+    ("storage.max_box_size", "MAX_BOX_SIZE", "--max-box-size"),
+    ("storage.snapshot_path", "SNAPSHOT_PATH", "--snapshot-path"),
+    (
+        "storage.auto_create_boxes",
+        "AUTO_CREATE_BOXES",
+        "--auto-create-boxes",
+    ),
+    ("pokeapi.base_url", "POKEAPI_BASE_URL", "--pokeapi-base-url"),
+    ("pokeapi.mode", "POKEAPI_MODE", "--pokeapi-mode"),
+    (
+        "pokeapi.dataset_path",
+        "POKEAPI_DATASET_PATH",
+        "--pokeapi-dataset-path",
+    ),
+    (
+        "pokeapi.cache_size",
+        "POKEAPI_CACHE_SIZE",
+        "--pokeapi-cache-size",
+    ),
+    (
+        "pokeapi.cache_ttl_secs",
+        "POKEAPI_CACHE_TTL_SECS",
+        "--pokeapi-cache-ttl-secs",
+    ),
+    ("auth.api_keys", "AUTH_API_KEYS", "--auth-api-keys"),
+    (
+        "rules.species_clause",
+        "RULES_SPECIES_CLAUSE",
+        "--rules-species-clause",
+    ),
+    (
+        "rules.item_clause",
+        "RULES_ITEM_CLAUSE",
+        "--rules-item-clause",
+    ),
+    ("rules.level_cap", "RULES_LEVEL_CAP", "--rules-level-cap"),
+    (
+        "rules.ban_legendaries",
+        "RULES_BAN_LEGENDARIES",
+        "--rules-ban-legendaries",
+    ),
+    (
+        "rules.banned_species",
+        "RULES_BANNED_SPECIES",
+        "--rules-banned-species",
+    ),
+    ("log.level", "LOG_LEVEL", "--log-level"),
+    ("log.format", "LOG_FORMAT", "--log-format"),
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid {
+        key: &'static str,
+        value: String,
+        reason: &'static str,
+    },
+    UnknownFlag(String),
+    MissingFlagValue(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => {
+                write!(f, "could not read config file {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                write!(f, "could not parse config file {}: {}", path.display(), e)
+            }
+            ConfigError::Invalid { key, value, reason } => {
+                write!(f, "invalid value '{}' for {}: {}", value, key, reason)
+            }
+            ConfigError::UnknownFlag(flag) => write!(f, "unknown flag '{}'", flag),
+            ConfigError::MissingFlagValue(flag) => write!(f, "flag '{}' requires a value", flag),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "0.0.0.0".to_string(),
+            port: 8080,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub max_party_size: usize,
+    pub max_box_size: usize,
+    pub snapshot_path: Option<PathBuf>,
+    /// Whether storing a pokemon wherever there's room adds a box once every box is full.
+    pub auto_create_boxes: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            max_party_size: 6,
+            max_box_size: 30,
+            snapshot_path: None,
+            auto_create_boxes: true,
+        }
+    }
+}
+
+/// Where pokemon data comes from: PokeAPI, the imported dataset, or PokeAPI with the dataset to
+/// fall back on when it can't be reached.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PokeApiMode {
+    Online,
+    Offline,
+    Fallback,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PokeApiConfig {
+    pub base_url: String,
+    pub mode: PokeApiMode,
+    /// Where `zed import` writes the dataset and where offline and fallback modes read it.
+    pub dataset_path: Option<PathBuf>,
+    /// Most entries each PokeAPI cache holds, 0 to turn caching off.
+    pub cache_size: usize,
+    /// How long cached PokeAPI data is used before it's fetched again. Unset keeps it until it's
+    /// evicted.
+    pub cache_ttl_secs: Option<u64>,
+}
+
+impl Default for PokeApiConfig {
+    fn default() -> Self {
+        PokeApiConfig {
+            base_url: "https://pokeapi.co/api/v2".to_string(),
+            mode: PokeApiMode::Online,
+            dataset_path: None,
+            cache_size: DEFAULT_CACHE_SIZE,
+            cache_ttl_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Keys clients must send as `Authorization: Bearer <key>`. Empty leaves the API open.
+    pub api_keys: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+/// Server configuration, layered from lowest to highest precedence: built-in defaults, the TOML
+/// config file, environment variables and finally command line flags.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub pokeapi: PokeApiConfig,
+    pub auth: AuthConfig,
+    pub rules: Rules,
+    pub log: LogConfig,
+}
+
+impl Config {
+    #[cfg(not(test))]
+    pub fn load<I: Iterator<Item = String>>(args: I) -> Result<Config, ConfigError> {
+        Config::load_with(
+            args,
+            |name| env::var(name).ok(),
+            Path::new(DEFAULT_CONFIG_PATH),
+        )
+    }
+
+    /// `load`, reading environment variables through `env` and the config file from
+    /// `default_path` unless another one is given.
+    fn load_with<I, E>(args: I, env: E, default_path: &Path) -> Result<Config, ConfigError>
+    where
+        I: Iterator<Item = String>,
+        E: Fn(&str) -> Option<String>,
+    {
+        let flags = parse_flags(args)?;
+
+        let explicit_path = flag_value(&flags, "--config")
+            .or_else(|| env("CONFIG_PATH"))
+            .map(PathBuf::from);
+        let mut config = match explicit_path {
+            Some(path) => Config::from_file(&path)?,
+            None if default_path.exists() => Config::from_file(default_path)?,
+            None => Config::default(),
+        };
+
+        config.apply(|key| env(setting(key).1))?;
+        config.apply(|key| flag_value(&flags, setting(key).2))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+
+        toml::from_str(&contents).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Overrides any setting that `lookup` has a value for. Keys are the dotted TOML paths.
+    fn apply<F: Fn(&str) -> Option<String>>(&mut self, lookup: F) -> Result<(), ConfigError> {
+        if let Some(host) = lookup("server.host") {
+            self.server.host = host;
+        }
+        if let Some(port) = lookup("server.port") {
+            self.server.port = parse_number("server.port", port)?;
+        }
+        if let Some(secs) = lookup("server.shutdown_timeout_secs") {
+            self.server.shutdown_timeout_secs = parse_number("server.shutdown_timeout_secs", secs)?;
+        }
+        if let Some(size) = lookup("storage.max_party_size") {
+            self.storage.max_party_size = parse_number("storage.max_party_size", size)?;
+        }
+        if let Some(size) = lookup("storage.max_box_size") {
+            self.storage.max_box_size = parse_number("storage.max_box_size", size)?;
+        }
+        if let Some(path) = lookup("storage.snapshot_path") {
+            self.storage.snapshot_path = Some(PathBuf::from(path));
+        }
+        if let Some(enabled) = lookup("storage.auto_create_boxes") {
+            self.storage.auto_create_boxes = parse_bool("storage.auto_create_boxes", enabled)?;
+        }
+        if let Some(url) = lookup("pokeapi.base_url") {
+            self.pokeapi.base_url = url;
+        }
+        if let Some(mode) = lookup("pokeapi.mode") {
+            self.pokeapi.mode = match mode.to_lowercase().as_str() {
+                "online" => PokeApiMode::Online,
+                "offline" => PokeApiMode::Offline,
+                "fallback" => PokeApiMode::Fallback,
+                _ => {
+                    return Err(invalid(
+                        "pokeapi.mode",
+                        &mode,
+                        "must be online, offline or fallback",
+                    ))
+                }
+            };
+        }
+        if let Some(path) = lookup("pokeapi.dataset_path") {
+            self.pokeapi.dataset_path = Some(PathBuf::from(path));
+        }
+        if let Some(size) = lookup("pokeapi.cache_size") {
+            self.pokeapi.cache_size = parse_number("pokeapi.cache_size", size)?;
+        }
+        if let Some(secs) = lookup("pokeapi.cache_ttl_secs") {
+            self.pokeapi.cache_ttl_secs = Some(parse_number("pokeapi.cache_ttl_secs", secs)?);
+        }
+        if let Some(keys) = lookup("auth.api_keys") {
+            // A comma separated list, like `rules.banned_species`.
+            self.auth.api_keys = keys
+                .split(',')
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect();
+        }
+        if let Some(enabled) = lookup("rules.species_clause") {
+            self.rules.species_clause = parse_bool("rules.species_clause", enabled)?;
+        }
+        if let Some(enabled) = lookup("rules.item_clause") {
+            self.rules.item_clause = parse_bool("rules.item_clause", enabled)?;
+        }
+        if let Some(cap) = lookup("rules.level_cap") {
+            self.rules.level_cap = Some(parse_number("rules.level_cap", cap)?);
+        }
+        if let Some(enabled) = lookup("rules.ban_legendaries") {
+            self.rules.ban_legendaries = parse_bool("rules.ban_legendaries", enabled)?;
+        }
+        if let Some(species) = lookup("rules.banned_species") {
+            // A comma separated list, e.g. `mewtwo,mew`.
+            self.rules.banned_species = species
+                .split(',')
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(level) = lookup("log.level") {
+            self.log.level = level;
+        }
+        if let Some(format) = lookup("log.format") {
+            self.log.format = match format.to_lowercase().as_str() {
+                "text" => LogFormat::Text,
+                "json" => LogFormat::Json,
+                _ => return Err(invalid("log.format", &format, "must be text or json")),
+            };
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.host.is_empty() {
+            return Err(invalid(
+                "server.host",
+                &self.server.host,
+                "must not be empty",
+            ));
+        }
+        if self.storage.max_party_size == 0 {
+            return Err(invalid(
+                "storage.max_party_size",
+                &self.storage.max_party_size,
+                "must be at least 1",
+            ));
+        }
+        if self.storage.max_box_size == 0 {
+            return Err(invalid(
+                "storage.max_box_size",
+                &self.storage.max_box_size,
+                "must be at least 1",
+            ));
+        }
+        if !self.pokeapi.base_url.starts_with("http://")
+            && !self.pokeapi.base_url.starts_with("https://")
+        {
+            return Err(invalid(
+                "pokeapi.base_url",
+                &self.pokeapi.base_url,
+                "must be an http:// or https:// URL",
+            ));
+        }
+        if self.pokeapi.mode != PokeApiMode::Online && self.pokeapi.dataset_path.is_none() {
+            return Err(invalid(
+                "pokeapi.mode",
+                &format!("{:?}", self.pokeapi.mode).to_lowercase(),
+                "needs pokeapi.dataset_path to be set",
+            ));
+        }
+        if self.pokeapi.cache_ttl_secs == Some(0) {
+            return Err(invalid(
+                "pokeapi.cache_ttl_secs",
+                &0,
+                "must be at least 1, or unset to keep entries until evicted",
+            ));
+        }
+        for (i, key) in self.auth.api_keys.iter().enumerate() {
+            // Keys end up compared against headers, so don't echo them back in the error.
+            if key.len() < MIN_API_KEY_LENGTH {
+                return Err(invalid(
+                    "auth.api_keys",
+                    &format!("key {}", i + 1),
+                    "must be at least 16 characters long",
+                ));
+            }
+            if !key.chars().all(|c| c.is_ascii_graphic()) {
+                return Err(invalid(
+                    "auth.api_keys",
+                    &format!("key {}", i + 1),
+                    "must be printable ASCII without spaces",
+                ));
+            }
+            if self.auth.api_keys[..i].contains(key) {
+                return Err(invalid(
+                    "auth.api_keys",
+                    &format!("key {}", i + 1),
+                    "must not repeat another key",
+                ));
+            }
+        }
+        if let Some(cap) = self.rules.level_cap {
+            if cap == 0 || cap > 100 {
+                return Err(invalid(
+                    "rules.level_cap",
+                    &cap,
+                    "must be between 1 and 100",
+                ));
+            }
+        }
+        if !LOG_LEVELS.contains(&self.log.level.to_lowercase().as_str()) {
+            return Err(invalid(
+                "log.level",
+                &self.log.level,
+                "must be one of off, error, warn, info, debug or trace",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid<V: fmt::Display>(key: &'static str, value: &V, reason: &'static str) -> ConfigError {
+    ConfigError::Invalid {
+        key,
+        value: value.to_string(),
+        reason,
+    }
+}
+
+fn parse_number<N: std::str::FromStr>(key: &'static str, value: String) -> Result<N, ConfigError> {
+    value
+        .parse::<N>()
+        .map_err(|_| invalid(key, &value, "must be a whole number in range"))
+}
+
+fn parse_bool(key: &'static str, value: String) -> Result<bool, ConfigError> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" => Ok(true),
+        "false" | "0" | "no" => Ok(false),
+        _ => Err(invalid(key, &value, "must be true or false")),
+    }
+}
+
+fn setting(key: &str) -> &'static (&'static str, &'static str, &'static str) {
+    SETTINGS
+        .iter()
+        .find(|(name, _, _)| *name == key)
+        .expect("every config key is listed in SETTINGS")
+}
+
+fn parse_flags<I: Iterator<Item = String>>(
+    mut args: I,
+) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut flags = vec![];
+
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.find('=') {
+            Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+            None => (arg.clone(), None),
+        };
+
+        if flag != "--config" && !SETTINGS.iter().any(|(_, _, known)| *known == flag) {
+            return Err(ConfigError::UnknownFlag(arg));
+        }
+
+        let value = match value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(ConfigError::MissingFlagValue(flag)),
+        };
+        flags.push((flag, value));
+    }
+
+    Ok(flags)
+}
+
+fn flag_value(flags: &[(String, String)], flag: &str) -> Option<String> {
+    flags
+        .iter()
+        .rev()
+        .find(|(name, _)| name == flag)
+        .map(|(_, value)| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>()
+            .into_iter()
+    }
+
+    /// Somewhere there's no config file, so a `zed.toml` in the checkout doesn't affect tests.
+    fn no_config_file() -> PathBuf {
+        env::temp_dir().join(format!("zed-no-config-{}.toml", std::process::id()))
+    }
+
+    fn load(flags: &[&str]) -> Result<Config, ConfigError> {
+        Config::load_with(args(flags), |_| None, &no_config_file())
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let path = env::temp_dir().join(format!("zed-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[server]\nhost = \"127.0.0.1\"\nport = 1000\n\n[storage]\nmax_box_size = 10\n",
+        )
+        .unwrap();
+
+        let config = Config::load_with(
+            args(&["--config", path.to_str().unwrap(), "--port=3000"]),
+            |name| match name {
+                "PORT" => Some("2000".to_string()),
+                "MAX_BOX_SIZE" => Some("20".to_string()),
+                _ => None,
+            },
+            &no_config_file(),
+        );
+        fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.storage.max_box_size, 20);
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(config.storage.max_party_size, 6);
+    }
+
+    #[test]
+    fn parses_flags_with_or_without_equals() {
+        assert_eq!(
+            parse_flags(args(&["--port=8081", "--host", "localhost"])).unwrap(),
+            vec![
+                ("--port".to_string(), "8081".to_string()),
+                ("--host".to_string(), "localhost".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_flags(args(&["--colour", "red"]))
+                .unwrap_err()
+                .to_string(),
+            "unknown flag '--colour'"
+        );
+        assert_eq!(
+            parse_flags(args(&["--port"])).unwrap_err().to_string(),
+            "flag '--port' requires a value"
+        );
+    }
+
+    #[test]
+    fn invalid_values_name_the_setting() {
+        let error = |flags: &[&str]| load(flags).unwrap_err().to_string();
+
+        assert_eq!(
+            error(&["--port", "eighty"]),
+            "invalid value 'eighty' for server.port: must be a whole number in range"
+        );
+        assert_eq!(
+            error(&["--max-party-size", "0"]),
+            "invalid value '0' for storage.max_party_size: must be at least 1"
+        );
+        assert_eq!(
+            error(&["--pokeapi-base-url", "ftp://pokeapi.co"]),
+            "invalid value 'ftp://pokeapi.co' for pokeapi.base_url: must be an http:// or https:// URL"
+        );
+        assert_eq!(
+            error(&["--pokeapi-mode", "offline"]),
+            "invalid value 'offline' for pokeapi.mode: needs pokeapi.dataset_path to be set"
+        );
+        assert_eq!(
+            error(&["--rules-level-cap", "101"]),
+            "invalid value '101' for rules.level_cap: must be between 1 and 100"
+        );
+        assert_eq!(
+            error(&["--auth-api-keys", "0123456789abcdef,short"]),
+            "invalid value 'key 2' for auth.api_keys: must be at least 16 characters long"
+        );
+        assert_eq!(
+            error(&["--log-format", "xml"]),
+            "invalid value 'xml' for log.format: must be text or json"
+        );
+        assert!(load(&["--auth-api-keys", "0123456789abcdef"]).is_ok());
+    }
+}
@@ -1,7 +1,47 @@
 use std::sync::Arc;
 use thruster::context::typed_hyper_context::TypedHyperContext;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+use crate::health::Health;
+use crate::persistence::Persistence;
+use crate::pokemon_api::PokeApi;
 use crate::storage::Storage;
+use crate::trainers::{current_trainer, Trainers};
 
-pub type Ctx = TypedHyperContext<Arc<RwLock<Storage>>>;
+#[derive(Default)]
+pub struct AppState {
+    pub trainers: RwLock<Trainers>,
+    pub health: Health,
+    pub persistence: Persistence,
+    pub pokeapi: PokeApi,
+    /// Keys clients must present; empty leaves the API open.
+    pub api_keys: Vec<String>,
+}
+
+impl AppState {
+    /// The storage of the trainer being served. The `trainer` middleware has already checked they
+    /// exist, and trainers are never removed.
+    pub async fn storage(&self) -> RwLockReadGuard<'_, Storage> {
+        let trainer = current_trainer();
+
+        RwLockReadGuard::map(self.trainers.read().await, |trainers| {
+            trainers.get(&trainer).expect("trainers are never removed")
+        })
+    }
+
+    /// Like `storage`, for changing it. Expired trades let go of their pokemon first, so nothing
+    /// stays held for a trade that's over.
+    pub async fn storage_mut(&self) -> RwLockWriteGuard<'_, Storage> {
+        let trainer = current_trainer();
+        let mut trainers = self.trainers.write().await;
+        trainers.expire_trades();
+
+        RwLockWriteGuard::map(trainers, |trainers| {
+            trainers
+                .get_mut(&trainer)
+                .expect("trainers are never removed")
+        })
+    }
+}
+
+pub type Ctx = TypedHyperContext<Arc<AppState>>;
@@ -0,0 +1,239 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+
+use crate::pokemon::Pokemon;
+use crate::types::{parse_types, Type};
+
+/// How many boxed pokemon to suggest for patching a hole.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// How the party fares against moves of one type.
+#[derive(Debug, Serialize)]
+pub struct DefensiveMatchup {
+    #[serde(rename = "type")]
+    pub type_: Type,
+    pub weak: usize,
+    pub resistant: usize,
+    pub immune: usize,
+}
+
+impl DefensiveMatchup {
+    /// Members weak to the type, less those who can take it.
+    fn exposure(&self) -> isize {
+        self.weak as isize - self.resistant as isize - self.immune as isize
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Hole {
+    /// A type at least two party members are weak to.
+    Weakness {
+        #[serde(rename = "type")]
+        type_: Type,
+    },
+    /// A type nothing in the party hits super effectively.
+    Coverage {
+        #[serde(rename = "type")]
+        type_: Type,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Suggestion<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub types: &'a [String],
+    /// The damage it takes from the weakness, or deals to the uncovered type.
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Analysis<'a> {
+    /// Types at least one party member's own types hit super effectively.
+    pub covered: Vec<Type>,
+    pub uncovered: Vec<Type>,
+    pub defense: Vec<DefensiveMatchup>,
+    pub shared_weaknesses: Vec<Type>,
+    pub resistances: Vec<Type>,
+    pub immunities: Vec<Type>,
+    pub biggest_hole: Option<Hole>,
+    pub suggestions: Vec<Suggestion<'a>>,
+}
+
+/// The best multiplier any of `attacking` gets against `defending`.
+fn best_attack(attacking: &[Type], defending: Type) -> f64 {
+    attacking
+        .iter()
+        .map(|type_| type_.effectiveness(defending))
+        .fold(0.0, f64::max)
+}
+
+/// Analyses the party's type matchups, treating each member's own types as the moves it has. The
+/// biggest hole is the shared weakness the fewest members can cover for, or failing that the
+/// first type the party can't hit hard; boxed pokemon that would patch it are suggested.
+pub fn analyse<'a>(party: &[&Pokemon], boxed: &[&'a Pokemon]) -> Analysis<'a> {
+    let party_types = party
+        .iter()
+        .map(|pokemon| parse_types(&pokemon.types))
+        .collect::<Vec<Vec<Type>>>();
+    let attacking = party_types.iter().flatten().copied().collect::<Vec<Type>>();
+
+    let (covered, uncovered) = Type::ALL
+        .iter()
+        .partition::<Vec<Type>, _>(|defending| best_attack(&attacking, **defending) > 1.0);
+
+    let defense = Type::ALL
+        .iter()
+        .map(|attacking| {
+            let multipliers = party_types
+                .iter()
+                .map(|types| attacking.effectiveness_against(types))
+                .collect::<Vec<f64>>();
+
+            DefensiveMatchup {
+                type_: *attacking,
+                weak: multipliers.iter().filter(|m| **m > 1.0).count(),
+                resistant: multipliers
+                    .iter()
+                    .filter(|m| **m > 0.0 && **m < 1.0)
+                    .count(),
+                immune: multipliers.iter().filter(|m| **m == 0.0).count(),
+            }
+        })
+        .collect::<Vec<DefensiveMatchup>>();
+
+    let types_where = |condition: fn(&DefensiveMatchup) -> bool| {
+        defense
+            .iter()
+            .filter(|matchup| condition(matchup))
+            .map(|matchup| matchup.type_)
+            .collect::<Vec<Type>>()
+    };
+    let shared_weaknesses = types_where(|matchup| matchup.weak >= 2);
+    let resistances = types_where(|matchup| matchup.resistant > 0);
+    let immunities = types_where(|matchup| matchup.immune > 0);
+
+    let biggest_hole = defense
+        .iter()
+        .filter(|matchup| matchup.weak >= 2)
+        // `max_by_key` keeps the last of equals, so reverse to prefer the earliest in the chart.
+        .rev()
+        .max_by_key(|matchup| (matchup.exposure(), matchup.weak))
+        .map(|matchup| Hole::Weakness {
+            type_: matchup.type_,
+        })
+        .or_else(|| {
+            uncovered
+                .first()
+                .map(|type_| Hole::Coverage { type_: *type_ })
+        })
+        .filter(|_| !party.is_empty());
+
+    let mut suggestions = match biggest_hole {
+        Some(Hole::Weakness { type_ }) => boxed
+            .iter()
+            .map(|pokemon| {
+                (
+                    pokemon,
+                    type_.effectiveness_against(&parse_types(&pokemon.types)),
+                )
+            })
+            .filter(|(_, multiplier)| *multiplier < 1.0)
+            .collect::<Vec<_>>(),
+        Some(Hole::Coverage { type_ }) => boxed
+            .iter()
+            .map(|pokemon| (pokemon, best_attack(&parse_types(&pokemon.types), type_)))
+            .filter(|(_, multiplier)| *multiplier > 1.0)
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+    // Best first: least damage taken for a weakness, most dealt for coverage.
+    let better = |a: f64, b: f64| match biggest_hole {
+        Some(Hole::Weakness { .. }) => a.partial_cmp(&b),
+        _ => b.partial_cmp(&a),
+    };
+    suggestions.sort_by(|(a, a_multiplier), (b, b_multiplier)| {
+        better(*a_multiplier, *b_multiplier)
+            .unwrap_or(Ordering::Equal)
+            .then(a.id().cmp(&b.id()))
+    });
+
+    Analysis {
+        covered,
+        uncovered,
+        defense,
+        shared_weaknesses,
+        resistances,
+        immunities,
+        biggest_hole,
+        suggestions: suggestions
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(pokemon, multiplier)| Suggestion {
+                id: pokemon.id(),
+                name: &pokemon.name,
+                types: &pokemon.types,
+                multiplier,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pokemon(id: u32, name: &str, types: &[&str]) -> Pokemon {
+        let mut pokemon: Pokemon = serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": id,
+            "name": name,
+            "height": 1,
+            "weight": 1,
+            "base_happiness": 0,
+            "types": types
+        }))
+        .unwrap();
+        pokemon.instance.id = id;
+
+        pokemon
+    }
+
+    #[test]
+    fn suggests_boxed_pokemon_for_a_shared_weakness() {
+        let party = [
+            pokemon(1, "charmander", &["fire"]),
+            pokemon(2, "geodude", &["rock", "ground"]),
+            pokemon(3, "kabuto", &["rock", "water"]),
+        ];
+        let boxed = [
+            pokemon(4, "bulbasaur", &["grass", "poison"]),
+            pokemon(5, "pidgey", &["normal", "flying"]),
+            pokemon(6, "chikorita", &["grass"]),
+        ];
+
+        let analysis = analyse(
+            &party.iter().collect::<Vec<&Pokemon>>(),
+            &boxed.iter().collect::<Vec<&Pokemon>>(),
+        );
+
+        // Water hits two of them, but ground hits all three.
+        assert!(analysis.shared_weaknesses.contains(&Type::Water));
+        assert_eq!(
+            analysis.biggest_hole,
+            Some(Hole::Weakness {
+                type_: Type::Ground
+            })
+        );
+        assert_eq!(
+            analysis
+                .suggestions
+                .iter()
+                .map(|suggestion| suggestion.name)
+                .collect::<Vec<&str>>(),
+            vec!["pidgey", "chikorita"]
+        );
+        assert!(analysis.immunities.contains(&Type::Electric));
+        assert!(analysis.uncovered.contains(&Type::Dragon));
+    }
+}
@@ -0,0 +1,502 @@
+use anyhow::{anyhow, Error};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::instance::Instance;
+use crate::names::NameIndex;
+use crate::pokemon::{Ability, GrowthRate, Pokemon, Sprites, Stats};
+use crate::pokemon_api::{id_from_url, PokemonFromApi, PokemonSepeciesFromApi};
+
+/// What a pokemon brings of its own, e.g. `giratina-origin`'s types and stats, without what it
+/// shares with the rest of its species.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PokemonRecord {
+    pub name: String,
+    pub species_id: u32,
+    pub is_default: bool,
+    pub height: u32,
+    pub weight: u32,
+    pub base_experience: u32,
+    /// Ordered by slot.
+    pub types: Vec<String>,
+    pub base_stats: Stats,
+    /// Ordered by slot.
+    pub abilities: Vec<Ability>,
+    pub sprites: Sprites,
+}
+
+impl From<PokemonFromApi> for PokemonRecord {
+    fn from(mut pokemon: PokemonFromApi) -> Self {
+        let base_stats = pokemon.base_stats();
+        pokemon.types.sort_by_key(|type_| type_.slot);
+        pokemon.abilities.sort_by_key(|ability| ability.slot);
+
+        PokemonRecord {
+            // Alternate forms have pokemon ids of their own, which aren't species ids.
+            species_id: pokemon
+                .species
+                .as_ref()
+                .and_then(|species| id_from_url(&species.url))
+                .unwrap_or(pokemon.id),
+            name: pokemon.name,
+            is_default: pokemon.is_default,
+            height: pokemon.height,
+            weight: pokemon.weight,
+            base_experience: pokemon.base_experience.unwrap_or(0),
+            types: pokemon
+                .types
+                .into_iter()
+                .map(|type_| type_.type_.name)
+                .collect(),
+            base_stats,
+            abilities: pokemon
+                .abilities
+                .into_iter()
+                .map(|ability| Ability {
+                    name: ability.ability.name,
+                    is_hidden: ability.is_hidden,
+                    slot: ability.slot,
+                })
+                .collect(),
+            sprites: pokemon.sprites,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpeciesRecord {
+    pub name: String,
+    pub base_happiness: u32,
+    pub gender_rate: i32,
+    pub growth_rate: GrowthRate,
+    pub capture_rate: u32,
+    pub is_legendary: bool,
+    pub is_mythical: bool,
+}
+
+impl From<PokemonSepeciesFromApi> for SpeciesRecord {
+    fn from(species: PokemonSepeciesFromApi) -> Self {
+        SpeciesRecord {
+            name: species.name,
+            base_happiness: species.base_happiness,
+            gender_rate: species.gender_rate,
+            growth_rate: species.growth_rate.name,
+            capture_rate: species.capture_rate,
+            is_legendary: species.is_legendary,
+            is_mythical: species.is_mythical,
+        }
+    }
+}
+
+/// Puts a pokemon together with its species, the way `get_pokemon` hands it out.
+pub fn to_pokemon(pokemon: PokemonRecord, species: SpeciesRecord) -> Pokemon {
+    Pokemon {
+        pokeAPI_id: pokemon.species_id,
+        species: if species.name.is_empty() {
+            pokemon.name.clone()
+        } else {
+            species.name
+        },
+        name: pokemon.name.clone(),
+        height: pokemon.height,
+        weight: pokemon.weight,
+        base_happiness: species.base_happiness,
+        base_experience: pokemon.base_experience,
+        types: pokemon.types,
+        base_stats: pokemon.base_stats,
+        abilities: pokemon.abilities,
+        sprites: pokemon.sprites,
+        gender_rate: species.gender_rate,
+        growth_rate: species.growth_rate,
+        is_legendary: species.is_legendary,
+        is_mythical: species.is_mythical,
+        capture_rate: species.capture_rate,
+        instance: Instance {
+            form: if pokemon.is_default {
+                None
+            } else {
+                Some(pokemon.name)
+            },
+            ..Instance::default()
+        },
+    }
+}
+
+/// A local copy of the PokeAPI data `get_pokemon` needs, indexed by pokemon and species id, so we
+/// can run without reaching PokeAPI.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Dataset {
+    pokemon: BTreeMap<u32, PokemonRecord>,
+    species: BTreeMap<u32, SpeciesRecord>,
+}
+
+impl Dataset {
+    /// Imports a PokeAPI data dump: either the `data/v2/csv` directory of the PokeAPI repository,
+    /// or the `data/api/v2` directory of its api-data JSON dump.
+    pub fn import(dir: &Path) -> Result<Dataset, Error> {
+        if dir.join("pokemon.csv").is_file() {
+            import_csv(dir)
+        } else if dir.join("pokemon").is_dir() {
+            import_json(dir)
+        } else {
+            Err(anyhow!(
+                "{} holds neither PokeAPI's CSV files nor its api-data JSON",
+                dir.display()
+            ))
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Dataset, Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("could not read {}: {}", path.display(), e))?;
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        // Same as snapshots, so a failed import never leaves a truncated dataset behind.
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    pub fn pokemon_count(&self) -> usize {
+        self.pokemon.len()
+    }
+
+    pub fn species_count(&self) -> usize {
+        self.species.len()
+    }
+
+    /// The pokemon with PokeAPI id `id`, if it and its species were imported.
+    pub fn get_pokemon(&self, id: u32) -> Option<Pokemon> {
+        let pokemon = self.pokemon.get(&id)?;
+        let species = self.species.get(&pokemon.species_id)?;
+
+        Some(to_pokemon(pokemon.clone(), species.clone()))
+    }
+
+    /// Every species and pokemon name in the dataset, the same as PokeAPI's name lists.
+    pub fn name_index(&self) -> NameIndex {
+        NameIndex::new(
+            self.species
+                .iter()
+                .map(|(id, species)| (species.name.clone(), *id))
+                .chain(
+                    self.pokemon
+                        .iter()
+                        .map(|(id, pokemon)| (pokemon.name.clone(), *id)),
+                ),
+        )
+    }
+}
+
+/// Rows of the CSV files that only map an id to a name, e.g. `types.csv`.
+#[derive(Deserialize)]
+struct IdentifierRow {
+    id: u32,
+    identifier: String,
+}
+
+#[derive(Deserialize)]
+struct PokemonRow {
+    id: u32,
+    identifier: String,
+    species_id: u32,
+    height: u32,
+    weight: u32,
+    base_experience: Option<u32>,
+    is_default: u8,
+}
+
+#[derive(Deserialize)]
+struct SpeciesRow {
+    id: u32,
+    identifier: String,
+    gender_rate: i32,
+    capture_rate: u32,
+    base_happiness: Option<u32>,
+    growth_rate_id: u32,
+    is_legendary: u8,
+    is_mythical: u8,
+}
+
+#[derive(Deserialize)]
+struct PokemonStatRow {
+    pokemon_id: u32,
+    stat_id: u32,
+    base_stat: u32,
+}
+
+#[derive(Deserialize)]
+struct PokemonTypeRow {
+    pokemon_id: u32,
+    type_id: u32,
+    slot: u32,
+}
+
+#[derive(Deserialize)]
+struct PokemonAbilityRow {
+    pokemon_id: u32,
+    ability_id: u32,
+    is_hidden: u8,
+    slot: u32,
+}
+
+fn read_csv<T: DeserializeOwned>(dir: &Path, file: &str) -> Result<Vec<T>, Error> {
+    let path = dir.join(file);
+
+    csv::Reader::from_path(&path)
+        .and_then(|mut reader| reader.deserialize().collect())
+        .map_err(|e| anyhow!("could not read {}: {}", path.display(), e))
+}
+
+fn read_identifiers(dir: &Path, file: &str) -> Result<HashMap<u32, String>, Error> {
+    Ok(read_csv::<IdentifierRow>(dir, file)?
+        .into_iter()
+        .map(|row| (row.id, row.identifier))
+        .collect())
+}
+
+fn identifier<'a>(
+    identifiers: &'a HashMap<u32, String>,
+    file: &str,
+    id: u32,
+) -> Result<&'a str, Error> {
+    identifiers
+        .get(&id)
+        .map(String::as_str)
+        .ok_or_else(|| anyhow!("{} has no row with id {}", file, id))
+}
+
+fn import_csv(dir: &Path) -> Result<Dataset, Error> {
+    let stat_names = read_identifiers(dir, "stats.csv")?;
+    let type_names = read_identifiers(dir, "types.csv")?;
+    let ability_names = read_identifiers(dir, "abilities.csv")?;
+    let growth_rates = read_identifiers(dir, "growth_rates.csv")?;
+
+    let mut stats = HashMap::<u32, Vec<(&str, u32)>>::new();
+    for row in read_csv::<PokemonStatRow>(dir, "pokemon_stats.csv")? {
+        let name = identifier(&stat_names, "stats.csv", row.stat_id)?;
+        stats
+            .entry(row.pokemon_id)
+            .or_default()
+            .push((name, row.base_stat));
+    }
+
+    let mut types = HashMap::<u32, Vec<(u32, String)>>::new();
+    for row in read_csv::<PokemonTypeRow>(dir, "pokemon_types.csv")? {
+        let name = identifier(&type_names, "types.csv", row.type_id)?;
+        types
+            .entry(row.pokemon_id)
+            .or_default()
+            .push((row.slot, name.to_string()));
+    }
+
+    let mut abilities = HashMap::<u32, Vec<Ability>>::new();
+    for row in read_csv::<PokemonAbilityRow>(dir, "pokemon_abilities.csv")? {
+        let name = identifier(&ability_names, "abilities.csv", row.ability_id)?;
+        abilities.entry(row.pokemon_id).or_default().push(Ability {
+            name: name.to_string(),
+            is_hidden: row.is_hidden == 1,
+            slot: row.slot,
+        });
+    }
+
+    let mut dataset = Dataset::default();
+    for row in read_csv::<PokemonRow>(dir, "pokemon.csv")? {
+        let mut types = types.remove(&row.id).unwrap_or_default();
+        types.sort();
+        let mut abilities = abilities.remove(&row.id).unwrap_or_default();
+        abilities.sort_by_key(|ability| ability.slot);
+
+        dataset.pokemon.insert(
+            row.id,
+            PokemonRecord {
+                name: row.identifier,
+                species_id: row.species_id,
+                is_default: row.is_default == 1,
+                height: row.height,
+                weight: row.weight,
+                base_experience: row.base_experience.unwrap_or(0),
+                types: types.into_iter().map(|(_, name)| name).collect(),
+                base_stats: Stats::from_pokeapi(stats.remove(&row.id).unwrap_or_default()),
+                abilities,
+                // The CSV files don't have sprites.
+                sprites: Sprites::default(),
+            },
+        );
+    }
+
+    for row in read_csv::<SpeciesRow>(dir, "pokemon_species.csv")? {
+        let growth_rate = identifier(&growth_rates, "growth_rates.csv", row.growth_rate_id)?;
+
+        dataset.species.insert(
+            row.id,
+            SpeciesRecord {
+                name: row.identifier,
+                base_happiness: row.base_happiness.unwrap_or(0),
+                gender_rate: row.gender_rate,
+                growth_rate: serde_json::from_value(growth_rate.into())
+                    .map_err(|_| anyhow!("unknown growth rate {}", growth_rate))?,
+                capture_rate: row.capture_rate,
+                is_legendary: row.is_legendary == 1,
+                is_mythical: row.is_mythical == 1,
+            },
+        );
+    }
+
+    Ok(dataset)
+}
+
+/// Parses every `<id>/index.json` under `dir`, e.g. `pokemon/487/index.json`.
+fn read_json_resources<T: DeserializeOwned>(dir: &Path) -> Result<Vec<T>, Error> {
+    let mut resources = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path().join("index.json");
+        if !path.is_file() {
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        resources.push(
+            serde_json::from_str(&contents)
+                .map_err(|e| anyhow!("could not parse {}: {}", path.display(), e))?,
+        );
+    }
+
+    Ok(resources)
+}
+
+fn import_json(dir: &Path) -> Result<Dataset, Error> {
+    let mut dataset = Dataset::default();
+
+    for pokemon in read_json_resources::<PokemonFromApi>(&dir.join("pokemon"))? {
+        dataset.pokemon.insert(pokemon.id, pokemon.into());
+    }
+    for species in read_json_resources::<PokemonSepeciesFromApi>(&dir.join("pokemon-species"))? {
+        dataset.species.insert(species.id, species.into());
+    }
+
+    Ok(dataset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn dump(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zed-dataset-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn imports_the_csv_files() {
+        let dir = dump(
+            "csv",
+            &[
+                (
+                    "pokemon.csv",
+                    "id,identifier,species_id,height,weight,base_experience,order,is_default\n\
+                     487,giratina-altered,487,45,7500,340,1,1\n\
+                     10007,giratina-origin,487,69,6500,340,2,0\n",
+                ),
+                (
+                    "pokemon_species.csv",
+                    "id,identifier,gender_rate,capture_rate,base_happiness,growth_rate_id,is_legendary,is_mythical\n\
+                     487,giratina,-1,3,0,1,1,0\n",
+                ),
+                (
+                    "growth_rates.csv",
+                    "id,identifier,formula\n1,slow,\"\\frac{5x^3}{4}, roughly\"\n",
+                ),
+                ("stats.csv", "id,identifier\n1,hp\n2,attack\n"),
+                (
+                    "pokemon_stats.csv",
+                    "pokemon_id,stat_id,base_stat,effort\n10007,1,150,3\n10007,2,120,0\n",
+                ),
+                ("types.csv", "id,identifier\n8,ghost\n16,dragon\n"),
+                (
+                    "pokemon_types.csv",
+                    "pokemon_id,type_id,slot\n10007,16,2\n10007,8,1\n",
+                ),
+                ("abilities.csv", "id,identifier\n26,levitate\n"),
+                (
+                    "pokemon_abilities.csv",
+                    "pokemon_id,ability_id,is_hidden,slot\n10007,26,0,1\n",
+                ),
+            ],
+        );
+
+        let dataset = Dataset::import(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((dataset.pokemon_count(), dataset.species_count()), (2, 1));
+        let origin = dataset.get_pokemon(10007).unwrap();
+        assert_eq!(origin.pokeAPI_id, 487);
+        assert_eq!(origin.name, "giratina-origin");
+        assert_eq!(origin.species, "giratina");
+        assert_eq!(origin.instance.form.as_deref(), Some("giratina-origin"));
+        assert_eq!(origin.types, vec!["ghost", "dragon"]);
+        assert_eq!((origin.base_stats.hp, origin.base_stats.attack), (150, 120));
+        assert_eq!(origin.abilities[0].name, "levitate");
+        assert_eq!(origin.growth_rate, GrowthRate::Slow);
+        assert!(origin.is_legendary);
+        assert_eq!(dataset.get_pokemon(487).unwrap().instance.form, None);
+        assert!(dataset.get_pokemon(1).is_none());
+        assert_eq!(dataset.name_index().find("Giratina Origin"), Ok(10007));
+    }
+
+    #[test]
+    fn imports_the_api_data_json_and_round_trips() {
+        let dir = dump(
+            "json",
+            &[
+                (
+                    "pokemon/140/index.json",
+                    r#"{"id": 140, "name": "kabuto", "height": 5, "weight": 115, "base_experience": 71,
+                        "stats": [{"base_stat": 30, "stat": {"name": "hp", "url": ""}}],
+                        "types": [{"slot": 2, "type": {"name": "water", "url": ""}},
+                                  {"slot": 1, "type": {"name": "rock", "url": ""}}],
+                        "abilities": [], "sprites": {"front_default": "kabuto.png"},
+                        "species": {"name": "kabuto", "url": "https://pokeapi.co/api/v2/pokemon-species/140/"},
+                        "is_default": true}"#,
+                ),
+                ("pokemon/index.json", r#"{"results": []}"#),
+                (
+                    "pokemon-species/140/index.json",
+                    r#"{"id": 140, "name": "kabuto", "base_happiness": 50, "gender_rate": 1,
+                        "growth_rate": {"name": "medium", "url": ""}, "capture_rate": 45}"#,
+                ),
+            ],
+        );
+
+        let dataset = Dataset::import(&dir).unwrap();
+        let path = dir.join("dataset.json");
+        dataset.save(&path).unwrap();
+        let dataset = Dataset::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let kabuto = dataset.get_pokemon(140).unwrap();
+        assert_eq!(kabuto.name, "kabuto");
+        assert_eq!(kabuto.types, vec!["rock", "water"]);
+        assert_eq!(kabuto.base_stats.hp, 30);
+        assert_eq!(kabuto.capture_rate, 45);
+        assert_eq!(kabuto.sprites.front_default.as_deref(), Some("kabuto.png"));
+        assert!(Dataset::import(&std::env::temp_dir().join("zed-no-such-dump")).is_err());
+    }
+}
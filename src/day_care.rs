@@ -0,0 +1,507 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::instance::{Gender, MAX_LEVEL};
+use crate::pokemon::Pokemon;
+use crate::storage::{Container, StorageError};
+
+pub const CAPACITY: usize = 2;
+/// Experience a pokemon gains per second at the day care, about what a step is worth in the games.
+const EXPERIENCE_PER_SECOND: i64 = 1;
+/// Steps in one of an egg's hatch cycles.
+const STEPS_PER_CYCLE: u32 = 255;
+const DITTO: u32 = 132;
+/// PokeAPI's egg group for species that can't breed.
+const NO_EGGS: &str = "no-eggs";
+
+/// Where the day care gets the time from, so tests can control it.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Utc::now().naive_utc()
+    }
+}
+
+/// What breeding needs to know about a species, from PokeAPI.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Breeding {
+    pub egg_groups: Vec<String>,
+    /// The first species of its evolution chain, which its eggs hatch into.
+    pub baby_species_id: u32,
+    pub baby_species: String,
+    /// How many cycles of `STEPS_PER_CYCLE` its eggs take to hatch.
+    pub hatch_counter: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Stay {
+    pokemon_id: u32,
+    deposited_at: NaiveDateTime,
+    breeding: Breeding,
+}
+
+/// How well the two pokemon get along, which decides how often they leave an egg.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// They won't breed.
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Compatibility {
+    /// Roughly how long the games' 20%, 50% and 70% chances per egg check take to come up.
+    fn egg_interval(self) -> Option<Duration> {
+        match self {
+            Compatibility::None => None,
+            Compatibility::Low => Some(Duration::minutes(35)),
+            Compatibility::Medium => Some(Duration::minutes(14)),
+            Compatibility::High => Some(Duration::minutes(10)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DayCareError {
+    NoEgg,
+    EggNotFound,
+    /// Steps left before it hatches.
+    EggNotReady(u32),
+}
+
+impl fmt::Display for DayCareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DayCareError::NoEgg => write!(f, "the day care doesn't have an egg waiting"),
+            DayCareError::EggNotFound => write!(f, "there's no egg with that id"),
+            DayCareError::EggNotReady(steps) => {
+                write!(f, "the egg needs another {} steps to hatch", steps)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Egg {
+    pub id: u32,
+    /// What it hatches into.
+    pub species_id: u32,
+    pub species: String,
+    /// Left before it can hatch.
+    pub steps: u32,
+    pub laid: NaiveDate,
+}
+
+impl Egg {
+    pub fn walk(&mut self, steps: u32) {
+        self.steps = self.steps.saturating_sub(steps);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Lodger<'a> {
+    pub pokemon: &'a Pokemon,
+    pub deposited_at: NaiveDateTime,
+    pub experience_gained: u32,
+    /// The level it'll be once withdrawn.
+    pub level: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status<'a> {
+    pub pokemon: Vec<Lodger<'a>>,
+    pub compatibility: Compatibility,
+    pub egg_ready: bool,
+}
+
+/// Holds up to two pokemon that grow while they're left there, and leave eggs if they're
+/// compatible. Growth and eggs are worked out from how long they've been there whenever asked.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DayCare {
+    pub pokemon: Container,
+    /// In the order they were deposited.
+    stays: Vec<Stay>,
+    /// When the pair started on their next egg: when the second arrived, or the last egg was
+    /// collected.
+    pair_since: Option<NaiveDateTime>,
+}
+
+impl Default for DayCare {
+    fn default() -> Self {
+        DayCare {
+            pokemon: Container::new(CAPACITY),
+            stays: vec![],
+            pair_since: None,
+        }
+    }
+}
+
+/// The experience `pokemon` will have after `since` until `now` at the day care.
+fn grown_experience(pokemon: &Pokemon, since: NaiveDateTime, now: NaiveDateTime) -> u32 {
+    let seconds = (now - since).num_seconds().max(0);
+    let gained = (seconds * EXPERIENCE_PER_SECOND).min(i64::from(u32::MAX)) as u32;
+
+    pokemon
+        .instance
+        .experience
+        .saturating_add(gained)
+        .min(pokemon.growth_rate.experience_for_level(MAX_LEVEL))
+}
+
+/// A pokemon at the day care along with its species' breeding data.
+type Parent<'a> = (&'a Pokemon, &'a Breeding);
+
+/// How well two pokemon get along. Ditto breeds with anything that can breed, other than another
+/// ditto; otherwise they need to be male and female and share an egg group. Pokemon of the same
+/// species get along better, and ones from different trainers better still.
+pub fn compatibility(a: Parent, b: Parent) -> Compatibility {
+    let ((a, a_breeding), (b, b_breeding)) = (a, b);
+    let can_breed = |breeding: &Breeding| !breeding.egg_groups.iter().any(|group| group == NO_EGGS);
+    if !can_breed(a_breeding) || !can_breed(b_breeding) {
+        return Compatibility::None;
+    }
+
+    let dittos = [a, b]
+        .iter()
+        .filter(|pokemon| pokemon.pokeAPI_id == DITTO)
+        .count();
+    let compatible = match dittos {
+        0 => {
+            let genders = (a.instance.gender, b.instance.gender);
+            let opposite = genders == (Gender::Male, Gender::Female)
+                || genders == (Gender::Female, Gender::Male);
+
+            opposite
+                && a_breeding
+                    .egg_groups
+                    .iter()
+                    .any(|group| b_breeding.egg_groups.contains(group))
+        }
+        1 => true,
+        _ => false,
+    };
+    if !compatible {
+        return Compatibility::None;
+    }
+
+    let same_species = a.pokeAPI_id == b.pokeAPI_id;
+    let same_trainer = a.instance.original_trainer == b.instance.original_trainer;
+    match (same_species, same_trainer) {
+        (true, false) => Compatibility::High,
+        (false, true) => Compatibility::Low,
+        _ => Compatibility::Medium,
+    }
+}
+
+impl DayCare {
+    pub fn admit(
+        &mut self,
+        pokemon: Pokemon,
+        breeding: Breeding,
+        now: NaiveDateTime,
+    ) -> Result<(), StorageError> {
+        let pokemon_id = pokemon.id();
+        self.pokemon.push(pokemon)?;
+        self.stays.push(Stay {
+            pokemon_id,
+            deposited_at: now,
+            breeding,
+        });
+        if self.stays.len() == CAPACITY {
+            self.pair_since = Some(now);
+        }
+
+        Ok(())
+    }
+
+    /// The experience and level the pokemon will have when it's withdrawn at `now`.
+    pub fn growth(&self, pokemon_id: u32, now: NaiveDateTime) -> Result<(u32, u32), StorageError> {
+        let pokemon = self.pokemon.get_pokemon_ref(pokemon_id)?;
+        let stay = self.stay(pokemon_id)?;
+        let experience = grown_experience(pokemon, stay.deposited_at, now);
+
+        Ok((
+            experience,
+            pokemon.growth_rate.level_for_experience(experience),
+        ))
+    }
+
+    /// Hands a pokemon back, grown by its time here.
+    pub fn release(
+        &mut self,
+        pokemon_id: u32,
+        now: NaiveDateTime,
+    ) -> Result<Pokemon, StorageError> {
+        let (experience, level) = self.growth(pokemon_id, now)?;
+
+        let mut pokemon = self.pokemon.remove(pokemon_id)?;
+        self.stays.retain(|stay| stay.pokemon_id != pokemon_id);
+        self.pair_since = None;
+        pokemon.instance.experience = experience;
+        pokemon.instance.level = pokemon.instance.level.max(level);
+
+        Ok(pokemon)
+    }
+
+    fn stay(&self, pokemon_id: u32) -> Result<&Stay, StorageError> {
+        self.stays
+            .iter()
+            .find(|stay| stay.pokemon_id == pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)
+    }
+
+    fn pair(&self) -> Option<(Parent<'_>, Parent<'_>)> {
+        match self.stays.as_slice() {
+            [a, b] => Some((
+                (
+                    self.pokemon.get_pokemon_ref(a.pokemon_id).ok()?,
+                    &a.breeding,
+                ),
+                (
+                    self.pokemon.get_pokemon_ref(b.pokemon_id).ok()?,
+                    &b.breeding,
+                ),
+            )),
+            _ => None,
+        }
+    }
+
+    fn compatibility(&self) -> Compatibility {
+        match self.pair() {
+            Some((a, b)) => compatibility(a, b),
+            None => Compatibility::None,
+        }
+    }
+
+    fn egg_ready(&self, now: NaiveDateTime) -> bool {
+        match (self.compatibility().egg_interval(), self.pair_since) {
+            (Some(interval), Some(since)) => now - since >= interval,
+            _ => false,
+        }
+    }
+
+    pub fn status(&self, now: NaiveDateTime) -> Status<'_> {
+        let pokemon = self
+            .stays
+            .iter()
+            .filter_map(|stay| {
+                let pokemon = self.pokemon.get_pokemon_ref(stay.pokemon_id).ok()?;
+                let experience = grown_experience(pokemon, stay.deposited_at, now);
+
+                Some(Lodger {
+                    pokemon,
+                    deposited_at: stay.deposited_at,
+                    experience_gained: experience.saturating_sub(pokemon.instance.experience),
+                    level: pokemon
+                        .instance
+                        .level
+                        .max(pokemon.growth_rate.level_for_experience(experience)),
+                })
+            })
+            .collect();
+
+        Status {
+            pokemon,
+            compatibility: self.compatibility(),
+            egg_ready: self.egg_ready(now),
+        }
+    }
+
+    /// Collects the egg the pair has left, which hatches into the mother's baby species, or the
+    /// baby species of whichever isn't a ditto.
+    pub fn collect_egg(&mut self, id: u32, now: NaiveDateTime) -> Result<Egg, DayCareError> {
+        if !self.egg_ready(now) {
+            return Err(DayCareError::NoEgg);
+        }
+        let ((a, a_breeding), (b, b_breeding)) = self.pair().ok_or(DayCareError::NoEgg)?;
+        let parent = if a.pokeAPI_id == DITTO || b.instance.gender == Gender::Female {
+            b_breeding
+        } else {
+            a_breeding
+        };
+
+        let egg = Egg {
+            id,
+            species_id: parent.baby_species_id,
+            species: parent.baby_species.clone(),
+            steps: (parent.hatch_counter + 1) * STEPS_PER_CYCLE,
+            laid: now.date(),
+        };
+        self.pair_since = Some(now);
+
+        Ok(egg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::Instance;
+    use crate::storage::{Storage, StorageDestination};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct FixedClock(Mutex<NaiveDateTime>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> NaiveDateTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn at(minutes: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            + Duration::minutes(minutes)
+    }
+
+    fn pokemon(id: u32, species_id: u32, gender: Gender, trainer: &str) -> Pokemon {
+        let mut pokemon: Pokemon = serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": species_id,
+            "name": format!("species-{}", species_id),
+            "height": 1,
+            "weight": 1,
+            "base_happiness": 0
+        }))
+        .unwrap();
+        pokemon.instance = Instance {
+            id,
+            gender,
+            level: 5,
+            experience: 125,
+            original_trainer: Some(trainer.to_string()),
+            ..Instance::default()
+        };
+
+        pokemon
+    }
+
+    fn breeding(egg_groups: &[&str], baby_species_id: u32) -> Breeding {
+        Breeding {
+            egg_groups: egg_groups.iter().map(|group| group.to_string()).collect(),
+            baby_species_id,
+            baby_species: format!("species-{}", baby_species_id),
+            hatch_counter: 20,
+        }
+    }
+
+    #[test]
+    fn works_out_compatibility() {
+        let monster = breeding(&["monster"], 1);
+        let water = breeding(&["water1"], 7);
+        let ditto = breeding(&["ditto"], DITTO);
+        let legendary = breeding(&[NO_EGGS], 150);
+        let male = pokemon(1, 2, Gender::Male, "red");
+        let female = pokemon(2, 2, Gender::Female, "blue");
+        let female_same_trainer = pokemon(3, 3, Gender::Female, "red");
+        let genderless = pokemon(4, 150, Gender::Genderless, "red");
+        let ditto_pokemon = pokemon(5, DITTO, Gender::Genderless, "red");
+
+        let cases = [
+            ((&male, &monster), (&female, &monster), Compatibility::High),
+            (
+                (&male, &monster),
+                (&female_same_trainer, &monster),
+                Compatibility::Low,
+            ),
+            ((&male, &monster), (&female, &water), Compatibility::None),
+            ((&male, &monster), (&male, &monster), Compatibility::None),
+            (
+                (&ditto_pokemon, &ditto),
+                (&male, &monster),
+                Compatibility::Low,
+            ),
+            (
+                (&ditto_pokemon, &ditto),
+                (&ditto_pokemon, &ditto),
+                Compatibility::None,
+            ),
+            (
+                (&ditto_pokemon, &ditto),
+                (&genderless, &legendary),
+                Compatibility::None,
+            ),
+        ];
+
+        for (a, b, expected) in cases.iter() {
+            assert_eq!(
+                compatibility(*a, *b),
+                *expected,
+                "{} and {}",
+                a.0.id(),
+                b.0.id()
+            );
+        }
+    }
+
+    #[test]
+    fn grows_and_leaves_eggs_over_time() {
+        let mut day_care = DayCare::default();
+        day_care
+            .admit(
+                pokemon(1, 2, Gender::Male, "red"),
+                breeding(&["monster"], 1),
+                at(0),
+            )
+            .unwrap();
+        day_care
+            .admit(
+                pokemon(2, 2, Gender::Female, "blue"),
+                breeding(&["monster"], 1),
+                at(5),
+            )
+            .unwrap();
+
+        assert_eq!(day_care.status(at(10)).pokemon[0].experience_gained, 600);
+        assert_eq!(day_care.collect_egg(1, at(14)), Err(DayCareError::NoEgg));
+
+        let egg = day_care.collect_egg(1, at(15)).unwrap();
+        assert_eq!((egg.species_id, egg.steps), (1, 21 * 255));
+        // Collecting it starts them on the next one.
+        assert!(!day_care.status(at(24)).egg_ready);
+        assert!(day_care.status(at(25)).egg_ready);
+
+        // Medium growth: 8 minutes of experience takes 125 to 605, level 8.
+        let withdrawn = day_care.release(1, at(8)).unwrap();
+        assert_eq!(
+            (withdrawn.instance.experience, withdrawn.instance.level),
+            (605, 8)
+        );
+        assert!(!day_care.status(at(60)).egg_ready);
+    }
+
+    #[test]
+    fn storage_hands_back_grown_pokemon() {
+        let clock = Arc::new(FixedClock(Mutex::new(at(0))));
+        let mut storage = Storage::default();
+        storage.set_clock(clock.clone());
+        let id = storage
+            .add_pokemon(
+                pokemon(0, 2, Gender::Male, "red"),
+                StorageDestination::Party,
+            )
+            .unwrap()
+            .id();
+
+        storage
+            .move_pokemon(id, StorageDestination::DayCare(breeding(&["monster"], 1)))
+            .unwrap();
+        assert!(storage.get_party().unwrap().is_empty());
+
+        *clock.0.lock().unwrap() = at(8);
+        assert_eq!(storage.day_care().pokemon[0].level, 8);
+        let pokemon = storage.move_pokemon(id, StorageDestination::Party).unwrap();
+        assert_eq!(pokemon.instance.level, 8);
+    }
+}
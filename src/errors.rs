@@ -1,13 +1,23 @@
 use thruster::errors::ThrusterError as Error;
 
 use crate::context::Ctx;
+use crate::day_care::DayCareError;
+use crate::names::UnknownName;
+use crate::station::StationError;
+use crate::storage::StorageError;
+use crate::trading::TradeError;
+use crate::wild::WildError;
 
 pub trait ErrorSet {
     fn parsing_error(context: Ctx, error: &str) -> Error<Ctx>;
     fn generic_error(context: Ctx) -> Error<Ctx>;
     fn unauthorized_error(context: Ctx) -> Error<Ctx>;
     fn not_found_error(context: Ctx) -> Error<Ctx>;
+    fn pokeapi_error(context: Ctx) -> Error<Ctx>;
     fn container_is_full(context: Ctx) -> Error<Ctx>;
+    fn invalid_request(context: Ctx, message: &str) -> Error<Ctx>;
+    fn storage_error(context: Ctx, error: StorageError) -> Error<Ctx>;
+    fn unknown_name(context: Ctx, error: UnknownName) -> Error<Ctx>;
 }
 
 impl ErrorSet for Error<Ctx> {
@@ -47,6 +57,15 @@ impl ErrorSet for Error<Ctx> {
         }
     }
 
+    fn pokeapi_error(context: Ctx) -> Error<Ctx> {
+        Error {
+            context,
+            message: "PokeAPI didn't answer as expected, try again later".to_string(),
+            status: 502,
+            cause: None,
+        }
+    }
+
     fn container_is_full(context: Ctx) -> Error<Ctx> {
         Error {
             context,
@@ -55,4 +74,87 @@ impl ErrorSet for Error<Ctx> {
             cause: None,
         }
     }
+
+    fn invalid_request(context: Ctx, message: &str) -> Error<Ctx> {
+        Error {
+            context,
+            message: message.to_string(),
+            status: 400,
+            cause: None,
+        }
+    }
+
+    fn storage_error(context: Ctx, error: StorageError) -> Error<Ctx> {
+        match error {
+            StorageError::ContainerIsFull => Error::container_is_full(context),
+            StorageError::BoxDoesNotExist | StorageError::PokemonNotFound => {
+                Error::not_found_error(context)
+            }
+            StorageError::AlreadyHoldingItem => Error::invalid_request(
+                context,
+                "That pokemon is already holding an item, swap it instead",
+            ),
+            StorageError::NotHoldingItem => {
+                Error::invalid_request(context, "That pokemon isn't holding an item")
+            }
+            StorageError::Bag(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Nuzlocke(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::DayCare(DayCareError::EggNotFound) => Error::not_found_error(context),
+            StorageError::DayCare(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Wild(WildError::NoEncounter) => Error::not_found_error(context),
+            StorageError::Wild(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Evolution(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Move(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Trade(TradeError::NotFound) => Error::not_found_error(context),
+            StorageError::Trade(e @ TradeError::UnknownTrainer(_)) => Error {
+                context,
+                message: e.to_string(),
+                status: 404,
+                cause: None,
+            },
+            StorageError::Trade(e @ TradeError::InEscrow(_)) => Error {
+                context,
+                message: e.to_string(),
+                status: 409,
+                cause: None,
+            },
+            StorageError::Trade(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Station(StationError::NotFound) => Error::not_found_error(context),
+            StorageError::Station(e @ StationError::NotYours) => Error {
+                context,
+                message: e.to_string(),
+                status: 403,
+                cause: None,
+            },
+            StorageError::Station(e) => Error::invalid_request(context, &e.to_string()),
+            StorageError::Changed => Error {
+                context,
+                message: "That pokemon changed while this was happening, try again".to_string(),
+                status: 409,
+                cause: None,
+            },
+            StorageError::IllegalParty(violations) => Error {
+                context,
+                message: format!(
+                    "That would break the party rules: {}",
+                    violations
+                        .iter()
+                        .map(|violation| violation.to_string())
+                        .collect::<Vec<String>>()
+                        .join("; ")
+                ),
+                status: 409,
+                cause: None,
+            },
+        }
+    }
+
+    fn unknown_name(context: Ctx, error: UnknownName) -> Error<Ctx> {
+        Error {
+            context,
+            message: error.to_string(),
+            status: 404,
+            cause: None,
+        }
+    }
 }
@@ -0,0 +1,382 @@
+use chrono::Utc;
+use std::fmt;
+
+use crate::instance::{EvolutionRecord, Gender};
+use crate::pokemon::Pokemon;
+
+/// One way of evolving into a species, taken from PokeAPI's evolution details.
+#[derive(Clone, Debug, Default)]
+pub struct EvolutionMethod {
+    /// PokeAPI's trigger name, e.g. `level-up`, `use-item` or `trade`.
+    pub trigger: String,
+    pub min_level: Option<u32>,
+    pub min_happiness: Option<u32>,
+    /// The item used on the pokemon for `use-item` evolutions.
+    pub item: Option<String>,
+    /// An item the pokemon has to be holding, used up by evolving.
+    pub held_item: Option<String>,
+    pub known_move: Option<String>,
+    pub gender: Option<Gender>,
+    pub trade_species: Option<String>,
+    /// Conditions PokeAPI lists that we have no way of checking, such as the time of day or
+    /// location. A method with any of these can't be used.
+    pub unsupported: Vec<String>,
+}
+
+/// A species the pokemon can evolve into, and the ways of getting there.
+#[derive(Clone, Debug)]
+pub struct Evolution {
+    pub species: String,
+    pub species_id: u32,
+    pub methods: Vec<EvolutionMethod>,
+}
+
+/// What's being done to a pokemon that might make it evolve.
+pub struct Trigger<'a> {
+    pub name: &'a str,
+    /// The item being used on it, for `use-item`.
+    pub item: Option<&'a str>,
+    /// The species it's being traded for, for `trade`. Only set when it's traded for a single
+    /// pokemon.
+    pub traded_for: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EvolutionError {
+    NoEvolutions(String),
+    UnknownTarget { from: String, into: String },
+    Ambiguous(Vec<String>),
+    NotMet(String),
+}
+
+impl fmt::Display for EvolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvolutionError::NoEvolutions(species) => write!(f, "{} doesn't evolve", species),
+            EvolutionError::UnknownTarget { from, into } => {
+                write!(f, "{} doesn't evolve into {}", from, into)
+            }
+            EvolutionError::Ambiguous(species) => write!(
+                f,
+                "this could evolve into any of {}, pick one with into",
+                species.join(", ")
+            ),
+            EvolutionError::NotMet(reasons) => write!(f, "can't evolve yet: {}", reasons),
+        }
+    }
+}
+
+/// Why `method` doesn't apply to `pokemon` right now, if it doesn't.
+fn unmet(method: &EvolutionMethod, pokemon: &Pokemon, trigger: &Trigger) -> Option<String> {
+    let instance = &pokemon.instance;
+
+    if method.trigger != trigger.name {
+        return Some(format!("needs the {} trigger", method.trigger));
+    }
+    if let Some(condition) = method.unsupported.first() {
+        return Some(format!("depends on {}, which isn't supported", condition));
+    }
+    if let Some(level) = method.min_level {
+        if instance.level < level {
+            return Some(format!("needs to be level {}", level));
+        }
+    }
+    if let Some(happiness) = method.min_happiness {
+        if instance.happiness < happiness {
+            return Some(format!("needs a happiness of {}", happiness));
+        }
+    }
+    if let Some(item) = &method.item {
+        if trigger.item != Some(item.as_str()) {
+            return Some(format!("needs a {} used on it", item));
+        }
+    }
+    if let Some(held_item) = &method.held_item {
+        if instance.held_item.as_ref().map(|held| &held.name) != Some(held_item) {
+            return Some(format!("needs to be holding a {}", held_item));
+        }
+    }
+    if let Some(known_move) = &method.known_move {
+        if !instance.moves.iter().any(|known| &known.name == known_move) {
+            return Some(format!("needs to know {}", known_move));
+        }
+    }
+    if let Some(gender) = method.gender {
+        if instance.gender != gender {
+            return Some(format!("needs to be {:?}", gender).to_lowercase());
+        }
+    }
+    if let Some(trade_species) = &method.trade_species {
+        if trigger.traded_for != Some(trade_species.as_str()) {
+            return Some(format!("needs to be traded for a {}", trade_species));
+        }
+    }
+
+    None
+}
+
+/// Works out what `pokemon` evolves into when `trigger` happens to it. `into` picks between
+/// evolutions, and is required when the conditions for more than one are met.
+pub fn choose<'a>(
+    evolutions: &'a [Evolution],
+    pokemon: &Pokemon,
+    into: Option<&str>,
+    trigger: &Trigger,
+) -> Result<(&'a Evolution, &'a EvolutionMethod), EvolutionError> {
+    if evolutions.is_empty() {
+        return Err(EvolutionError::NoEvolutions(pokemon.name.clone()));
+    }
+
+    let candidates = evolutions
+        .iter()
+        .filter(|evolution| into.is_none_or(|into| evolution.species == into))
+        .collect::<Vec<&Evolution>>();
+    if candidates.is_empty() {
+        return Err(EvolutionError::UnknownTarget {
+            from: pokemon.name.clone(),
+            into: into.unwrap_or_default().to_string(),
+        });
+    }
+
+    let mut met = vec![];
+    let mut reasons = vec![];
+    for evolution in candidates {
+        let usable =
+            evolution
+                .methods
+                .iter()
+                .find(|method| match unmet(method, pokemon, trigger) {
+                    Some(reason) => {
+                        reasons.push(format!("{} {}", evolution.species, reason));
+                        false
+                    }
+                    None => true,
+                });
+
+        if let Some(method) = usable {
+            met.push((evolution, method));
+        }
+    }
+
+    match met.len() {
+        0 => Err(EvolutionError::NotMet(reasons.join("; "))),
+        1 => Ok(met[0]),
+        _ => Err(EvolutionError::Ambiguous(
+            met.iter()
+                .map(|(evolution, _)| evolution.species.clone())
+                .collect(),
+        )),
+    }
+}
+
+/// Turns `pokemon` into `species` in place. Everything about the individual pokemon is kept and
+/// the change is added to its evolution history.
+pub fn evolve(pokemon: &mut Pokemon, species: Pokemon, trigger: &str) {
+    let mut instance = std::mem::take(&mut pokemon.instance);
+    instance.form = species.instance.form.clone();
+
+    instance.evolutions.push(EvolutionRecord {
+        from: pokemon.name.clone(),
+        to: species.name.clone(),
+        trigger: trigger.to_string(),
+        level: instance.level,
+        date: Utc::now().naive_utc().date(),
+    });
+
+    *pokemon = Pokemon {
+        instance,
+        ..species
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::Instance;
+    use crate::items::Item;
+
+    fn pokemon(level: u32, happiness: u32, held_item: Option<&str>) -> Pokemon {
+        let mut pokemon: Pokemon = serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": 133,
+            "name": "eevee",
+            "height": 3,
+            "weight": 65,
+            "base_happiness": 70
+        }))
+        .unwrap();
+        pokemon.instance = Instance {
+            level,
+            happiness,
+            held_item: held_item.map(|name| Item {
+                name: name.to_string(),
+                category: "held-items".to_string(),
+            }),
+            ..Instance::default()
+        };
+
+        pokemon
+    }
+
+    fn evolution(species: &str, method: EvolutionMethod) -> Evolution {
+        Evolution {
+            species: species.to_string(),
+            species_id: 0,
+            methods: vec![method],
+        }
+    }
+
+    fn trigger<'a>(name: &'a str, item: Option<&'a str>) -> Trigger<'a> {
+        Trigger {
+            name,
+            item,
+            traded_for: None,
+        }
+    }
+
+    fn chosen(
+        evolutions: &[Evolution],
+        pokemon: &Pokemon,
+        trigger: &Trigger,
+    ) -> Result<String, EvolutionError> {
+        choose(evolutions, pokemon, None, trigger).map(|(evolution, _)| evolution.species.clone())
+    }
+
+    #[test]
+    fn checks_level_and_happiness() {
+        let by_level = [evolution(
+            "ivysaur",
+            EvolutionMethod {
+                trigger: "level-up".to_string(),
+                min_level: Some(16),
+                ..EvolutionMethod::default()
+            },
+        )];
+        let level_up = trigger("level-up", None);
+
+        assert_eq!(
+            chosen(&by_level, &pokemon(15, 0, None), &level_up),
+            Err(EvolutionError::NotMet(
+                "ivysaur needs to be level 16".to_string()
+            ))
+        );
+        assert_eq!(
+            chosen(&by_level, &pokemon(16, 0, None), &level_up),
+            Ok("ivysaur".to_string())
+        );
+
+        let by_happiness = [evolution(
+            "espeon",
+            EvolutionMethod {
+                trigger: "level-up".to_string(),
+                min_happiness: Some(160),
+                ..EvolutionMethod::default()
+            },
+        )];
+        assert_eq!(
+            chosen(&by_happiness, &pokemon(5, 159, None), &level_up),
+            Err(EvolutionError::NotMet(
+                "espeon needs a happiness of 160".to_string()
+            ))
+        );
+        assert_eq!(
+            chosen(&by_happiness, &pokemon(5, 160, None), &level_up),
+            Ok("espeon".to_string())
+        );
+    }
+
+    #[test]
+    fn checks_used_and_held_items() {
+        let evolutions = [
+            evolution(
+                "vaporeon",
+                EvolutionMethod {
+                    trigger: "use-item".to_string(),
+                    item: Some("water-stone".to_string()),
+                    ..EvolutionMethod::default()
+                },
+            ),
+            evolution(
+                "sylveon",
+                EvolutionMethod {
+                    trigger: "level-up".to_string(),
+                    held_item: Some("pixie-plate".to_string()),
+                    ..EvolutionMethod::default()
+                },
+            ),
+        ];
+
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(5, 0, None),
+                &trigger("use-item", Some("fire-stone"))
+            ),
+            Err(EvolutionError::NotMet(
+                "vaporeon needs a water-stone used on it; sylveon needs the level-up trigger"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(5, 0, None),
+                &trigger("use-item", Some("water-stone"))
+            ),
+            Ok("vaporeon".to_string())
+        );
+
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(5, 0, Some("oran-berry")),
+                &trigger("level-up", None)
+            ),
+            Err(EvolutionError::NotMet(
+                "vaporeon needs the use-item trigger; sylveon needs to be holding a pixie-plate"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(5, 0, Some("pixie-plate")),
+                &trigger("level-up", None)
+            ),
+            Ok("sylveon".to_string())
+        );
+    }
+
+    #[test]
+    fn refuses_trade_evolutions() {
+        let evolutions = [evolution(
+            "escavalier",
+            EvolutionMethod {
+                trigger: "trade".to_string(),
+                trade_species: Some("shelmet".to_string()),
+                ..EvolutionMethod::default()
+            },
+        )];
+
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(100, 255, None),
+                &trigger("level-up", None)
+            ),
+            Err(EvolutionError::NotMet(
+                "escavalier needs the trade trigger".to_string()
+            ))
+        );
+        // Even claiming a trade happened isn't enough without the other species.
+        assert_eq!(
+            chosen(
+                &evolutions,
+                &pokemon(100, 255, None),
+                &trigger("trade", None)
+            ),
+            Err(EvolutionError::NotMet(
+                "escavalier needs to be traded for a shelmet".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::pokemon::Pokemon;
+
+/// What PokeAPI's regional forms are named after. They're separate evolution lines rather than
+/// forms a pokemon can switch between.
+const REGIONS: [&str; 4] = ["alola", "galar", "hisui", "paldea"];
+
+/// One of the pokemon that make up a species, e.g. `giratina-altered` and `giratina-origin`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Variety {
+    pub pokemon_id: u32,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FormError {
+    UnknownForm { form: String, forms: Vec<String> },
+    BattleOnly(String),
+    Regional(String),
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormError::UnknownForm { form, forms } => write!(
+                f,
+                "{} isn't a form of this species, which has {}",
+                form,
+                forms.join(", ")
+            ),
+            FormError::BattleOnly(form) => {
+                write!(f, "{} only exists during battle", form)
+            }
+            FormError::Regional(form) => write!(
+                f,
+                "{} is a regional form, which pokemon can't change into or out of",
+                form
+            ),
+        }
+    }
+}
+
+/// The part of a variety's name that follows the species', e.g. `origin` for `giratina-origin`.
+fn form_name<'a>(species: &str, variety: &'a str) -> &'a str {
+    variety
+        .strip_prefix(species)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .unwrap_or(variety)
+}
+
+/// The region a variety of `species` is from, e.g. `alola` for `vulpix-alola`, if it's a regional
+/// form.
+fn region<'a>(species: &str, variety: &'a str) -> Option<&'a str> {
+    form_name(species, variety)
+        .split('-')
+        .next()
+        .filter(|region| REGIONS.contains(region))
+}
+
+pub fn is_regional(species: &str, variety: &str) -> bool {
+    region(species, variety).is_some()
+}
+
+/// The variety of `species` from the same region as `form`, a regional form of `from`, e.g.
+/// `ninetales-alola` for `vulpix-alola` evolving into ninetales. `None` when the evolved species
+/// has no form from that region.
+pub fn regional_counterpart<'a>(
+    from: &str,
+    form: &str,
+    species: &str,
+    varieties: &'a [Variety],
+) -> Option<&'a Variety> {
+    let from_region = region(from, form)?;
+
+    varieties
+        .iter()
+        .find(|variety| region(species, &variety.name) == Some(from_region))
+}
+
+/// Finds the variety of `species` that `form` names, in full or by what follows the species'
+/// name, and checks a pokemon currently in form `current` could change into it. `None` stands for
+/// the default variety either way. Whether the new form only exists in battle is up to the caller.
+pub fn find_change<'a>(
+    species: &str,
+    varieties: &'a [Variety],
+    current: Option<&str>,
+    form: Option<&str>,
+) -> Result<&'a Variety, FormError> {
+    let target = varieties
+        .iter()
+        .find(|variety| match form {
+            Some(form) => variety.name == form || form_name(species, &variety.name) == form,
+            None => variety.is_default,
+        })
+        .ok_or_else(|| FormError::UnknownForm {
+            form: form.unwrap_or(species).to_string(),
+            forms: varieties
+                .iter()
+                .map(|variety| variety.name.clone())
+                .collect(),
+        })?;
+
+    let current = current
+        .or_else(|| {
+            varieties
+                .iter()
+                .find(|variety| variety.is_default)
+                .map(|variety| variety.name.as_str())
+        })
+        .unwrap_or(species);
+    if current != target.name {
+        for name in [current, target.name.as_str()].iter() {
+            if is_regional(species, name) {
+                return Err(FormError::Regional(name.to_string()));
+            }
+        }
+    }
+
+    Ok(target)
+}
+
+/// Turns `pokemon` into another form of its species, `form` being that form's data. Everything
+/// about the individual pokemon is kept.
+pub fn change(pokemon: &mut Pokemon, form: Pokemon) {
+    let mut instance = std::mem::take(&mut pokemon.instance);
+    instance.form = form.instance.form.clone();
+
+    *pokemon = Pokemon { instance, ..form };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varieties(species: &str, forms: &[&str]) -> Vec<Variety> {
+        std::iter::once(species)
+            .chain(forms.iter().copied())
+            .zip(1..)
+            .map(|(name, pokemon_id)| Variety {
+                pokemon_id,
+                name: name.to_string(),
+                is_default: pokemon_id == 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_forms_by_full_or_short_name() {
+        let giratina = varieties("giratina-altered", &["giratina-origin"]);
+
+        let origin = find_change("giratina", &giratina, None, Some("origin")).unwrap();
+        assert_eq!(origin.pokemon_id, 2);
+        assert_eq!(
+            find_change("giratina", &giratina, None, Some("giratina-origin")),
+            Ok(origin)
+        );
+        assert_eq!(
+            find_change("giratina", &giratina, Some("giratina-origin"), None)
+                .unwrap()
+                .name,
+            "giratina-altered"
+        );
+        assert!(matches!(
+            find_change("giratina", &giratina, None, Some("sky")),
+            Err(FormError::UnknownForm { .. })
+        ));
+    }
+
+    #[test]
+    fn regional_forms_are_separate_lines() {
+        let vulpix = varieties("vulpix", &["vulpix-alola"]);
+
+        assert_eq!(
+            find_change("vulpix", &vulpix, None, Some("alola")),
+            Err(FormError::Regional("vulpix-alola".to_string()))
+        );
+        assert_eq!(
+            find_change("vulpix", &vulpix, Some("vulpix-alola"), None),
+            Err(FormError::Regional("vulpix-alola".to_string()))
+        );
+        assert!(find_change("vulpix", &vulpix, Some("vulpix-alola"), Some("alola")).is_ok());
+    }
+
+    #[test]
+    fn evolving_keeps_the_region() {
+        let ninetales = varieties("ninetales", &["ninetales-alola"]);
+        let darmanitan = varieties(
+            "darmanitan-standard",
+            &[
+                "darmanitan-zen",
+                "darmanitan-galar-standard",
+                "darmanitan-galar-zen",
+            ],
+        );
+
+        assert_eq!(
+            regional_counterpart("vulpix", "vulpix-alola", "ninetales", &ninetales)
+                .map(|variety| variety.name.as_str()),
+            Some("ninetales-alola")
+        );
+        assert_eq!(
+            regional_counterpart("darumaka", "darumaka-galar", "darmanitan", &darmanitan)
+                .map(|variety| variety.name.as_str()),
+            Some("darmanitan-galar-standard")
+        );
+        assert_eq!(
+            regional_counterpart("giratina", "giratina-origin", "ninetales", &ninetales),
+            None
+        );
+        assert_eq!(
+            regional_counterpart("vulpix", "vulpix-galar", "ninetales", &ninetales),
+            None
+        );
+    }
+}
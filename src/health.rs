@@ -0,0 +1,236 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::persistence::Persistence;
+use crate::pokemon_api::PokeApi;
+
+// How long a PokeAPI reachability result is trusted before we probe again, so
+// orchestrators polling `/readyz` don't turn into a steady stream of upstream calls.
+const POKEAPI_CHECK_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Starting,
+    Ready,
+    Draining,
+}
+
+impl Phase {
+    fn from_u8(value: u8) -> Phase {
+        match value {
+            1 => Phase::Ready,
+            2 => Phase::Draining,
+            _ => Phase::Starting,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Phase::Starting => 0,
+            Phase::Ready => 1,
+            Phase::Draining => 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn ok(detail: &str) -> Check {
+        Check {
+            ok: true,
+            detail: detail.to_string(),
+        }
+    }
+
+    fn failed(detail: &str) -> Check {
+        Check {
+            ok: false,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Checks {
+    pub storage: Check,
+    pub persistence: Check,
+    pub pokeapi: Check,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub phase: Phase,
+    pub checks: Checks,
+}
+
+/// PokeAPI only has to be reachable when there's nothing to serve pokemon from without it.
+fn pokeapi_check(reachable: bool, warm: bool) -> Check {
+    match (reachable, warm) {
+        (true, _) => Check::ok("pokeapi reachable"),
+        (false, true) => Check::ok("pokeapi unreachable, serving cached pokemon"),
+        (false, false) => Check::failed("pokeapi unreachable and nothing is cached"),
+    }
+}
+
+#[derive(Debug)]
+pub struct Health {
+    phase: AtomicU8,
+    last_pokeapi_check: Mutex<Option<(Instant, bool)>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            phase: AtomicU8::new(Phase::Starting.as_u8()),
+            last_pokeapi_check: Mutex::new(None),
+        }
+    }
+}
+
+impl Health {
+    pub fn phase(&self) -> Phase {
+        Phase::from_u8(self.phase.load(Ordering::SeqCst))
+    }
+
+    pub fn set_phase(&self, phase: Phase) {
+        self.phase.store(phase.as_u8(), Ordering::SeqCst);
+    }
+
+    /// Waits for `signal`, then reports the server as draining so it's taken out of rotation
+    /// while in-flight requests finish.
+    pub async fn drain_on<F: Future<Output = ()>>(&self, signal: F) {
+        signal.await;
+        self.set_phase(Phase::Draining);
+    }
+
+    pub async fn readiness(&self, persistence: &Persistence, pokeapi: &PokeApi) -> Readiness {
+        let phase = self.phase();
+
+        let storage = match phase {
+            Phase::Starting => Check::failed("storage is still loading"),
+            Phase::Ready => Check::ok("storage loaded"),
+            Phase::Draining => Check::failed("server is shutting down"),
+        };
+        let persistence = if !persistence.is_configured() {
+            Check::ok("persistence is not configured")
+        } else {
+            match persistence.check_writable() {
+                Ok(()) => Check::ok("snapshot path writable"),
+                Err(e) => Check::failed(&format!("snapshot path not writable: {}", e)),
+            }
+        };
+        let pokeapi = pokeapi_check(self.pokeapi_reachable(pokeapi).await, pokeapi.is_warm());
+
+        Readiness {
+            ready: phase == Phase::Ready && storage.ok && persistence.ok && pokeapi.ok,
+            phase,
+            checks: Checks {
+                storage,
+                persistence,
+                pokeapi,
+            },
+        }
+    }
+
+    async fn pokeapi_reachable(&self, pokeapi: &PokeApi) -> bool {
+        if let Some((checked_at, reachable)) = *self.last_pokeapi_check.lock().unwrap() {
+            if checked_at.elapsed() < POKEAPI_CHECK_TTL {
+                return reachable;
+            }
+        }
+
+        let reachable = pokeapi.ping().await.is_ok();
+        *self.last_pokeapi_check.lock().unwrap() = Some((Instant::now(), reachable));
+
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::AppState;
+    use crate::server::testing;
+    use std::sync::Arc;
+
+    async fn get(server: &testing::TestServer, path: &str) -> (u16, serde_json::Value) {
+        let response = reqwest::get(&server.url(path)).await.unwrap();
+        let status = response.status().as_u16();
+
+        (
+            status,
+            serde_json::from_str(&response.text().await.unwrap()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_the_phase() {
+        let state = Arc::new(AppState::default());
+        let (server, shutdown) = testing::start(state.clone()).await;
+
+        let (status, body) = get(&server, "/readyz").await;
+        assert_eq!(
+            (status, &body["phase"]),
+            (503, &serde_json::json!("starting"))
+        );
+        assert_eq!(body["checks"]["storage"]["ok"], false);
+
+        state.health.set_phase(Phase::Ready);
+        let (status, body) = get(&server, "/readyz").await;
+        assert_eq!((status, &body["ready"]), (200, &serde_json::json!(true)));
+
+        state.health.set_phase(Phase::Draining);
+        assert_eq!(get(&server, "/readyz").await.0, 503);
+        // Still alive while draining.
+        let (status, body) = get(&server, "/healthz").await;
+        assert_eq!((status, &body["status"]), (200, &serde_json::json!("ok")));
+
+        shutdown.send(()).unwrap();
+        server.stopped().await;
+    }
+
+    #[tokio::test]
+    async fn shutting_down_stops_being_ready() {
+        let state = Arc::new(AppState::default());
+        state.health.set_phase(Phase::Ready);
+        let (signal, signalled) = tokio::sync::oneshot::channel::<()>();
+        let draining_state = state.clone();
+        let server = testing::start_with(state.clone(), async move {
+            draining_state
+                .health
+                .drain_on(async {
+                    let _ = signalled.await;
+                })
+                .await
+        })
+        .await;
+        assert_eq!(get(&server, "/readyz").await.0, 200);
+
+        signal.send(()).unwrap();
+        server.stopped().await;
+
+        assert_eq!(state.health.phase(), Phase::Draining);
+        let readiness = state
+            .health
+            .readiness(&state.persistence, &state.pokeapi)
+            .await;
+        assert!(!readiness.ready);
+    }
+
+    #[test]
+    fn a_warm_cache_stands_in_for_pokeapi() {
+        assert!(pokeapi_check(true, false).ok);
+        assert!(pokeapi_check(false, true).ok);
+        assert!(!pokeapi_check(false, false).ok);
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::Error;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+type Waiters<V> = Vec<oneshot::Sender<Result<V, String>>>;
+
+/// Shares one upstream call between everyone asking for the same key at the same time. The first
+/// caller for a key does the work and hands a copy of the result to whoever asked in the meantime.
+#[derive(Debug)]
+pub struct InFlight<K, V> {
+    waiting: Mutex<HashMap<K, Waiters<V>>>,
+}
+
+impl<K: Eq + Hash, V> Default for InFlight<K, V> {
+    fn default() -> Self {
+        InFlight {
+            waiting: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Held by the caller doing the work. If it's dropped before finishing, e.g. because the request
+/// it was serving went away, the waiters are let go so one of them can take over.
+struct Leader<'a, K: Eq + Hash, V> {
+    in_flight: &'a InFlight<K, V>,
+    key: Option<K>,
+}
+
+impl<K: Eq + Hash, V> Leader<'_, K, V> {
+    fn finish(mut self) -> Waiters<V> {
+        let key = self.key.take().expect("a leader finishes once");
+
+        self.in_flight
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .unwrap_or_default()
+    }
+}
+
+impl<K: Eq + Hash, V> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.in_flight.waiting.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> InFlight<K, V> {
+    /// Runs `fetch` for `key`, unless it's already running, in which case this waits for that run
+    /// to finish instead. Errors are shared by their message.
+    pub async fn run<F, Fut>(&self, key: K, fetch: F) -> Result<V, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, Error>>,
+    {
+        loop {
+            let waiting = {
+                let mut waiting = self.waiting.lock().unwrap();
+                match waiting.get_mut(&key) {
+                    Some(waiters) => {
+                        let (sender, receiver) = oneshot::channel();
+                        waiters.push(sender);
+                        Some(receiver)
+                    }
+                    None => {
+                        waiting.insert(key.clone(), vec![]);
+                        None
+                    }
+                }
+            };
+
+            match waiting {
+                Some(receiver) => match receiver.await {
+                    Ok(result) => return result.map_err(Error::msg),
+                    // The leader gave up, so try again, probably as the leader this time.
+                    Err(_) => continue,
+                },
+                None => {
+                    let leader = Leader {
+                        in_flight: self,
+                        key: Some(key),
+                    };
+                    let result = fetch().await;
+
+                    for waiter in leader.finish() {
+                        let _ = waiter.send(match &result {
+                            Ok(value) => Ok(value.clone()),
+                            Err(e) => Err(e.to_string()),
+                        });
+                    }
+
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use tokio::time::delay_for;
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_call() {
+        let in_flight = InFlight::<u32, u32>::default();
+        let calls = AtomicU32::new(0);
+        let fetch = |id: u32| {
+            let calls = &calls;
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                delay_for(Duration::from_millis(20)).await;
+                if id == 0 {
+                    Err(anyhow!("no pokemon 0"))
+                } else {
+                    Ok(id * 10)
+                }
+            }
+        };
+
+        let (a, b, c) = tokio::join!(
+            in_flight.run(1, fetch(1)),
+            in_flight.run(1, fetch(1)),
+            in_flight.run(2, fetch(2))
+        );
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (10, 10, 20));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let (a, b) = tokio::join!(in_flight.run(0, fetch(0)), in_flight.run(0, fetch(0)));
+        assert_eq!(b.unwrap_err().to_string(), a.unwrap_err().to_string());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn a_waiter_takes_over_when_the_leader_gives_up() {
+        let in_flight = InFlight::<u32, u32>::default();
+
+        let abandoned = tokio::time::timeout(
+            Duration::from_millis(10),
+            in_flight.run(1, || async {
+                delay_for(Duration::from_secs(60)).await;
+                Ok(1)
+            }),
+        );
+        let waiter = async {
+            // Queue up behind the leader before it's abandoned.
+            delay_for(Duration::from_millis(1)).await;
+            in_flight.run(1, || async { Ok(2) }).await
+        };
+
+        let (abandoned, waited) = tokio::join!(abandoned, waiter);
+        assert!(abandoned.is_err());
+        assert_eq!(waited.unwrap(), 2);
+    }
+}
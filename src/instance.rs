@@ -0,0 +1,526 @@
+use chrono::{NaiveDate, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::items::Item;
+use crate::moves::MoveSlot;
+use crate::pokemon::{Pokemon, Stats};
+
+pub const MIN_LEVEL: u32 = 1;
+pub const MAX_LEVEL: u32 = 100;
+pub const MAX_IV: u32 = 31;
+pub const MAX_EV: u32 = 252;
+pub const MAX_EV_TOTAL: u32 = 510;
+pub const MAX_NICKNAME_LENGTH: usize = 12;
+pub const MAX_HAPPINESS: u32 = 255;
+const DEFAULT_LEVEL: u32 = 5;
+const SHINY_ODDS: u32 = 4096;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    Hp,
+    Attack,
+    Defense,
+    SpecialAttack,
+    SpecialDefense,
+    Speed,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Nature {
+    #[default]
+    Hardy,
+    Lonely,
+    Brave,
+    Adamant,
+    Naughty,
+    Bold,
+    Docile,
+    Relaxed,
+    Impish,
+    Lax,
+    Timid,
+    Hasty,
+    Serious,
+    Jolly,
+    Naive,
+    Modest,
+    Mild,
+    Quiet,
+    Bashful,
+    Rash,
+    Calm,
+    Gentle,
+    Sassy,
+    Careful,
+    Quirky,
+}
+
+impl Nature {
+    pub const ALL: [Nature; 25] = [
+        Nature::Hardy,
+        Nature::Lonely,
+        Nature::Brave,
+        Nature::Adamant,
+        Nature::Naughty,
+        Nature::Bold,
+        Nature::Docile,
+        Nature::Relaxed,
+        Nature::Impish,
+        Nature::Lax,
+        Nature::Timid,
+        Nature::Hasty,
+        Nature::Serious,
+        Nature::Jolly,
+        Nature::Naive,
+        Nature::Modest,
+        Nature::Mild,
+        Nature::Quiet,
+        Nature::Bashful,
+        Nature::Rash,
+        Nature::Calm,
+        Nature::Gentle,
+        Nature::Sassy,
+        Nature::Careful,
+        Nature::Quirky,
+    ];
+
+    /// The (raised, lowered) stats for this nature, or `None` for the five neutral natures.
+    pub fn modifiers(self) -> Option<(Stat, Stat)> {
+        // Natures are laid out in a 5x5 grid: the row picks the raised stat and the column the
+        // lowered one, with the diagonal being neutral.
+        const GRID: [Stat; 5] = [
+            Stat::Attack,
+            Stat::Defense,
+            Stat::Speed,
+            Stat::SpecialAttack,
+            Stat::SpecialDefense,
+        ];
+        let index = Nature::ALL
+            .iter()
+            .position(|nature| *nature == self)
+            .unwrap();
+        let (raised, lowered) = (index / 5, index % 5);
+
+        if raised == lowered {
+            None
+        } else {
+            Some((GRID[raised], GRID[lowered]))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gender {
+    Male,
+    Female,
+    #[default]
+    Genderless,
+}
+
+/// Data belonging to one particular caught pokemon, as opposed to its species.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Instance {
+    /// Unique within a trainer's storage and used to address the pokemon once caught.
+    pub id: u32,
+    pub nickname: Option<String>,
+    pub level: u32,
+    pub experience: u32,
+    pub nature: Nature,
+    pub gender: Gender,
+    pub shiny: bool,
+    pub ivs: Stats,
+    pub evs: Stats,
+    pub original_trainer: Option<String>,
+    pub met_date: NaiveDate,
+    /// Up to `moves::MAX_MOVES`, changed through teaching and forgetting rather than edits.
+    pub moves: Vec<MoveSlot>,
+    /// Given and taken through the bag rather than edits.
+    pub held_item: Option<Item>,
+    /// Starts at the species' base happiness.
+    pub happiness: u32,
+    /// Oldest first.
+    pub evolutions: Vec<EvolutionRecord>,
+    /// The variety of its species it is, e.g. `giratina-origin`, unless it's the default one.
+    pub form: Option<String>,
+}
+
+/// A species change recorded when a pokemon evolves.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EvolutionRecord {
+    pub from: String,
+    pub to: String,
+    pub trigger: String,
+    pub level: u32,
+    pub date: NaiveDate,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Instance {
+            id: 0,
+            nickname: None,
+            level: DEFAULT_LEVEL,
+            experience: 0,
+            nature: Nature::default(),
+            gender: Gender::default(),
+            shiny: false,
+            ivs: Stats::default(),
+            evs: Stats::default(),
+            original_trainer: None,
+            met_date: Utc::now().naive_utc().date(),
+            moves: vec![],
+            held_item: None,
+            happiness: 0,
+            evolutions: vec![],
+            form: None,
+        }
+    }
+}
+
+/// Instance data a client may supply when catching a pokemon or edit afterwards. Anything left out
+/// is generated (on catch) or left untouched (on edit).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstanceChanges {
+    pub nickname: Option<String>,
+    pub level: Option<u32>,
+    pub experience: Option<u32>,
+    pub nature: Option<Nature>,
+    pub gender: Option<Gender>,
+    pub shiny: Option<bool>,
+    pub ivs: Option<Stats>,
+    pub evs: Option<Stats>,
+    pub original_trainer: Option<String>,
+    pub met_date: Option<NaiveDate>,
+    pub happiness: Option<u32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InstanceError {
+    LevelOutOfRange(u32),
+    LevelAndExperience,
+    IvOutOfRange(Stat, u32),
+    EvOutOfRange(Stat, u32),
+    EvTotalTooHigh(u32),
+    InvalidNickname,
+    InvalidGender(Gender),
+    HappinessOutOfRange(u32),
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceError::LevelOutOfRange(level) => write!(
+                f,
+                "level {} must be between {} and {}",
+                level, MIN_LEVEL, MAX_LEVEL
+            ),
+            InstanceError::LevelAndExperience => {
+                write!(f, "set either level or experience, not both")
+            }
+            InstanceError::IvOutOfRange(stat, value) => write!(
+                f,
+                "{:?} IV {} must be between 0 and {}",
+                stat, value, MAX_IV
+            ),
+            InstanceError::EvOutOfRange(stat, value) => write!(
+                f,
+                "{:?} EV {} must be between 0 and {}",
+                stat, value, MAX_EV
+            ),
+            InstanceError::EvTotalTooHigh(total) => {
+                write!(f, "EVs total {} but may not exceed {}", total, MAX_EV_TOTAL)
+            }
+            InstanceError::InvalidNickname => write!(
+                f,
+                "nickname must be between 1 and {} characters",
+                MAX_NICKNAME_LENGTH
+            ),
+            InstanceError::InvalidGender(gender) => {
+                write!(f, "this species cannot be {:?}", gender)
+            }
+            InstanceError::HappinessOutOfRange(happiness) => write!(
+                f,
+                "happiness {} must be between 0 and {}",
+                happiness, MAX_HAPPINESS
+            ),
+        }
+    }
+}
+
+fn validate_stats(
+    stats: &Stats,
+    max: u32,
+    error: fn(Stat, u32) -> InstanceError,
+) -> Result<(), InstanceError> {
+    for (stat, value) in stats.iter() {
+        if value > max {
+            return Err(error(stat, value));
+        }
+    }
+
+    Ok(())
+}
+
+fn random_gender<R: Rng>(gender_rate: i32, rng: &mut R) -> Gender {
+    if gender_rate < 0 {
+        Gender::Genderless
+    } else if rng.gen_range(0..8) < gender_rate {
+        Gender::Female
+    } else {
+        Gender::Male
+    }
+}
+
+fn gender_allowed(gender_rate: i32, gender: Gender) -> bool {
+    match gender {
+        Gender::Genderless => gender_rate < 0,
+        Gender::Female => gender_rate > 0,
+        Gender::Male => (0..8).contains(&gender_rate),
+    }
+}
+
+impl Instance {
+    /// Rolls a new instance of `species`, using any values from `changes` in place of random ones.
+    pub fn generate<R: Rng>(
+        species: &Pokemon,
+        changes: InstanceChanges,
+        rng: &mut R,
+    ) -> Result<Instance, InstanceError> {
+        let mut instance = Instance {
+            nature: Nature::ALL[rng.gen_range(0..Nature::ALL.len())],
+            gender: random_gender(species.gender_rate, rng),
+            shiny: rng.gen_range(0..SHINY_ODDS) == 0,
+            ivs: Stats {
+                hp: rng.gen_range(0..=MAX_IV),
+                attack: rng.gen_range(0..=MAX_IV),
+                defense: rng.gen_range(0..=MAX_IV),
+                special_attack: rng.gen_range(0..=MAX_IV),
+                special_defense: rng.gen_range(0..=MAX_IV),
+                speed: rng.gen_range(0..=MAX_IV),
+            },
+            experience: species.growth_rate.experience_for_level(DEFAULT_LEVEL),
+            happiness: species.base_happiness,
+            form: species.instance.form.clone(),
+            ..Instance::default()
+        };
+
+        instance.apply(species, changes)?;
+
+        Ok(instance)
+    }
+
+    /// Validates `changes` against `species` and, only if they're all valid, applies them.
+    pub fn apply(
+        &mut self,
+        species: &Pokemon,
+        changes: InstanceChanges,
+    ) -> Result<(), InstanceError> {
+        if changes.level.is_some() && changes.experience.is_some() {
+            return Err(InstanceError::LevelAndExperience);
+        }
+        if let Some(level) = changes.level {
+            if !(MIN_LEVEL..=MAX_LEVEL).contains(&level) {
+                return Err(InstanceError::LevelOutOfRange(level));
+            }
+        }
+        if let Some(nickname) = &changes.nickname {
+            let length = nickname.chars().count();
+            if length == 0 || length > MAX_NICKNAME_LENGTH {
+                return Err(InstanceError::InvalidNickname);
+            }
+        }
+        if let Some(gender) = changes.gender {
+            if !gender_allowed(species.gender_rate, gender) {
+                return Err(InstanceError::InvalidGender(gender));
+            }
+        }
+        if let Some(happiness) = changes.happiness {
+            if happiness > MAX_HAPPINESS {
+                return Err(InstanceError::HappinessOutOfRange(happiness));
+            }
+        }
+        if let Some(ivs) = &changes.ivs {
+            validate_stats(ivs, MAX_IV, InstanceError::IvOutOfRange)?;
+        }
+        if let Some(evs) = &changes.evs {
+            validate_stats(evs, MAX_EV, InstanceError::EvOutOfRange)?;
+            if evs.total() > MAX_EV_TOTAL {
+                return Err(InstanceError::EvTotalTooHigh(evs.total()));
+            }
+        }
+
+        if let Some(level) = changes.level {
+            self.level = level;
+            self.experience = species.growth_rate.experience_for_level(level);
+        }
+        if let Some(experience) = changes.experience {
+            self.experience = experience.min(species.growth_rate.experience_for_level(MAX_LEVEL));
+            self.level = species.growth_rate.level_for_experience(self.experience);
+        }
+        if changes.nickname.is_some() {
+            self.nickname = changes.nickname;
+        }
+        if let Some(nature) = changes.nature {
+            self.nature = nature;
+        }
+        if let Some(gender) = changes.gender {
+            self.gender = gender;
+        }
+        if let Some(shiny) = changes.shiny {
+            self.shiny = shiny;
+        }
+        if let Some(ivs) = changes.ivs {
+            self.ivs = ivs;
+        }
+        if let Some(evs) = changes.evs {
+            self.evs = evs;
+        }
+        if changes.original_trainer.is_some() {
+            self.original_trainer = changes.original_trainer;
+        }
+        if let Some(met_date) = changes.met_date {
+            self.met_date = met_date;
+        }
+        if let Some(happiness) = changes.happiness {
+            self.happiness = happiness;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(gender_rate: i32, changes: InstanceChanges) -> Result<Instance, InstanceError> {
+        let species: Pokemon = serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": 140,
+            "name": "kabuto",
+            "height": 5,
+            "weight": 115,
+            "base_happiness": 50,
+            "gender_rate": gender_rate,
+            "growth_rate": "medium"
+        }))
+        .unwrap();
+
+        let mut instance = Instance::default();
+        instance.apply(&species, changes).map(|()| instance)
+    }
+
+    fn evs(hp: u32, attack: u32, defense: u32) -> Stats {
+        Stats {
+            hp,
+            attack,
+            defense,
+            ..Stats::default()
+        }
+    }
+
+    #[test]
+    fn applies_valid_changes() {
+        let instance = apply(
+            1,
+            InstanceChanges {
+                level: Some(50),
+                gender: Some(Gender::Female),
+                evs: Some(evs(MAX_EV, MAX_EV, 6)),
+                ..InstanceChanges::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(instance.level, 50);
+        assert_eq!(instance.experience, 125_000);
+        assert_eq!(instance.evs.total(), MAX_EV_TOTAL);
+
+        // Experience decides the level it's enough for.
+        let instance = apply(
+            1,
+            InstanceChanges {
+                experience: Some(1_100),
+                ..InstanceChanges::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((instance.level, instance.experience), (10, 1_100));
+    }
+
+    #[test]
+    fn rejects_levels_out_of_range() {
+        for level in [0, MAX_LEVEL + 1].iter() {
+            let changes = InstanceChanges {
+                level: Some(*level),
+                ..InstanceChanges::default()
+            };
+            assert_eq!(
+                apply(1, changes).unwrap_err(),
+                InstanceError::LevelOutOfRange(*level)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_level_and_experience_together() {
+        let changes = InstanceChanges {
+            level: Some(10),
+            experience: Some(1_000),
+            ..InstanceChanges::default()
+        };
+
+        assert_eq!(
+            apply(1, changes).unwrap_err(),
+            InstanceError::LevelAndExperience
+        );
+    }
+
+    #[test]
+    fn rejects_evs_over_either_cap() {
+        let too_high = InstanceChanges {
+            evs: Some(evs(0, MAX_EV + 1, 0)),
+            ..InstanceChanges::default()
+        };
+        assert_eq!(
+            apply(1, too_high).unwrap_err(),
+            InstanceError::EvOutOfRange(Stat::Attack, MAX_EV + 1)
+        );
+
+        let too_many = InstanceChanges {
+            evs: Some(evs(MAX_EV, MAX_EV, 7)),
+            ..InstanceChanges::default()
+        };
+        assert_eq!(
+            apply(1, too_many).unwrap_err(),
+            InstanceError::EvTotalTooHigh(MAX_EV_TOTAL + 1)
+        );
+    }
+
+    #[test]
+    fn rejects_genders_the_species_cant_have() {
+        // Always male, always female, and genderless.
+        for (gender_rate, gender) in [
+            (0, Gender::Female),
+            (8, Gender::Male),
+            (-1, Gender::Male),
+            (4, Gender::Genderless),
+        ]
+        .iter()
+        {
+            let changes = InstanceChanges {
+                gender: Some(*gender),
+                ..InstanceChanges::default()
+            };
+            assert_eq!(
+                apply(*gender_rate, changes).unwrap_err(),
+                InstanceError::InvalidGender(*gender)
+            );
+        }
+    }
+}
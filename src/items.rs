@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Most of any one item the bag will hold, as in the games.
+pub const MAX_ITEM_COUNT: u32 = 999;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Item {
+    pub name: String,
+    /// PokeAPI's item category, e.g. `standard-balls`, `healing` or `held-items`.
+    pub category: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BagError {
+    NotInBag(String),
+    TooMany(String),
+}
+
+impl fmt::Display for BagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BagError::NotInBag(name) => write!(f, "there's no {} in the bag", name),
+            BagError::TooMany(name) => write!(
+                f,
+                "the bag can't hold more than {} of {}",
+                MAX_ITEM_COUNT, name
+            ),
+        }
+    }
+}
+
+/// A trainer's items, counted by name and grouped by category.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Bag {
+    pockets: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+impl Bag {
+    pub fn count(&self, name: &str) -> u32 {
+        self.pockets
+            .values()
+            .find_map(|pocket| pocket.get(name))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The category of the pocket holding the item called `name`, if there's one in the bag.
+    pub fn category(&self, name: &str) -> Option<&str> {
+        self.pockets
+            .iter()
+            .find(|(_, pocket)| pocket.contains_key(name))
+            .map(|(category, _)| category.as_str())
+    }
+
+    /// Errors if `count` more of `item` wouldn't fit, without changing anything.
+    pub fn check_room(&self, item: &Item, count: u32) -> Result<(), BagError> {
+        let total = self.count(&item.name).checked_add(count);
+        if total.is_none_or(|total| total > MAX_ITEM_COUNT) {
+            Err(BagError::TooMany(item.name.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Adds `count` of `item`, returning how many the bag now holds.
+    pub fn add(&mut self, item: &Item, count: u32) -> Result<u32, BagError> {
+        self.check_room(item, count)?;
+
+        let held = self
+            .pockets
+            .entry(item.category.clone())
+            .or_default()
+            .entry(item.name.clone())
+            .or_insert(0);
+        *held += count;
+
+        Ok(*held)
+    }
+
+    /// Takes one of the item called `name` out of the bag.
+    pub fn take(&mut self, name: &str) -> Result<Item, BagError> {
+        let (category, pocket) = self
+            .pockets
+            .iter_mut()
+            .find(|(_, pocket)| pocket.contains_key(name))
+            .ok_or_else(|| BagError::NotInBag(name.to_string()))?;
+        let item = Item {
+            name: name.to_string(),
+            category: category.clone(),
+        };
+
+        let held = pocket.get_mut(name).unwrap();
+        *held -= 1;
+        if *held == 0 {
+            pocket.remove(name);
+        }
+        if pocket.is_empty() {
+            self.pockets.remove(&item.category);
+        }
+
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn potion() -> Item {
+        Item {
+            name: "potion".to_string(),
+            category: "healing".to_string(),
+        }
+    }
+
+    #[test]
+    fn refuses_more_than_the_limit() {
+        let mut bag = Bag::default();
+        assert_eq!(
+            bag.add(&potion(), MAX_ITEM_COUNT - 1),
+            Ok(MAX_ITEM_COUNT - 1)
+        );
+        assert_eq!(bag.add(&potion(), 1), Ok(MAX_ITEM_COUNT));
+
+        let too_many = Err(BagError::TooMany("potion".to_string()));
+        assert_eq!(bag.add(&potion(), 1), too_many);
+        // Big enough to overflow the count rather than just go past the limit.
+        assert_eq!(bag.add(&potion(), u32::MAX), too_many);
+        assert_eq!(bag.count("potion"), MAX_ITEM_COUNT);
+    }
+
+    #[test]
+    fn empties_pockets_as_items_are_taken() {
+        let mut bag = Bag::default();
+        bag.add(&potion(), 1).unwrap();
+        assert_eq!(bag.category("potion"), Some("healing"));
+
+        assert_eq!(bag.take("potion"), Ok(potion()));
+        assert_eq!(
+            bag.take("potion"),
+            Err(BagError::NotInBag("potion".to_string()))
+        );
+        assert_eq!(bag.category("potion"), None);
+    }
+}
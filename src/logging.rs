@@ -0,0 +1,117 @@
+use log::Record;
+use serde::Serialize;
+
+#[cfg(not(test))]
+use crate::config::LogConfig;
+use crate::config::LogFormat;
+
+tokio::task_local! {
+    /// The id of the request the current task is serving, set by the `request_id` middleware.
+    pub static REQUEST_ID: String;
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: &'a str,
+    level: &'a str,
+    target: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    message: String,
+}
+
+/// Formats one log line, tagged with the id of the request being served if there is one.
+fn line(format: LogFormat, timestamp: &str, record: &Record) -> String {
+    let request_id = current_request_id();
+
+    match format {
+        LogFormat::Text => format!(
+            "[{} {:<5} {}{}] {}",
+            timestamp,
+            record.level(),
+            record.target(),
+            request_id.map(|id| format!(" {}", id)).unwrap_or_default(),
+            record.args()
+        ),
+        LogFormat::Json => serde_json::to_string(&JsonLine {
+            timestamp,
+            level: record.level().as_str(),
+            target: record.target(),
+            request_id,
+            message: record.args().to_string(),
+        })
+        .unwrap(),
+    }
+}
+
+#[cfg(not(test))]
+pub fn init(config: &LogConfig) {
+    use std::io::Write;
+
+    let format = config.format;
+    env_logger::from_env(env_logger::Env::default().default_filter_or(&config.level))
+        .format(move |buf, record| {
+            writeln!(
+                buf,
+                "{}",
+                line(format, &buf.timestamp().to_string(), record)
+            )
+        })
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn record_line(format: LogFormat) -> String {
+        line(
+            format,
+            "2020-01-01T00:00:00Z",
+            &Record::builder()
+                .level(Level::Info)
+                .target("zed::app")
+                .args(format_args!("caught a kabuto"))
+                .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn lines_carry_the_request_id_being_served() {
+        let text = REQUEST_ID
+            .scope("abc-123".to_string(), async {
+                record_line(LogFormat::Text)
+            })
+            .await;
+        assert_eq!(
+            text,
+            "[2020-01-01T00:00:00Z INFO  zed::app abc-123] caught a kabuto"
+        );
+
+        let json = REQUEST_ID
+            .scope("abc-123".to_string(), async {
+                record_line(LogFormat::Json)
+            })
+            .await;
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["request_id"], "abc-123");
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "caught a kabuto");
+    }
+
+    #[test]
+    fn lines_outside_a_request_have_no_request_id() {
+        assert_eq!(
+            record_line(LogFormat::Text),
+            "[2020-01-01T00:00:00Z INFO  zed::app] caught a kabuto"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&record_line(LogFormat::Json)).unwrap();
+        assert!(json.get("request_id").is_none());
+    }
+}
@@ -1,17 +1,102 @@
 use dotenv::dotenv;
 
 pub mod app;
+mod battle;
+mod cache;
+mod config;
 mod context;
+mod coverage;
+mod dataset;
+mod day_care;
 mod errors;
+mod evolution;
+mod forms;
+mod health;
+mod in_flight;
+mod instance;
+mod items;
+mod logging;
+mod moves;
+mod names;
+mod nuzlocke;
+mod openapi;
+mod persistence;
+mod pokedex;
 mod pokemon;
 mod pokemon_api;
+mod router;
+mod rules;
+mod server;
+mod station;
+mod stats;
 mod storage;
+mod trading;
+mod trainers;
+mod types;
+mod wild;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    env_logger::init();
 
     #[cfg(not(test))]
-    app::init().await;
+    {
+        let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+        // `zed import <dump directory> [flags]` imports a PokeAPI data dump instead of serving.
+        let import_from = if args.first().map(String::as_str) == Some("import") {
+            if args.len() < 2 {
+                eprintln!("Usage: zed import <PokeAPI data directory> [flags]");
+                std::process::exit(1);
+            }
+            args.remove(0);
+            Some(std::path::PathBuf::from(args.remove(0)))
+        } else {
+            None
+        };
+
+        let config = match config::Config::load(args.into_iter()) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Invalid configuration: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        logging::init(&config.log);
+
+        match import_from {
+            Some(dir) => {
+                if let Err(e) = import(&dir, &config) {
+                    eprintln!("Import failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                if let Err(e) = app::init(config).await {
+                    eprintln!("Failed to start: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn import(dir: &std::path::Path, config: &config::Config) -> Result<(), anyhow::Error> {
+    use log::info;
+
+    let path = config.pokeapi.dataset_path.as_ref().ok_or_else(|| {
+        anyhow::anyhow!("set pokeapi.dataset_path to say where the dataset should go")
+    })?;
+
+    let dataset = dataset::Dataset::import(dir)?;
+    dataset.save(path)?;
+    info!(
+        "Imported {} pokemon of {} species into {}",
+        dataset.pokemon_count(),
+        dataset.species_count(),
+        path.display()
+    );
+
+    Ok(())
 }
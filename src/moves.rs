@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const MAX_MOVES: usize = 4;
+
+/// A way a species can learn a move within a single version group.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LearnableMove {
+    pub name: String,
+    /// PokeAPI's learn method, e.g. `level-up`, `machine`, `egg` or `tutor`.
+    pub method: String,
+    /// The level the move is learnt at for `level-up`, otherwise 0.
+    pub level: u32,
+}
+
+/// A move a pokemon knows, with the PP it has left.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MoveSlot {
+    pub name: String,
+    pub pp: u32,
+    pub max_pp: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+    NotLearnable(String),
+    LevelTooLow { name: String, level: u32 },
+    AlreadyKnown(String),
+    NotKnown(String),
+    TooManyMoves,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NotLearnable(name) => {
+                write!(f, "{} can't be learnt in this version group", name)
+            }
+            MoveError::LevelTooLow { name, level } => {
+                write!(f, "{} isn't learnt until level {}", name, level)
+            }
+            MoveError::AlreadyKnown(name) => write!(f, "{} is already known", name),
+            MoveError::NotKnown(name) => write!(f, "{} isn't a known move", name),
+            MoveError::TooManyMoves => write!(
+                f,
+                "a pokemon can only know {} moves, pick one to replace",
+                MAX_MOVES
+            ),
+        }
+    }
+}
+
+/// Checks that a pokemon at `level` can learn `name` according to `learnset`. Moves learnt by
+/// levelling up need the level to have been reached; any other method is always allowed.
+pub fn check_learnable(
+    learnset: &[LearnableMove],
+    name: &str,
+    level: u32,
+) -> Result<(), MoveError> {
+    let ways = learnset
+        .iter()
+        .filter(|learnable| learnable.name == name)
+        .collect::<Vec<&LearnableMove>>();
+
+    if ways.is_empty() {
+        return Err(MoveError::NotLearnable(name.to_string()));
+    }
+
+    let learnable_now = ways
+        .iter()
+        .any(|learnable| learnable.method != "level-up" || learnable.level <= level);
+    if !learnable_now {
+        return Err(MoveError::LevelTooLow {
+            name: name.to_string(),
+            level: ways.iter().map(|learnable| learnable.level).min().unwrap(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Adds `slot` to `moves`, taking the place of `replace` if given. Learnability should already have
+/// been checked with `check_learnable`.
+pub fn teach(
+    moves: &mut Vec<MoveSlot>,
+    slot: MoveSlot,
+    replace: Option<&str>,
+) -> Result<(), MoveError> {
+    if moves.iter().any(|known| known.name == slot.name) {
+        return Err(MoveError::AlreadyKnown(slot.name));
+    }
+
+    match replace {
+        Some(replace) => {
+            let index = moves
+                .iter()
+                .position(|known| known.name == replace)
+                .ok_or_else(|| MoveError::NotKnown(replace.to_string()))?;
+
+            moves[index] = slot;
+        }
+        None if moves.len() >= MAX_MOVES => return Err(MoveError::TooManyMoves),
+        None => moves.push(slot),
+    }
+
+    Ok(())
+}
+
+pub fn forget(moves: &mut Vec<MoveSlot>, name: &str) -> Result<(), MoveError> {
+    let index = moves
+        .iter()
+        .position(|known| known.name == name)
+        .ok_or_else(|| MoveError::NotKnown(name.to_string()))?;
+
+    moves.remove(index);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learnable(name: &str, method: &str, level: u32) -> LearnableMove {
+        LearnableMove {
+            name: name.to_string(),
+            method: method.to_string(),
+            level,
+        }
+    }
+
+    fn slot(name: &str) -> MoveSlot {
+        MoveSlot {
+            name: name.to_string(),
+            pp: 10,
+            max_pp: 10,
+        }
+    }
+
+    #[test]
+    fn level_up_moves_wait_for_their_level() {
+        let learnset = vec![
+            learnable("absorb", "level-up", 1),
+            learnable("mud-shot", "level-up", 26),
+            learnable("mud-shot", "level-up", 31),
+            learnable("ancient-power", "egg", 0),
+        ];
+
+        assert_eq!(check_learnable(&learnset, "absorb", 1), Ok(()));
+        assert_eq!(check_learnable(&learnset, "ancient-power", 1), Ok(()));
+        assert_eq!(check_learnable(&learnset, "mud-shot", 26), Ok(()));
+        assert_eq!(
+            check_learnable(&learnset, "mud-shot", 25),
+            Err(MoveError::LevelTooLow {
+                name: "mud-shot".to_string(),
+                level: 26
+            })
+        );
+        assert_eq!(
+            check_learnable(&learnset, "surf", 100),
+            Err(MoveError::NotLearnable("surf".to_string()))
+        );
+    }
+
+    #[test]
+    fn teaching_a_fifth_move_needs_one_replaced() {
+        let mut moves = ["scratch", "harden", "absorb", "leer"]
+            .iter()
+            .map(|name| slot(name))
+            .collect::<Vec<MoveSlot>>();
+
+        assert_eq!(
+            teach(&mut moves, slot("mud-shot"), None),
+            Err(MoveError::TooManyMoves)
+        );
+        assert_eq!(
+            teach(&mut moves, slot("mud-shot"), Some("surf")),
+            Err(MoveError::NotKnown("surf".to_string()))
+        );
+        assert_eq!(
+            teach(&mut moves, slot("absorb"), Some("leer")),
+            Err(MoveError::AlreadyKnown("absorb".to_string()))
+        );
+
+        teach(&mut moves, slot("mud-shot"), Some("harden")).unwrap();
+        let names = moves
+            .iter()
+            .map(|known| known.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["scratch", "mud-shot", "absorb", "leer"]);
+    }
+
+    #[test]
+    fn forgets_only_known_moves() {
+        let mut moves = vec![slot("scratch"), slot("harden")];
+
+        forget(&mut moves, "scratch").unwrap();
+        assert_eq!(moves, vec![slot("harden")]);
+        assert_eq!(
+            forget(&mut moves, "scratch"),
+            Err(MoveError::NotKnown("scratch".to_string()))
+        );
+
+        teach(&mut moves, slot("absorb"), None).unwrap();
+        assert_eq!(moves.len(), 2);
+    }
+}
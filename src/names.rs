@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Most "did you mean" suggestions given for a misspelled name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Turns a name the way people write it into the way PokeAPI does, e.g. `Mr. Mime` into
+/// `mr-mime` and `Farfetch'd` into `farfetchd`.
+pub fn normalize(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '.' | '\'' | '’' | ':'))
+        .map(|c| {
+            if c.is_whitespace() || c == '_' {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+/// How many single character edits turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownName {
+    pub name: String,
+    /// Known names close to it, closest first.
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "there's no pokemon called {}", self.name)?;
+
+        match self.suggestions.split_last() {
+            Some((last, [])) => write!(f, ", did you mean {}?", last),
+            Some((last, rest)) => write!(f, ", did you mean {} or {}?", rest.join(", "), last),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Every species and form name PokeAPI knows, e.g. `giratina` and `giratina-origin`, with the id
+/// of the pokemon each stands for.
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    ids: BTreeMap<String, u32>,
+}
+
+impl NameIndex {
+    pub fn new<I: IntoIterator<Item = (String, u32)>>(names: I) -> Self {
+        NameIndex {
+            ids: names
+                .into_iter()
+                .map(|(name, id)| (normalize(&name), id))
+                .collect(),
+        }
+    }
+
+    /// The id of the pokemon called `name`, ignoring case and punctuation.
+    pub fn find(&self, name: &str) -> Result<u32, UnknownName> {
+        let name = normalize(name);
+        if let Some(id) = self.ids.get(&name) {
+            return Ok(*id);
+        }
+
+        // Allow about one typo per three letters, and always at least two.
+        let tolerance = (name.chars().count() / 3).max(2);
+        let mut close = self
+            .ids
+            .keys()
+            .map(|known| (distance(&name, known), known))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .collect::<Vec<(usize, &String)>>();
+        close.sort();
+
+        Err(UnknownName {
+            suggestions: close
+                .into_iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(_, known)| known.clone())
+                .collect(),
+            name,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> NameIndex {
+        NameIndex::new(vec![
+            ("kabuto".to_string(), 140),
+            ("kabutops".to_string(), 141),
+            ("mr-mime".to_string(), 122),
+            ("giratina".to_string(), 487),
+            ("giratina-origin".to_string(), 10007),
+        ])
+    }
+
+    #[test]
+    fn finds_names_however_they_are_written() {
+        let index = index();
+
+        assert_eq!(index.find("Kabuto"), Ok(140));
+        assert_eq!(index.find("  Mr. Mime "), Ok(122));
+        assert_eq!(index.find("GIRATINA_origin"), Ok(10007));
+    }
+
+    #[test]
+    fn suggests_close_names_for_typos() {
+        let unknown = index().find("kabutop").unwrap_err();
+
+        assert_eq!(unknown.suggestions, vec!["kabuto", "kabutops"]);
+        assert_eq!(
+            unknown.to_string(),
+            "there's no pokemon called kabutop, did you mean kabuto or kabutops?"
+        );
+        assert_eq!(index().find("charizard").unwrap_err().suggestions.len(), 0);
+    }
+}
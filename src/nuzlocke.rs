@@ -0,0 +1,221 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Caught,
+    /// It got away or was knocked out, and the area is used up either way.
+    Missed,
+    /// A species already caught this run, which under the dupes clause leaves the area open.
+    Dupe,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Encounter {
+    pub area: String,
+    pub species: String,
+    pub species_id: u32,
+    pub outcome: Outcome,
+    /// The pokemon that was caught, for `Caught`.
+    pub pokemon_id: Option<u32>,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NuzlockeError {
+    NotRunning,
+    AlreadyRunning,
+    AreaUsed { area: String, species: String },
+    NicknameRequired,
+    EncounterRequired,
+    InGraveyard,
+}
+
+impl fmt::Display for NuzlockeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NuzlockeError::NotRunning => write!(f, "no nuzlocke run is active"),
+            NuzlockeError::AlreadyRunning => write!(f, "a nuzlocke run is already active"),
+            NuzlockeError::AreaUsed { area, species } => write!(
+                f,
+                "the encounter for {} was already used on {}",
+                area, species
+            ),
+            NuzlockeError::NicknameRequired => {
+                write!(f, "pokemon caught during a nuzlocke run must be nicknamed")
+            }
+            NuzlockeError::EncounterRequired => write!(
+                f,
+                "during a nuzlocke run pokemon are caught by recording an encounter"
+            ),
+            NuzlockeError::InGraveyard => write!(
+                f,
+                "pokemon only enter the graveyard by fainting, and stay there as they were"
+            ),
+        }
+    }
+}
+
+/// A nuzlocke challenge in progress: one encounter per area, nicknames for everything caught, and
+/// any pokemon that faints is gone for good.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Run {
+    pub started: NaiveDate,
+    pub dupes_clause: bool,
+    /// The box fainted pokemon are moved to.
+    pub graveyard: usize,
+    /// In the order they happened.
+    pub encounters: Vec<Encounter>,
+    /// Ids of pokemon that have fainted, in the order they did.
+    pub fainted: Vec<u32>,
+}
+
+impl Run {
+    pub fn new(graveyard: usize, dupes_clause: bool) -> Self {
+        Run {
+            started: Utc::now().naive_utc().date(),
+            dupes_clause,
+            graveyard,
+            encounters: vec![],
+            fainted: vec![],
+        }
+    }
+
+    /// Ids of every pokemon caught during the run.
+    pub fn caught(&self) -> impl Iterator<Item = u32> + '_ {
+        self.encounters
+            .iter()
+            .filter_map(|encounter| encounter.pokemon_id)
+    }
+
+    /// Works out what an encounter with `species_id` in `area` comes to, or why it isn't allowed.
+    pub fn check_encounter(
+        &self,
+        area: &str,
+        species_id: u32,
+        caught: bool,
+        nickname: Option<&str>,
+    ) -> Result<Outcome, NuzlockeError> {
+        if let Some(used) = self
+            .encounters
+            .iter()
+            .find(|encounter| encounter.area == area && encounter.outcome != Outcome::Dupe)
+        {
+            return Err(NuzlockeError::AreaUsed {
+                area: area.to_string(),
+                species: used.species.clone(),
+            });
+        }
+
+        let dupe = self.dupes_clause
+            && self.encounters.iter().any(|encounter| {
+                encounter.outcome == Outcome::Caught && encounter.species_id == species_id
+            });
+
+        match (dupe, caught) {
+            (true, _) => Ok(Outcome::Dupe),
+            (false, true) if nickname.is_none() => Err(NuzlockeError::NicknameRequired),
+            (false, true) => Ok(Outcome::Caught),
+            (false, false) => Ok(Outcome::Missed),
+        }
+    }
+
+    /// Where the encounter with a wild `species_id` in `area` was recorded when it appeared, if it
+    /// hasn't been caught yet.
+    pub fn pending_encounter(&self, area: &str, species_id: u32) -> Option<usize> {
+        self.encounters.iter().rposition(|encounter| {
+            encounter.area == area
+                && encounter.species_id == species_id
+                && encounter.outcome != Outcome::Caught
+        })
+    }
+}
+
+/// How a run is going.
+#[derive(Debug, Serialize)]
+pub struct Status<'a> {
+    pub started: NaiveDate,
+    pub dupes_clause: bool,
+    pub graveyard_box: usize,
+    /// Areas whose encounter has been used.
+    pub areas: usize,
+    pub caught: usize,
+    pub fainted: usize,
+    pub alive: usize,
+    /// Everything caught this run has fainted or been released.
+    pub wiped: bool,
+    pub encounters: &'a [Encounter],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encounter(area: &str, species_id: u32, outcome: Outcome) -> Encounter {
+        Encounter {
+            area: area.to_string(),
+            species: format!("species-{}", species_id),
+            species_id,
+            outcome,
+            pokemon_id: if outcome == Outcome::Caught {
+                Some(species_id)
+            } else {
+                None
+            },
+            date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn allows_one_encounter_per_area() {
+        let mut run = Run::new(0, true);
+        run.encounters = vec![
+            encounter("route-1", 16, Outcome::Caught),
+            encounter("route-2", 19, Outcome::Missed),
+            encounter("route-3", 16, Outcome::Dupe),
+        ];
+
+        assert_eq!(
+            run.check_encounter("route-1", 19, false, None),
+            Err(NuzlockeError::AreaUsed {
+                area: "route-1".to_string(),
+                species: "species-16".to_string()
+            })
+        );
+        assert!(run
+            .check_encounter("route-2", 10, true, Some("Bug"))
+            .is_err());
+        // A dupe leaves the area open.
+        assert_eq!(
+            run.check_encounter("route-3", 10, true, Some("Bug")),
+            Ok(Outcome::Caught)
+        );
+    }
+
+    #[test]
+    fn applies_the_dupes_clause_and_nicknames() {
+        let mut run = Run::new(0, true);
+        run.encounters = vec![encounter("route-1", 16, Outcome::Caught)];
+
+        assert_eq!(
+            run.check_encounter("route-2", 16, true, Some("Again")),
+            Ok(Outcome::Dupe)
+        );
+        assert_eq!(
+            run.check_encounter("route-2", 19, true, None),
+            Err(NuzlockeError::NicknameRequired)
+        );
+        assert_eq!(
+            run.check_encounter("route-2", 19, false, None),
+            Ok(Outcome::Missed)
+        );
+
+        run.dupes_clause = false;
+        assert_eq!(
+            run.check_encounter("route-2", 16, true, Some("Again")),
+            Ok(Outcome::Caught)
+        );
+    }
+}
@@ -0,0 +1,1459 @@
+use serde_json::{json, Map, Value};
+
+use crate::instance::Nature;
+use crate::types::Type;
+
+/// Documentation for a single route registered in `app::create`. Every registered route needs an
+/// entry here; the tests below fail if the two drift apart.
+pub struct RouteDoc {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub request: Option<&'static str>,
+    pub response: &'static str,
+    pub errors: &'static [u16],
+}
+
+pub const ROUTES: &[RouteDoc] = &[
+    RouteDoc {
+        method: "POST",
+        path: "/boxes",
+        summary: "Create a new, empty box",
+        request: None,
+        response: "CreateBoxResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/boxes/:id",
+        summary: "List the pokemon in a box",
+        request: None,
+        response: "GetBoxResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/parties",
+        summary: "List the pokemon in the party",
+        request: None,
+        response: "GetBoxResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/parties/analysis",
+        summary: "Analyse the party's type coverage and weaknesses, suggesting boxed pokemon to patch the biggest hole",
+        request: None,
+        response: "PartyAnalysisResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/parties/validate",
+        summary: "Check a proposed party of stored pokemon against the configured rules without changing anything",
+        request: Some("ValidatePartyRequest"),
+        response: "ValidatePartyResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/battles",
+        summary: "Simulate a singles battle between two teams of stored pokemon, returning the turn by turn log",
+        request: Some("BattleRequest"),
+        response: "Battle",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/boxes/:id/pokemon",
+        summary: "Move a stored pokemon into a box by id, or catch a new one by pokeAPI_id or name",
+        request: Some("MovePokemonRequest"),
+        response: "StorePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/parties/pokemon",
+        summary: "Move a stored pokemon into the party by id, or catch a new one by pokeAPI_id or name",
+        request: Some("MovePokemonRequest"),
+        response: "StorePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon",
+        summary: "Catch or move a pokemon into the party if there's room, otherwise the first box with space",
+        request: Some("MovePokemonRequest"),
+        response: "StorePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/pokemon/:id",
+        summary: "Get a stored pokemon along with its actual stats",
+        request: None,
+        response: "GetPokemonResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "PATCH",
+        path: "/pokemon/:id",
+        summary: "Edit a stored pokemon's nickname, level, nature and other per-pokemon details",
+        request: Some("EditPokemonRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/pokemon/:id",
+        summary: "Release a stored pokemon, putting any held item back in the bag",
+        request: None,
+        response: "ReleasePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon/:id/item",
+        summary: "Give a stored pokemon an item from the bag to hold",
+        request: Some("GiveItemRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "PUT",
+        path: "/pokemon/:id/item",
+        summary: "Give a stored pokemon an item from the bag, swapping out anything it held",
+        request: Some("GiveItemRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/pokemon/:id/item",
+        summary: "Take a stored pokemon's held item back into the bag",
+        request: None,
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/pokemon/:id/moves/learnable",
+        summary: "List the moves a stored pokemon's species can learn in ?version_group=",
+        request: None,
+        response: "LearnableMovesResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon/:id/moves",
+        summary: "Teach a stored pokemon a move, optionally replacing one it knows",
+        request: Some("TeachMoveRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/pokemon/:id/moves/:move",
+        summary: "Make a stored pokemon forget a move",
+        request: None,
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon/:id/evolve",
+        summary: "Evolve a stored pokemon along its PokeAPI evolution chain",
+        request: Some("EvolvePokemonRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon/:id/form",
+        summary: "Change a stored pokemon into another form of its species",
+        request: Some("ChangeFormRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/bag",
+        summary: "List the items in the bag by category",
+        request: None,
+        response: "GetBagResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/bag/items",
+        summary: "Add items to the bag, checking they exist in PokeAPI",
+        request: Some("AddItemRequest"),
+        response: "AddItemResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/pokedex",
+        summary: "Report how many species have been seen and caught, nationally and by generation",
+        request: None,
+        response: "GetPokedexResponse",
+        errors: &[502],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/pokedex/:dex",
+        summary: "Report completion of a PokeAPI pokedex, e.g. national or kanto, and the species still missing",
+        request: None,
+        response: "GetDexCompletionResponse",
+        errors: &[404, 502],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/nuzlocke",
+        summary: "Start a nuzlocke run, adding a graveyard box for fainted pokemon",
+        request: Some("StartNuzlockeRequest"),
+        response: "NuzlockeStatus",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/nuzlocke",
+        summary: "Report how the nuzlocke run is going",
+        request: None,
+        response: "NuzlockeStatus",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/nuzlocke",
+        summary: "End the nuzlocke run, reporting how it finished",
+        request: None,
+        response: "NuzlockeStatus",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/nuzlocke/encounters",
+        summary: "Record the run's encounter in an area, storing the pokemon if it was caught",
+        request: Some("RecordEncounterRequest"),
+        response: "RecordEncounterResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokemon/:id/faint",
+        summary: "Mark a pokemon as fainted during a nuzlocke run, moving it to the graveyard",
+        request: None,
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/day-care",
+        summary: "Show the pokemon at the day care, how much they've grown and whether they've left an egg",
+        request: None,
+        response: "DayCareStatus",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/day-care/pokemon",
+        summary: "Leave a stored pokemon at the day care; move it to the party or a box to withdraw it",
+        request: Some("DepositPokemonRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/day-care/egg",
+        summary: "Collect the egg the day care's pair has left",
+        request: None,
+        response: "EggResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/eggs",
+        summary: "List the eggs waiting to hatch",
+        request: None,
+        response: "GetEggsResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/eggs/walk",
+        summary: "Walk some steps, counting them towards hatching every egg",
+        request: Some("WalkRequest"),
+        response: "GetEggsResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/eggs/:id/hatch",
+        summary: "Hatch an egg that has done its steps into the party or a box",
+        request: Some("HatchEggRequest"),
+        response: "MovePokemonResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/wild",
+        summary: "Show the wild pokemon being faced",
+        request: None,
+        response: "WildEncounterResponse",
+        errors: &[404],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/wild",
+        summary: "Encounter a random wild pokemon in a location area, using PokeAPI's encounter rates and levels",
+        request: Some("WildEncounterRequest"),
+        response: "WildEncounterResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/wild/catch",
+        summary: "Throw a ball from the bag at the wild pokemon, storing it in the party or the first box with space if it's caught",
+        request: Some("CatchRequest"),
+        response: "CatchResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/trainers",
+        summary: "List the trainers with storage on the server",
+        request: None,
+        response: "TrainersResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/trainers",
+        summary: "Add a trainer with empty storage, configured like the default trainer's",
+        request: Some("AddTrainerRequest"),
+        response: "AddTrainerResponse",
+        errors: &[400, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/trades",
+        summary: "List the open trades the trainer is part of",
+        request: None,
+        response: "TradesResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/trades",
+        summary: "Offer another trainer some of your pokemon for some of theirs, holding yours until the trade is done",
+        request: Some("OfferTradeRequest"),
+        response: "TradeResponse",
+        errors: &[400, 404, 409],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/trades/history",
+        summary: "List the trainer's completed, cancelled and expired trades",
+        request: None,
+        response: "TradesResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/trades/:id",
+        summary: "Get an open or closed trade the trainer is part of",
+        request: None,
+        response: "TradeResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/trades/:id",
+        summary: "Cancel an open trade, letting go of the pokemon held for it",
+        request: None,
+        response: "TradeResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/trades/:id/confirm",
+        summary: "Confirm your side of a trade; once both have, the pokemon are swapped and any that evolve by trading do",
+        request: Some("ConfirmTradeRequest"),
+        response: "TradeResponse",
+        errors: &[400, 404, 409, 502],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/station",
+        summary: "List the pokemon on the trade station, oldest first, filtered by the species, wanted, min_level, max_level and trainer query parameters",
+        request: None,
+        response: "ListingsResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/station",
+        summary: "Deposit a boxed pokemon on the trade station for anyone with the species and levels you want",
+        request: Some("ListPokemonRequest"),
+        response: "ListingResponse",
+        errors: &[400, 404, 409, 502],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/station/:id",
+        summary: "Get a trade station listing",
+        request: None,
+        response: "ListingResponse",
+        errors: &[400, 404],
+    },
+    RouteDoc {
+        method: "DELETE",
+        path: "/station/:id",
+        summary: "Withdraw your listing, putting the pokemon back in its box or the first with room",
+        request: None,
+        response: "StorePokemonResponse",
+        errors: &[400, 403, 404, 409],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/station/:id/fulfil",
+        summary: "Trade one of your pokemon for a listed one, which has to be what it wants; any that evolve by trading do",
+        request: Some("FulfilListingRequest"),
+        response: "FulfilListingResponse",
+        errors: &[400, 404, 409, 502],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/info",
+        summary: "Write the current state of storage to the server logs",
+        request: None,
+        response: "Text",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/healthz",
+        summary: "Liveness check",
+        request: None,
+        response: "HealthzResponse",
+        errors: &[],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/readyz",
+        summary: "Readiness check, including storage, persistence and PokeAPI",
+        request: None,
+        response: "Readiness",
+        errors: &[503],
+    },
+    RouteDoc {
+        method: "POST",
+        path: "/pokeapi/prefetch",
+        summary: "Fetch pokemon from PokeAPI ahead of time to warm the cache",
+        request: Some("PrefetchRequest"),
+        response: "PrefetchResponse",
+        errors: &[400],
+    },
+    RouteDoc {
+        method: "GET",
+        path: "/openapi.json",
+        summary: "This document",
+        request: None,
+        response: "OpenApi",
+        errors: &[],
+    },
+];
+
+fn error_description(status: u16) -> &'static str {
+    match status {
+        400 => "The request could not be parsed or was invalid",
+        401 => "Unauthorized",
+        403 => "Only the trainer it belongs to can do that",
+        404 => "Not found",
+        409 => "The destination was full, or the request conflicts with how things are now",
+        502 => "PokeAPI couldn't be reached or gave an unexpected answer",
+        503 => "Not ready to serve traffic",
+        _ => "Error",
+    }
+}
+
+fn schemas() -> Value {
+    let natures = Nature::ALL
+        .iter()
+        .map(|nature| serde_json::to_value(nature).unwrap())
+        .collect::<Vec<Value>>();
+    let check = json!({
+        "type": "object",
+        "properties": {
+            "ok": { "type": "boolean" },
+            "detail": { "type": "string" }
+        }
+    });
+
+    let stats = json!({ "$ref": "#/components/schemas/Stats" });
+    let instance = json!({
+        "nickname": { "type": "string", "nullable": true, "maxLength": 12 },
+        "level": { "type": "integer", "minimum": 1, "maximum": 100 },
+        "experience": { "type": "integer" },
+        "nature": { "type": "string", "enum": natures },
+        "gender": { "type": "string", "enum": ["male", "female", "genderless"] },
+        "shiny": { "type": "boolean" },
+        "ivs": stats,
+        "evs": stats,
+        "original_trainer": { "type": "string", "nullable": true },
+        "met_date": { "type": "string", "format": "date" },
+        "happiness": { "type": "integer", "minimum": 0, "maximum": 255 }
+    });
+    let item = json!({ "$ref": "#/components/schemas/Item" });
+    let bag = json!({
+        "type": "object",
+        "description": "Item counts by name, grouped by category",
+        "additionalProperties": {
+            "type": "object",
+            "additionalProperties": { "type": "integer" }
+        }
+    });
+    let move_slot = json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "pp": { "type": "integer" },
+            "max_pp": { "type": "integer" }
+        }
+    });
+    let completion = json!({
+        "total": { "type": "integer" },
+        "seen": { "type": "integer" },
+        "caught": { "type": "integer" },
+        "percent": { "type": "number", "description": "Percentage caught, to one decimal place" }
+    });
+    let mut generation = json!({
+        "generation": { "type": "string" }
+    });
+    let mut dex_completion = json!({
+        "pokedex": { "type": "string" },
+        "missing": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "number": { "type": "integer" },
+                    "species": { "type": "string" },
+                    "species_id": { "type": "integer" }
+                }
+            }
+        }
+    });
+    for (name, schema) in completion.as_object().unwrap() {
+        generation[name] = schema.clone();
+        dex_completion[name] = schema.clone();
+    }
+    let types = json!({
+        "type": "array",
+        "items": {
+            "type": "string",
+            "enum": Type::ALL
+                .iter()
+                .map(|type_| serde_json::to_value(type_).unwrap())
+                .collect::<Vec<Value>>()
+        }
+    });
+    let party_analysis = json!({
+        "type": "object",
+        "description": "Each party member's own types stand in for its moves.",
+        "properties": {
+            "covered": types,
+            "uncovered": types,
+            "defense": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "type": "string" },
+                        "weak": { "type": "integer" },
+                        "resistant": { "type": "integer" },
+                        "immune": { "type": "integer" }
+                    }
+                }
+            },
+            "shared_weaknesses": types,
+            "resistances": types,
+            "immunities": types,
+            "biggest_hole": {
+                "type": "object",
+                "nullable": true,
+                "properties": {
+                    "kind": { "type": "string", "enum": ["weakness", "coverage"] },
+                    "type": { "type": "string" }
+                }
+            },
+            "suggestions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "name": { "type": "string" },
+                        "types": { "type": "array", "items": { "type": "string" } },
+                        "multiplier": { "type": "number", "description": "Damage taken from the weakness, or dealt to the uncovered type" }
+                    }
+                }
+            }
+        }
+    });
+    let rules = json!({
+        "type": "object",
+        "properties": {
+            "species_clause": { "type": "boolean" },
+            "item_clause": { "type": "boolean" },
+            "level_cap": { "type": "integer", "nullable": true },
+            "ban_legendaries": { "type": "boolean" },
+            "banned_species": { "type": "array", "items": { "type": "string" } }
+        }
+    });
+    let location = json!({
+        "oneOf": [
+            { "type": "string", "enum": ["party", "day_care"] },
+            {
+                "type": "object",
+                "properties": {
+                    "box": { "type": "integer" }
+                }
+            }
+        ]
+    });
+    let encounter = json!({
+        "type": "object",
+        "properties": {
+            "area": { "type": "string" },
+            "species": { "type": "string" },
+            "species_id": { "type": "integer" },
+            "outcome": { "type": "string", "enum": ["caught", "missed", "dupe"] },
+            "pokemon_id": { "type": "integer", "nullable": true },
+            "date": { "type": "string", "format": "date" }
+        }
+    });
+    let nuzlocke_status = json!({
+        "type": "object",
+        "properties": {
+            "started": { "type": "string", "format": "date" },
+            "dupes_clause": { "type": "boolean" },
+            "graveyard_box": { "type": "integer" },
+            "areas": { "type": "integer", "description": "Areas whose encounter has been used" },
+            "caught": { "type": "integer" },
+            "fainted": { "type": "integer" },
+            "alive": { "type": "integer" },
+            "wiped": { "type": "boolean", "description": "Everything caught this run has fainted or been released" },
+            "encounters": { "type": "array", "items": encounter }
+        }
+    });
+    let egg = json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "integer" },
+            "species_id": { "type": "integer", "description": "What it hatches into" },
+            "species": { "type": "string" },
+            "steps": { "type": "integer", "description": "Left before it can hatch" },
+            "laid": { "type": "string", "format": "date" }
+        }
+    });
+    let battle_event = json!({
+        "type": "object",
+        "required": ["event", "side", "pokemon"],
+        "properties": {
+            "event": { "type": "string", "enum": ["send_out", "use_move", "miss", "no_effect", "damage", "recoil", "faint"] },
+            "side": { "type": "string", "enum": ["player", "opponent"] },
+            "pokemon": { "type": "integer", "description": "Id of the stored pokemon" },
+            "name": { "type": "string", "description": "For send_out" },
+            "move": { "type": "string", "description": "For use_move" },
+            "amount": { "type": "integer", "description": "For damage and recoil" },
+            "hp": { "type": "integer", "description": "Left afterwards, for send_out, damage and recoil" },
+            "critical": { "type": "boolean", "description": "For damage" },
+            "effectiveness": { "type": "number", "description": "For damage" }
+        }
+    });
+    let mut record_encounter = json!({
+        "area": { "type": "string" },
+        "pokeAPI_id": { "type": "integer" },
+        "name": { "type": "string", "description": "The species or form name, instead of pokeAPI_id" },
+        "caught": { "type": "boolean" },
+        "box": { "type": "integer", "description": "Where a caught pokemon goes, the party if left out" }
+    });
+    let mut pokemon = json!({
+        "id": { "type": "integer" },
+        "pokeAPI_id": { "type": "integer" },
+        "name": { "type": "string", "description": "PokeAPI's name for the pokemon, e.g. giratina-origin" },
+        "species": { "type": "string", "description": "The species' name, e.g. giratina" },
+        "height": { "type": "integer" },
+        "weight": { "type": "integer" },
+        "base_happiness": { "type": "integer" },
+        "base_experience": { "type": "integer" },
+        "types": { "type": "array", "items": { "type": "string" } },
+        "base_stats": stats,
+        "abilities": { "type": "array", "items": { "$ref": "#/components/schemas/Ability" } },
+        "sprites": { "$ref": "#/components/schemas/Sprites" },
+        "gender_rate": { "type": "integer" },
+        "growth_rate": { "type": "string" },
+        "is_legendary": { "type": "boolean" },
+        "is_mythical": { "type": "boolean" },
+        "capture_rate": { "type": "integer", "minimum": 0, "maximum": 255 },
+        "moves": { "type": "array", "maxItems": 4, "items": move_slot },
+        "held_item": { "allOf": [item], "nullable": true },
+        "evolutions": { "type": "array", "items": { "$ref": "#/components/schemas/EvolutionRecord" } },
+        "form": { "type": "string", "nullable": true, "description": "The variety of its species, e.g. giratina-origin, unless it's the default" }
+    });
+    let mut move_request = json!({
+        "id": { "type": "integer", "description": "Move an already stored pokemon" },
+        "pokeAPI_id": { "type": "integer", "description": "Catch a new pokemon of this species" },
+        "name": { "type": "string", "description": "Catch a new pokemon by species or form name, ignoring case, e.g. giratina-origin" }
+    });
+    for (name, schema) in instance.as_object().unwrap() {
+        pokemon[name] = schema.clone();
+        move_request[name] = schema.clone();
+        record_encounter[name] = schema.clone();
+    }
+
+    let mut schemas = json!({
+        "Pokemon": {
+            "type": "object",
+            "properties": pokemon
+        },
+        "Stats": {
+            "type": "object",
+            "properties": {
+                "hp": { "type": "integer" },
+                "attack": { "type": "integer" },
+                "defense": { "type": "integer" },
+                "special_attack": { "type": "integer" },
+                "special_defense": { "type": "integer" },
+                "speed": { "type": "integer" }
+            }
+        },
+        "Ability": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "is_hidden": { "type": "boolean" },
+                "slot": { "type": "integer" }
+            }
+        },
+        "Sprites": {
+            "type": "object",
+            "properties": {
+                "front_default": { "type": "string", "nullable": true },
+                "front_shiny": { "type": "string", "nullable": true },
+                "back_default": { "type": "string", "nullable": true },
+                "back_shiny": { "type": "string", "nullable": true }
+            }
+        },
+        "CreateBoxResponse": {
+            "type": "object",
+            "properties": {
+                "box_id": { "type": "integer" }
+            }
+        },
+        "GetBoxResponse": {
+            "type": "object",
+            "properties": {
+                "pokemon": { "type": "array", "items": { "$ref": "#/components/schemas/Pokemon" } }
+            }
+        },
+        "MovePokemonRequest": {
+            "type": "object",
+            "description": "Exactly one of id, pokeAPI_id or name. Details may only be given when catching; anything left out is rolled at random. A misspelled name is a 404 suggesting close names.",
+            "properties": move_request
+        },
+        "EditPokemonRequest": {
+            "type": "object",
+            "description": "Only the fields given are changed. Set level or experience, not both.",
+            "properties": instance
+        },
+        "MovePokemonResponse": {
+            "type": "object",
+            "properties": {
+                "pokemon": { "$ref": "#/components/schemas/Pokemon" }
+            }
+        },
+        "StorePokemonResponse": {
+            "type": "object",
+            "properties": {
+                "pokemon": { "$ref": "#/components/schemas/Pokemon" },
+                "location": location
+            }
+        },
+        "GetPokemonResponse": {
+            "type": "object",
+            "properties": {
+                "pokemon": { "$ref": "#/components/schemas/Pokemon" },
+                "stats": stats
+            }
+        },
+        "Item": {
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "category": { "type": "string" }
+            }
+        },
+        "ReleasePokemonResponse": {
+            "type": "object",
+            "properties": {
+                "pokemon": { "$ref": "#/components/schemas/Pokemon" },
+                "returned_item": { "allOf": [item], "nullable": true }
+            }
+        },
+        "GetBagResponse": {
+            "type": "object",
+            "properties": {
+                "bag": { "type": "object", "properties": { "pockets": bag } }
+            }
+        },
+        "AddItemRequest": {
+            "type": "object",
+            "required": ["item"],
+            "properties": {
+                "item": { "type": "string" },
+                "count": { "type": "integer", "minimum": 1, "default": 1 }
+            }
+        },
+        "AddItemResponse": {
+            "type": "object",
+            "properties": {
+                "item": item,
+                "count": { "type": "integer", "description": "How many of the item the bag now holds" },
+                "bag": { "type": "object", "properties": { "pockets": bag } }
+            }
+        },
+        "GiveItemRequest": {
+            "type": "object",
+            "required": ["item"],
+            "properties": {
+                "item": { "type": "string" }
+            }
+        },
+        "LearnableMovesResponse": {
+            "type": "object",
+            "properties": {
+                "version_group": { "type": "string" },
+                "moves": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "method": { "type": "string" },
+                            "level": { "type": "integer" }
+                        }
+                    }
+                }
+            }
+        },
+        "TeachMoveRequest": {
+            "type": "object",
+            "required": ["move", "version_group"],
+            "properties": {
+                "move": { "type": "string" },
+                "version_group": { "type": "string" },
+                "replace": { "type": "string", "description": "A known move to forget, needed once four are known" }
+            }
+        },
+        "EvolvePokemonRequest": {
+            "type": "object",
+            "properties": {
+                "trigger": { "type": "string", "default": "level-up", "description": "e.g. level-up or use-item; trade evolutions happen by trading instead" },
+                "into": { "type": "string", "description": "The species to evolve into, needed when more than one is possible" },
+                "item": { "type": "string", "description": "The item from the bag to use, for use-item" }
+            }
+        },
+        "ChangeFormRequest": {
+            "type": "object",
+            "properties": {
+                "form": { "type": "string", "nullable": true, "description": "e.g. origin or giratina-origin, or null for the species' default form" }
+            }
+        },
+        "EvolutionRecord": {
+            "type": "object",
+            "properties": {
+                "from": { "type": "string" },
+                "to": { "type": "string" },
+                "trigger": { "type": "string" },
+                "level": { "type": "integer" },
+                "date": { "type": "string", "format": "date" }
+            }
+        },
+        "GetPokedexResponse": {
+            "type": "object",
+            "properties": {
+                "seen": { "type": "integer" },
+                "caught": { "type": "integer" },
+                "national": { "type": "object", "properties": completion },
+                "generations": { "type": "array", "items": { "type": "object", "properties": generation } }
+            }
+        },
+        "GetDexCompletionResponse": {
+            "type": "object",
+            "properties": dex_completion
+        },
+        "PartyAnalysisResponse": party_analysis,
+        "ValidatePartyRequest": {
+            "type": "object",
+            "required": ["pokemon"],
+            "properties": {
+                "pokemon": { "type": "array", "items": { "type": "integer" }, "description": "Ids of stored pokemon" }
+            }
+        },
+        "ValidatePartyResponse": {
+            "type": "object",
+            "properties": {
+                "legal": { "type": "boolean" },
+                "violations": { "type": "array", "items": { "type": "string" } },
+                "rules": rules
+            }
+        },
+        "HealthzResponse": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string" }
+            }
+        },
+        "Readiness": {
+            "type": "object",
+            "properties": {
+                "ready": { "type": "boolean" },
+                "phase": { "type": "string", "enum": ["starting", "ready", "draining"] },
+                "checks": {
+                    "type": "object",
+                    "properties": {
+                        "storage": check,
+                        "persistence": check,
+                        "pokeapi": check
+                    }
+                }
+            }
+        },
+        "ErrorResponse": {
+            "type": "object",
+            "properties": {
+                "error": { "type": "string" },
+                "request_id": { "type": "string" }
+            }
+        },
+        "Text": { "type": "string" },
+        "OpenApi": { "type": "object" }
+    });
+
+    // Added separately, as a single `json!` literal holding every schema hits the macro recursion
+    // limit.
+    schemas["StartNuzlockeRequest"] = json!({
+        "type": "object",
+        "properties": {
+            "dupes_clause": { "type": "boolean", "default": true }
+        }
+    });
+    schemas["NuzlockeStatus"] = nuzlocke_status;
+    schemas["RecordEncounterRequest"] = json!({
+        "type": "object",
+        "required": ["area", "caught"],
+        "description": "Exactly one of pokeAPI_id or name. A caught pokemon must be given a nickname; the other details are optional, as when catching.",
+        "properties": record_encounter
+    });
+    schemas["RecordEncounterResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "encounter": encounter,
+            "pokemon": { "allOf": [{ "$ref": "#/components/schemas/Pokemon" }], "nullable": true }
+        }
+    });
+    schemas["BattleRequest"] = json!({
+        "type": "object",
+        "required": ["opponent"],
+        "properties": {
+            "player": { "type": "array", "items": { "type": "integer" }, "description": "Ids of stored pokemon in the order they're sent out, the party if left out" },
+            "opponent": { "type": "array", "items": { "type": "integer" }, "description": "Ids of stored pokemon in the order they're sent out" },
+            "seed": { "type": "integer", "description": "Picked at random if left out; the same seed replays the same battle" }
+        }
+    });
+    schemas["Battle"] = json!({
+        "type": "object",
+        "properties": {
+            "seed": { "type": "integer" },
+            "winner": { "type": "string", "enum": ["player", "opponent"], "nullable": true, "description": "Null for a draw" },
+            "turns": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "number": { "type": "integer", "description": "Turn 0 is the opening send outs" },
+                        "events": { "type": "array", "items": battle_event }
+                    }
+                }
+            }
+        }
+    });
+    schemas["DayCareStatus"] = json!({
+        "type": "object",
+        "properties": {
+            "pokemon": {
+                "type": "array",
+                "maxItems": 2,
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "pokemon": { "$ref": "#/components/schemas/Pokemon" },
+                        "deposited_at": { "type": "string", "format": "date-time" },
+                        "experience_gained": { "type": "integer" },
+                        "level": { "type": "integer", "description": "The level it'll be once withdrawn" }
+                    }
+                }
+            },
+            "compatibility": { "type": "string", "enum": ["none", "low", "medium", "high"] },
+            "egg_ready": { "type": "boolean" }
+        }
+    });
+    schemas["DepositPokemonRequest"] = json!({
+        "type": "object",
+        "required": ["id"],
+        "properties": {
+            "id": { "type": "integer" }
+        }
+    });
+    schemas["EggResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "egg": egg
+        }
+    });
+    schemas["GetEggsResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "eggs": { "type": "array", "items": egg }
+        }
+    });
+    schemas["WalkRequest"] = json!({
+        "type": "object",
+        "required": ["steps"],
+        "properties": {
+            "steps": { "type": "integer", "minimum": 0 }
+        }
+    });
+    schemas["HatchEggRequest"] = json!({
+        "type": "object",
+        "properties": {
+            "box": { "type": "integer", "description": "Where the hatched pokemon goes, the party if left out" }
+        }
+    });
+    schemas["WildEncounterRequest"] = json!({
+        "type": "object",
+        "required": ["area", "version"],
+        "properties": {
+            "area": { "type": "string", "description": "PokeAPI's location area name, e.g. mt-moon-b1f" },
+            "version": { "type": "string", "description": "The game version whose encounters to use, e.g. red" },
+            "method": { "type": "string", "default": "walk", "description": "How the pokemon is found, e.g. walk, surf or old-rod" }
+        }
+    });
+    schemas["WildEncounterResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "encounter": {
+                "type": "object",
+                "properties": {
+                    "area": { "type": "string" },
+                    "version": { "type": "string" },
+                    "method": { "type": "string" },
+                    "pokemon": { "$ref": "#/components/schemas/Pokemon" }
+                }
+            }
+        }
+    });
+    schemas["CatchRequest"] = json!({
+        "type": "object",
+        "required": ["ball"],
+        "properties": {
+            "ball": { "type": "string", "description": "A poke ball in the bag, e.g. great-ball" },
+            "nickname": { "type": "string", "description": "Given to the pokemon if it's caught; required during a nuzlocke run" }
+        }
+    });
+    schemas["CatchResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "throw": {
+                "type": "object",
+                "properties": {
+                    "ball": { "type": "string" },
+                    "probability": { "type": "number", "minimum": 0, "maximum": 1 },
+                    "shakes": { "type": "integer", "minimum": 0, "maximum": 4 },
+                    "caught": { "type": "boolean" }
+                }
+            },
+            "pokemon": { "allOf": [{ "$ref": "#/components/schemas/Pokemon" }], "nullable": true, "description": "The caught pokemon; null if it broke free, or was let go as a nuzlocke dupe" },
+            "location": { "allOf": [location], "nullable": true, "description": "Where the caught pokemon was stored" }
+        }
+    });
+    let confirm_trade = json!({
+        "type": "object",
+        "properties": {
+            "box": { "type": "integer", "description": "Where the pokemon you receive go, wherever there's room if left out" }
+        }
+    });
+    let received = json!({
+        "type": "object",
+        "properties": {
+            "trainer": { "type": "string" },
+            "pokemon_id": { "type": "integer", "description": "Its id in the receiving trainer's storage" },
+            "species": { "type": "string" },
+            "evolved_into": { "type": "string", "nullable": true }
+        }
+    });
+    let trade = json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "integer" },
+            "from": { "type": "string", "description": "The trainer who made the offer" },
+            "to": { "type": "string", "description": "The trainer it was made to" },
+            "offered": { "type": "array", "items": { "type": "integer" }, "description": "Ids of from's pokemon" },
+            "requested": { "type": "array", "items": { "type": "integer" }, "description": "Ids of to's pokemon" },
+            "created": { "type": "string", "format": "date-time" },
+            "expires": { "type": "string", "format": "date-time" },
+            "from_confirmed": { "allOf": [confirm_trade], "nullable": true },
+            "to_confirmed": { "allOf": [confirm_trade], "nullable": true },
+            "outcome": { "type": "string", "enum": ["completed", "cancelled", "expired"], "description": "Once the trade is closed" },
+            "closed": { "type": "string", "format": "date-time", "description": "Once the trade is closed" },
+            "cancelled_by": { "type": "string", "nullable": true },
+            "received": {
+                "type": "array",
+                "description": "For a completed trade, what each trainer received",
+                "items": received
+            }
+        }
+    });
+    schemas["TrainersResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "trainers": { "type": "array", "items": { "type": "string" } }
+        }
+    });
+    schemas["AddTrainerRequest"] = json!({
+        "type": "object",
+        "required": ["name"],
+        "properties": {
+            "name": { "type": "string", "pattern": "^[A-Za-z0-9_-]{1,32}$" }
+        }
+    });
+    schemas["AddTrainerResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" }
+        }
+    });
+    schemas["OfferTradeRequest"] = json!({
+        "type": "object",
+        "required": ["to", "offered", "requested"],
+        "properties": {
+            "to": { "type": "string", "description": "The trainer to offer the trade to" },
+            "offered": { "type": "array", "items": { "type": "integer" }, "minItems": 1, "description": "Ids of your pokemon, held from now until the trade is done" },
+            "requested": { "type": "array", "items": { "type": "integer" }, "minItems": 1, "description": "Ids of their pokemon" },
+            "expires_in_secs": { "type": "integer", "minimum": 1, "maximum": 604800, "default": 86400 }
+        }
+    });
+    schemas["ConfirmTradeRequest"] = confirm_trade;
+    schemas["TradeResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "trade": trade
+        }
+    });
+    schemas["TradesResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "trades": { "type": "array", "items": trade }
+        }
+    });
+    let wanted = json!({
+        "type": "object",
+        "properties": {
+            "species_id": { "type": "integer" },
+            "species": { "type": "string" },
+            "min_level": { "type": "integer", "nullable": true },
+            "max_level": { "type": "integer", "nullable": true }
+        }
+    });
+    let listing = json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "integer" },
+            "trainer": { "type": "string", "description": "The trainer who deposited it" },
+            "pokemon": { "allOf": [{ "$ref": "#/components/schemas/Pokemon" }], "description": "Its id is the one it had in the depositor's storage" },
+            "box": { "type": "integer", "description": "The box it was deposited from" },
+            "wanted": wanted,
+            "deposited": { "type": "string", "format": "date-time" }
+        }
+    });
+    schemas["ListPokemonRequest"] = json!({
+        "type": "object",
+        "required": ["pokemon_id", "wanted"],
+        "properties": {
+            "pokemon_id": { "type": "integer", "description": "One of your boxed pokemon, out of your storage until it's withdrawn or traded" },
+            "wanted": {
+                "type": "object",
+                "description": "The species, by pokeAPI_id or name, and levels you'll take for it",
+                "properties": {
+                    "pokeAPI_id": { "type": "integer" },
+                    "name": { "type": "string" },
+                    "min_level": { "type": "integer", "minimum": 1, "maximum": 100 },
+                    "max_level": { "type": "integer", "minimum": 1, "maximum": 100 }
+                }
+            }
+        }
+    });
+    schemas["ListingResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "listing": listing
+        }
+    });
+    schemas["ListingsResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "listings": { "type": "array", "items": listing }
+        }
+    });
+    schemas["FulfilListingRequest"] = json!({
+        "type": "object",
+        "required": ["pokemon_id"],
+        "properties": {
+            "pokemon_id": { "type": "integer", "description": "Your pokemon, which has to be what the listing wants" },
+            "box": { "type": "integer", "description": "Where the listed pokemon goes, wherever there's room if left out" }
+        }
+    });
+    schemas["FulfilListingResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "received": { "type": "array", "items": received, "description": "What each trainer received" }
+        }
+    });
+    schemas["PrefetchRequest"] = json!({
+        "type": "object",
+        "required": ["ids"],
+        "properties": {
+            "ids": { "type": "array", "items": { "type": "integer" }, "description": "PokeAPI pokemon ids" },
+            "concurrency": { "type": "integer", "minimum": 1, "maximum": 16, "default": 4, "description": "How many to fetch at once" }
+        }
+    });
+    schemas["PrefetchResponse"] = json!({
+        "type": "object",
+        "properties": {
+            "fetched": { "type": "integer" },
+            "failed": { "type": "array", "items": { "type": "integer" }, "description": "Ids that couldn't be fetched" }
+        }
+    });
+
+    schemas
+}
+
+/// Converts thruster's `/boxes/:id` style paths to OpenAPI's `/boxes/{id}`, returning the names of
+/// the path parameters along the way.
+fn openapi_path(path: &str) -> (String, Vec<&str>) {
+    let mut params = vec![];
+    let segments = path
+        .split('/')
+        .map(|segment| {
+            if let Some(param) = segment.strip_prefix(':') {
+                params.push(param);
+                format!("{{{}}}", param)
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<String>>();
+
+    (segments.join("/"), params)
+}
+
+fn operation(route: &RouteDoc) -> Value {
+    let (_, params) = openapi_path(route.path);
+
+    let mut responses = Map::new();
+    responses.insert(
+        "200".to_string(),
+        json!({
+            "description": "OK",
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": format!("#/components/schemas/{}", route.response) }
+                }
+            }
+        }),
+    );
+    for status in route.errors {
+        let schema = if *status == 503 {
+            "Readiness"
+        } else {
+            "ErrorResponse"
+        };
+
+        responses.insert(
+            status.to_string(),
+            json!({
+                "description": error_description(*status),
+                "content": {
+                    "application/json": {
+                        "schema": { "$ref": format!("#/components/schemas/{}", schema) }
+                    }
+                }
+            }),
+        );
+    }
+
+    let mut parameters = params
+        .iter()
+        .map(|param| {
+            json!({
+                "name": param,
+                "in": "path",
+                "required": true,
+                "schema": { "type": if param.ends_with("id") { "integer" } else { "string" } }
+            })
+        })
+        .collect::<Vec<Value>>();
+    parameters.push(json!({
+        "name": "X-Trainer",
+        "in": "header",
+        "required": false,
+        "description": "The trainer whose storage to use, the default trainer if left out. An unknown trainer is a 404.",
+        "schema": { "type": "string" }
+    }));
+
+    let mut operation = json!({
+        "summary": route.summary,
+        "parameters": parameters,
+        "responses": responses,
+    });
+
+    if let Some(request) = route.request {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": format!("#/components/schemas/{}", request) }
+                }
+            }
+        });
+    }
+
+    operation
+}
+
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+
+    for route in ROUTES {
+        let (path, _) = openapi_path(route.path);
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+
+        item[route.method.to_lowercase()] = operation(route);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Zed",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app;
+    use crate::router::Router;
+    use std::collections::BTreeSet;
+
+    fn documented_routes() -> BTreeSet<(&'static str, &'static str)> {
+        ROUTES
+            .iter()
+            .map(|route| (route.method, route.path))
+            .collect()
+    }
+
+    #[test]
+    fn every_registered_route_is_documented() {
+        let documented = documented_routes();
+
+        for (method, path, _) in app::routes() {
+            assert!(
+                documented.contains(&(method, path)),
+                "{} {} is registered but missing from openapi::ROUTES",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn every_documented_route_is_registered() {
+        let registered = app::routes()
+            .into_iter()
+            .map(|(method, path, _)| (method, path))
+            .collect::<BTreeSet<_>>();
+
+        for (method, path) in documented_routes() {
+            assert!(
+                registered.contains(&(method, path)),
+                "{} {} is documented but not registered",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn documented_routes_resolve_to_their_handlers() {
+        let router = Router::new(app::routes());
+
+        for (method, path) in documented_routes() {
+            let request_path = path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with(':') {
+                        "0"
+                    } else {
+                        segment
+                    }
+                })
+                .collect::<Vec<&str>>()
+                .join("/");
+            let matched = router.resolve(method, &request_path);
+
+            assert_eq!(
+                matched.map(|matched| matched.path),
+                Some(path),
+                "{} {} does not resolve to its handler",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn every_referenced_schema_exists() {
+        let schemas = schemas();
+
+        for route in ROUTES {
+            for schema in route.request.iter().chain(std::iter::once(&route.response)) {
+                assert!(
+                    schemas.get(schema).is_some(),
+                    "{} {} references missing schema {}",
+                    route.method,
+                    route.path,
+                    schema
+                );
+            }
+        }
+    }
+}
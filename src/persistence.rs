@@ -0,0 +1,101 @@
+use anyhow::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::storage::Storage;
+use crate::trainers::Trainers;
+
+/// Snapshots every trainer's storage and the trades between them to a JSON file so they survive a
+/// restart. When no path is configured every operation is a no-op.
+#[derive(Debug, Default)]
+pub struct Persistence {
+    path: Option<PathBuf>,
+}
+
+impl Persistence {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Persistence { path }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Snapshots from before there were several trainers hold a single storage, which becomes the
+    /// default trainer's.
+    pub fn load(&self) -> Result<Option<Trainers>, Error> {
+        let path = match &self.path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        };
+
+        let contents = serde_json::from_str::<serde_json::Value>(&fs::read_to_string(path)?)?;
+
+        let mut trainers = if contents.get("trainers").is_some() {
+            serde_json::from_value::<Trainers>(contents)?
+        } else {
+            Trainers::new(serde_json::from_value::<Storage>(contents)?)
+        };
+        for (_, storage) in trainers.storages_mut() {
+            storage.assign_missing_ids();
+            storage.register_stored_species();
+        }
+
+        Ok(Some(trainers))
+    }
+
+    pub fn save(&self, trainers: &Trainers) -> Result<(), Error> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // Write to a sibling file and rename it into place so a crash mid-write never leaves a
+        // truncated snapshot behind.
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string(trainers)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    pub fn check_writable(&self) -> Result<(), Error> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let probe_path = path.with_extension("probe");
+        fs::write(&probe_path, b"")?;
+        fs::remove_file(&probe_path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trainers::DEFAULT_TRAINER;
+
+    #[test]
+    fn single_storage_snapshots_become_the_default_trainers() {
+        let path = std::env::temp_dir().join(format!("zed-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, serde_json::to_string(&Storage::new(3, 12)).unwrap()).unwrap();
+        let persistence = Persistence::new(Some(path.clone()));
+
+        let mut trainers = persistence.load().unwrap().unwrap();
+        assert_eq!(
+            trainers.names().collect::<Vec<&str>>(),
+            vec![DEFAULT_TRAINER]
+        );
+        assert_eq!(trainers.get(DEFAULT_TRAINER).unwrap().max_party_size(), 3);
+
+        trainers.add("misty").unwrap();
+        persistence.save(&trainers).unwrap();
+        let restored = persistence.load().unwrap().unwrap();
+        assert_eq!(restored.get("misty").unwrap().max_box_size(), 12);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
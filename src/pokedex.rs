@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The species a trainer has seen and caught, by PokeAPI id. Species stay registered after the
+/// pokemon themselves are released.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Pokedex {
+    seen: BTreeSet<u32>,
+    caught: BTreeSet<u32>,
+}
+
+impl Pokedex {
+    pub fn register_seen(&mut self, species_id: u32) {
+        self.seen.insert(species_id);
+    }
+
+    /// Registers a species as caught, which also means it's been seen.
+    pub fn register_caught(&mut self, species_id: u32) {
+        self.seen.insert(species_id);
+        self.caught.insert(species_id);
+    }
+
+    pub fn has_seen(&self, species_id: u32) -> bool {
+        self.seen.contains(&species_id)
+    }
+
+    pub fn has_caught(&self, species_id: u32) -> bool {
+        self.caught.contains(&species_id)
+    }
+
+    pub fn seen_count(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn caught_count(&self) -> usize {
+        self.caught.len()
+    }
+}
+
+/// A species' place in one of PokeAPI's pokedexes, e.g. `national` or `kanto`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DexEntry {
+    pub number: u32,
+    pub species: String,
+    pub species_id: u32,
+}
+
+/// The species introduced in a generation.
+#[derive(Clone, Debug)]
+pub struct Generation {
+    /// PokeAPI's name for it, e.g. `generation-i`.
+    pub name: String,
+    pub species_ids: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Completion {
+    pub total: usize,
+    pub seen: usize,
+    pub caught: usize,
+    /// How much of the list has been caught, as a percentage to one decimal place.
+    pub percent: f64,
+}
+
+/// How much of the species in `species_ids` the trainer has seen and caught.
+pub fn completion<I>(pokedex: &Pokedex, species_ids: I) -> Completion
+where
+    I: IntoIterator<Item = u32>,
+{
+    let mut total = 0;
+    let mut seen = 0;
+    let mut caught = 0;
+    for species_id in species_ids {
+        total += 1;
+        if pokedex.has_seen(species_id) {
+            seen += 1;
+        }
+        if pokedex.has_caught(species_id) {
+            caught += 1;
+        }
+    }
+
+    let percent = if total == 0 {
+        0.0
+    } else {
+        (caught as f64 * 1000.0 / total as f64).round() / 10.0
+    };
+
+    Completion {
+        total,
+        seen,
+        caught,
+        percent,
+    }
+}
+
+/// The entries of a pokedex the trainer has yet to catch, in pokedex order.
+pub fn missing<'a>(pokedex: &Pokedex, entries: &'a [DexEntry]) -> Vec<&'a DexEntry> {
+    entries
+        .iter()
+        .filter(|entry| !pokedex.has_caught(entry.species_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pokemon::Pokemon;
+    use crate::storage::{Storage, StorageDestination};
+
+    fn entries() -> Vec<DexEntry> {
+        ["bulbasaur", "ivysaur", "venusaur"]
+            .iter()
+            .zip(1..)
+            .map(|(species, id)| DexEntry {
+                number: id,
+                species: species.to_string(),
+                species_id: id,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn completion_counts_what_has_been_seen_and_caught() {
+        let mut pokedex = Pokedex::default();
+        pokedex.register_seen(2);
+        pokedex.register_caught(3);
+        // Not in the list, so it doesn't count towards it.
+        pokedex.register_caught(140);
+
+        assert_eq!(
+            completion(&pokedex, entries().iter().map(|entry| entry.species_id)),
+            Completion {
+                total: 3,
+                seen: 2,
+                caught: 1,
+                percent: 33.3,
+            }
+        );
+        assert_eq!(completion(&pokedex, vec![]).percent, 0.0);
+        assert_eq!(
+            missing(&pokedex, &entries())
+                .iter()
+                .map(|entry| entry.species.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["bulbasaur", "ivysaur"]
+        );
+    }
+
+    #[test]
+    fn releasing_a_pokemon_keeps_its_species_registered() {
+        let mut storage = Storage::new(6, 1);
+        let kabuto: Pokemon = serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": 140,
+            "name": "kabuto",
+            "height": 5,
+            "weight": 115,
+            "base_happiness": 50
+        }))
+        .unwrap();
+        let id = storage
+            .add_pokemon(kabuto, StorageDestination::Party)
+            .unwrap()
+            .id();
+
+        storage.release_pokemon(id).unwrap();
+        assert!(storage.pokedex().has_seen(140));
+        assert!(storage.pokedex().has_caught(140));
+        assert_eq!(storage.pokedex().caught_count(), 1);
+    }
+}
@@ -1,16 +1,193 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::instance::{Instance, Stat};
+
+/// One value per stat. Used for base stats as well as a pokemon's IVs and EVs.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stats {
+    pub hp: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub special_attack: u32,
+    pub special_defense: u32,
+    pub speed: u32,
+}
+
+impl Stats {
+    /// Collects base stats listed by PokeAPI's stat names, e.g. `special-attack`. Stats that
+    /// aren't listed are 0.
+    pub fn from_pokeapi<'a, I: IntoIterator<Item = (&'a str, u32)>>(stats: I) -> Stats {
+        let mut base_stats = Stats::default();
+        for (name, value) in stats {
+            match name {
+                "hp" => base_stats.hp = value,
+                "attack" => base_stats.attack = value,
+                "defense" => base_stats.defense = value,
+                "special-attack" => base_stats.special_attack = value,
+                "special-defense" => base_stats.special_defense = value,
+                "speed" => base_stats.speed = value,
+                _ => (),
+            }
+        }
+
+        base_stats
+    }
+
+    pub fn get(&self, stat: Stat) -> u32 {
+        match stat {
+            Stat::Hp => self.hp,
+            Stat::Attack => self.attack,
+            Stat::Defense => self.defense,
+            Stat::SpecialAttack => self.special_attack,
+            Stat::SpecialDefense => self.special_defense,
+            Stat::Speed => self.speed,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Stat, u32)> + '_ {
+        [
+            Stat::Hp,
+            Stat::Attack,
+            Stat::Defense,
+            Stat::SpecialAttack,
+            Stat::SpecialDefense,
+            Stat::Speed,
+        ]
+        .iter()
+        .map(move |stat| (*stat, self.get(*stat)))
+    }
+
+    pub fn total(&self) -> u32 {
+        self.iter().map(|(_, value)| value).sum()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Ability {
+    pub name: String,
+    pub is_hidden: bool,
+    pub slot: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Sprites {
+    pub front_default: Option<String>,
+    pub front_shiny: Option<String>,
+    pub back_default: Option<String>,
+    pub back_shiny: Option<String>,
+}
+
+/// How much experience a species needs per level, named the way PokeAPI names them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GrowthRate {
+    Fast,
+    #[default]
+    Medium,
+    MediumSlow,
+    Slow,
+    #[serde(rename = "slow-then-very-fast")]
+    Erratic,
+    #[serde(rename = "fast-then-very-slow")]
+    Fluctuating,
+}
+
+impl GrowthRate {
+    pub fn experience_for_level(self, level: u32) -> u32 {
+        let n = i64::from(level);
+        let cubed = n * n * n;
+
+        let experience = match self {
+            GrowthRate::Fast => 4 * cubed / 5,
+            GrowthRate::Medium => cubed,
+            GrowthRate::MediumSlow => 6 * cubed / 5 - 15 * n * n + 100 * n - 140,
+            GrowthRate::Slow => 5 * cubed / 4,
+            GrowthRate::Erratic => match level {
+                0..=49 => cubed * (100 - n) / 50,
+                50..=67 => cubed * (150 - n) / 100,
+                68..=97 => cubed * ((1911 - 10 * n) / 3) / 500,
+                _ => cubed * (160 - n) / 100,
+            },
+            GrowthRate::Fluctuating => match level {
+                0..=14 => cubed * ((n + 1) / 3 + 24) / 50,
+                15..=35 => cubed * (n + 14) / 50,
+                _ => cubed * (n / 2 + 32) / 50,
+            },
+        };
+
+        // Level 1 is always 0 experience; medium-slow's formula dips below it.
+        if level <= 1 {
+            0
+        } else {
+            experience.max(0) as u32
+        }
+    }
+
+    pub fn level_for_experience(self, experience: u32) -> u32 {
+        (2..=100)
+            .take_while(|level| self.experience_for_level(*level) <= experience)
+            .last()
+            .unwrap_or(1)
+    }
+}
+
+fn genderless() -> i32 {
+    -1
+}
+
+// Fields added after the first release default when missing so older snapshots still load.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
 pub struct Pokemon {
     pub pokeAPI_id: u32,
+    /// PokeAPI's name for this pokemon, e.g. `giratina-origin`.
     pub name: String,
+    /// The name of its species, e.g. `giratina`. Empty in snapshots from before it was kept, when
+    /// `name` was always the species'.
+    #[serde(default)]
+    pub species: String,
     pub height: u32,
     pub weight: u32,
     pub base_happiness: u32,
+    #[serde(default)]
+    pub base_experience: u32,
+    /// Type names ordered by slot, e.g. `["rock", "water"]`.
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub base_stats: Stats,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    #[serde(default)]
+    pub sprites: Sprites,
+    /// Chance of being female in eighths, or -1 for genderless species.
+    #[serde(default = "genderless")]
+    pub gender_rate: i32,
+    #[serde(default)]
+    pub growth_rate: GrowthRate,
+    #[serde(default)]
+    pub is_legendary: bool,
+    #[serde(default)]
+    pub is_mythical: bool,
+    /// Out of 255, how easily wild pokemon of the species are caught.
+    #[serde(default)]
+    pub capture_rate: u32,
+    #[serde(flatten)]
+    pub instance: Instance,
 }
 
 impl Pokemon {
+    /// The id of this particular pokemon within its trainer's storage. `pokeAPI_id` is the species.
     pub fn id(&self) -> u32 {
-        self.pokeAPI_id
+        self.instance.id
+    }
+
+    pub fn species_name(&self) -> &str {
+        if self.species.is_empty() {
+            &self.name
+        } else {
+            &self.species
+        }
     }
 }
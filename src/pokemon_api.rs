@@ -1,7 +1,120 @@
 use anyhow::Error;
 use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::pokemon::Pokemon;
+use crate::battle::{BattleMove, DamageClass};
+use crate::cache::Cache;
+use crate::config::PokeApiMode;
+use crate::dataset::{Dataset, PokemonRecord, SpeciesRecord};
+use crate::day_care::Breeding;
+use crate::evolution::{Evolution, EvolutionMethod};
+use crate::forms::Variety;
+use crate::in_flight::InFlight;
+use crate::instance::Gender;
+use crate::items::Item;
+use crate::moves::{LearnableMove, MoveSlot};
+use crate::names::NameIndex;
+use crate::pokedex::{DexEntry, Generation};
+use crate::pokemon::{GrowthRate, Pokemon, Sprites, Stats};
+use crate::types::Type;
+use crate::wild::EncounterSlot;
+
+const DEFAULT_BASE_URL: &str = "https://pokeapi.co/api/v2";
+/// Entries per cache unless configured otherwise.
+pub const DEFAULT_CACHE_SIZE: usize = 2048;
+/// PokeAPI numbers alternate forms from here up. Below it, a pokemon shares its id with its
+/// species.
+const FIRST_FORM_ID: u32 = 10001;
+/// Most pokemon a prefetch fetches at once unless told otherwise.
+pub const DEFAULT_PREFETCH_CONCURRENCY: usize = 4;
+/// Keeps a prefetch from flooding PokeAPI, which rate limits by client.
+pub const MAX_PREFETCH_CONCURRENCY: usize = 16;
+
+#[derive(Debug, Deserialize)]
+pub struct NamedResourceFromApi {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatFromApi {
+    pub base_stat: u32,
+    pub stat: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TypeFromApi {
+    pub slot: u32,
+    #[serde(rename = "type")]
+    pub type_: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AbilityFromApi {
+    pub ability: NamedResourceFromApi,
+    pub is_hidden: bool,
+    pub slot: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VersionGroupDetailFromApi {
+    pub level_learned_at: u32,
+    pub move_learn_method: NamedResourceFromApi,
+    pub version_group: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PokemonMoveFromApi {
+    #[serde(rename = "move")]
+    pub move_: NamedResourceFromApi,
+    pub version_group_details: Vec<VersionGroupDetailFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct MoveFromApi {
+    pub name: String,
+    pub pp: Option<u32>,
+    pub power: Option<u32>,
+    pub accuracy: Option<u32>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(rename = "type")]
+    pub type_: Option<NamedResourceFromApi>,
+    pub damage_class: Option<NamedResourceFromApi>,
+}
+
+impl MoveFromApi {
+    #[cfg_attr(test, allow(dead_code))]
+    fn into_battle_move(self) -> BattleMove {
+        let damage_class = match self.damage_class.as_ref().map(|class| class.name.as_str()) {
+            Some("physical") => DamageClass::Physical,
+            Some("special") => DamageClass::Special,
+            _ => DamageClass::Status,
+        };
+
+        BattleMove {
+            type_: self
+                .type_
+                .as_ref()
+                .and_then(|type_| Type::from_name(&type_.name)),
+            power: self.power,
+            accuracy: self.accuracy,
+            pp: self.pp.unwrap_or(0),
+            priority: self.priority,
+            damage_class,
+            name: self.name,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct ItemFromApi {
+    pub name: String,
+    pub category: NamedResourceFromApi,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct PokemonFromApi {
@@ -9,99 +122,1005 @@ pub struct PokemonFromApi {
     pub name: String,
     pub height: u32,
     pub weight: u32,
+    pub base_experience: Option<u32>,
+    pub stats: Vec<StatFromApi>,
+    pub types: Vec<TypeFromApi>,
+    pub abilities: Vec<AbilityFromApi>,
+    pub sprites: Sprites,
+    #[serde(default)]
+    pub moves: Vec<PokemonMoveFromApi>,
+    pub species: Option<NamedResourceFromApi>,
+    #[serde(default = "default_variety")]
+    pub is_default: bool,
+}
+
+fn default_variety() -> bool {
+    true
+}
+
+impl PokemonFromApi {
+    pub fn base_stats(&self) -> Stats {
+        Stats::from_pokeapi(
+            self.stats
+                .iter()
+                .map(|stat| (stat.stat.name.as_str(), stat.base_stat)),
+        )
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PokemonSepeciesFromApi {
     pub id: u32,
+    #[serde(default)]
+    pub name: String,
     pub base_happiness: u32,
+    pub gender_rate: i32,
+    pub growth_rate: GrowthRateFromApi,
+    pub evolution_chain: Option<ResourceFromApi>,
+    #[serde(default)]
+    pub egg_groups: Vec<NamedResourceFromApi>,
+    pub hatch_counter: Option<u32>,
+    #[serde(default)]
+    pub capture_rate: u32,
+    #[serde(default)]
+    pub is_legendary: bool,
+    #[serde(default)]
+    pub is_mythical: bool,
+    #[serde(default)]
+    pub varieties: Vec<VarietyFromApi>,
 }
 
-#[cfg(test)]
-pub async fn get_pokemon(_id: u32) -> Result<Pokemon, Error> {
-    Ok(Pokemon {
-        pokeAPI_id: 141,
-        name: "kabuptops".to_string(),
-        height: 13,
-        weight: 405,
-        base_happiness: 0,
-    })
+#[derive(Debug, Deserialize)]
+pub struct VarietyFromApi {
+    pub is_default: bool,
+    pub pokemon: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct PokemonFormFromApi {
+    pub is_battle_only: bool,
 }
 
-#[cfg(not(test))]
-pub async fn get_pokemon(id: u32) -> Result<Pokemon, Error> {
-    let pokemon = get_pokemon_from_api(id).await?;
-    let species = get_pokemon_species_from_api(id).await?;
+#[derive(Debug, Deserialize)]
+pub struct ResourceFromApi {
+    pub url: String,
+}
 
-    Ok(Pokemon {
-        pokeAPI_id: pokemon.id,
-        name: pokemon.name,
-        height: pokemon.height,
-        weight: pokemon.weight,
-        base_happiness: species.base_happiness,
-    })
+#[derive(Debug, Deserialize)]
+pub struct EvolutionChainFromApi {
+    pub chain: ChainLinkFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChainLinkFromApi {
+    pub species: NamedResourceFromApi,
+    pub evolution_details: Vec<EvolutionDetailFromApi>,
+    pub evolves_to: Vec<ChainLinkFromApi>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct EvolutionDetailFromApi {
+    pub trigger: Option<NamedResourceFromApi>,
+    pub min_level: Option<u32>,
+    pub min_happiness: Option<u32>,
+    pub item: Option<NamedResourceFromApi>,
+    pub held_item: Option<NamedResourceFromApi>,
+    pub known_move: Option<NamedResourceFromApi>,
+    /// 1 for female, 2 for male.
+    pub gender: Option<u32>,
+    pub trade_species: Option<NamedResourceFromApi>,
+    pub known_move_type: Option<NamedResourceFromApi>,
+    pub location: Option<NamedResourceFromApi>,
+    pub min_affection: Option<u32>,
+    pub min_beauty: Option<u32>,
+    pub needs_overworld_rain: bool,
+    pub party_species: Option<NamedResourceFromApi>,
+    pub party_type: Option<NamedResourceFromApi>,
+    pub relative_physical_stats: Option<i32>,
+    pub time_of_day: String,
+    pub turn_upside_down: bool,
+}
+
+impl EvolutionDetailFromApi {
+    fn into_method(self) -> EvolutionMethod {
+        let named = |resource: Option<NamedResourceFromApi>| resource.map(|r| r.name);
+        let unsupported = [
+            ("known_move_type", self.known_move_type.is_some()),
+            ("location", self.location.is_some()),
+            ("min_affection", self.min_affection.is_some()),
+            ("min_beauty", self.min_beauty.is_some()),
+            ("needs_overworld_rain", self.needs_overworld_rain),
+            ("party_species", self.party_species.is_some()),
+            ("party_type", self.party_type.is_some()),
+            (
+                "relative_physical_stats",
+                self.relative_physical_stats.is_some(),
+            ),
+            ("time_of_day", !self.time_of_day.is_empty()),
+            ("turn_upside_down", self.turn_upside_down),
+        ];
+
+        EvolutionMethod {
+            trigger: named(self.trigger).unwrap_or_default(),
+            min_level: self.min_level,
+            min_happiness: self.min_happiness,
+            item: named(self.item),
+            held_item: named(self.held_item),
+            known_move: named(self.known_move),
+            gender: match self.gender {
+                Some(1) => Some(Gender::Female),
+                Some(2) => Some(Gender::Male),
+                _ => None,
+            },
+            trade_species: named(self.trade_species),
+            unsupported: unsupported
+                .iter()
+                .filter(|(_, present)| *present)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        }
+    }
 }
 
-#[cfg(not(test))]
-async fn get_pokemon_from_api(id: u32) -> Result<PokemonFromApi, Error> {
+/// Parses a PokeAPI response body as `T`, logging it if it isn't one.
+fn parse<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, Error> {
     use log::error;
-    use reqwest::header::CONTENT_TYPE;
 
-    let client = reqwest::Client::new();
-    let req = client
-        .get(&format!("https://pokeapi.co/api/v2/pokemon/{}", id))
-        .header(CONTENT_TYPE, "application/json; charset=utf-8");
+    serde_json::from_str::<T>(body).map_err(|e| {
+        error!("pokemon api parsing error: {:#?}", e);
+        e.into()
+    })
+}
+
+/// Where the saved PokeAPI response for `path` is kept.
+#[cfg(test)]
+fn fixture(path: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/pokeapi")
+        .join(format!("{}.json", path))
+}
+
+/// PokeAPI links resources by URL, e.g. `https://pokeapi.co/api/v2/pokemon-species/25/`. We only
+/// keep the id so requests keep going to our own `base_url`.
+pub fn id_from_url(url: &str) -> Option<u32> {
+    url.trim_end_matches('/').rsplit('/').next()?.parse().ok()
+}
+
+fn find_link(link: ChainLinkFromApi, species_id: u32) -> Option<ChainLinkFromApi> {
+    if id_from_url(&link.species.url) == Some(species_id) {
+        return Some(link);
+    }
+
+    link.evolves_to
+        .into_iter()
+        .find_map(|next| find_link(next, species_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PokedexEntryFromApi {
+    pub entry_number: u32,
+    pub pokemon_species: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PokedexFromApi {
+    pub pokemon_entries: Vec<PokedexEntryFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct ResourceListFromApi {
+    pub results: Vec<NamedResourceFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct GenerationFromApi {
+    pub id: u32,
+    pub name: String,
+    pub pokemon_species: Vec<NamedResourceFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct EncounterDetailFromApi {
+    pub min_level: u32,
+    pub max_level: u32,
+    pub chance: u32,
+    pub method: NamedResourceFromApi,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct VersionEncounterDetailFromApi {
+    pub version: NamedResourceFromApi,
+    pub encounter_details: Vec<EncounterDetailFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct PokemonEncounterFromApi {
+    pub pokemon: NamedResourceFromApi,
+    pub version_details: Vec<VersionEncounterDetailFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, allow(dead_code))]
+pub struct LocationAreaFromApi {
+    pub pokemon_encounters: Vec<PokemonEncounterFromApi>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrowthRateFromApi {
+    pub name: GrowthRate,
+}
+
+#[derive(Debug)]
+pub struct PokeApi {
+    base_url: String,
+    client: reqwest::Client,
+    /// Encounter slots by location area. They never change, and an area is usually visited more
+    /// than once.
+    encounters: Cache<String, Vec<EncounterSlot>>,
+    /// Loaded the first time a pokemon is looked up by name.
+    names: Cache<(), Arc<NameIndex>>,
+    /// Pokedexes by name, and the list of generations. These only change with a new game.
+    pokedexes: Cache<String, Vec<DexEntry>>,
+    generations: Cache<(), Vec<Generation>>,
+    mode: PokeApiMode,
+    /// Imported with `zed import`, and empty unless the mode uses it.
+    dataset: Dataset,
+    /// Pokemon and their species by pokemon id, which never change either.
+    pokemon: Cache<u32, (PokemonRecord, SpeciesRecord)>,
+    /// Pokemon being fetched right now, so concurrent lookups of one share the upstream calls.
+    fetching: InFlight<u32, (PokemonRecord, SpeciesRecord)>,
+}
+
+impl Default for PokeApi {
+    fn default() -> Self {
+        PokeApi::new(DEFAULT_BASE_URL)
+    }
+}
+
+impl PokeApi {
+    pub fn new(base_url: &str) -> Self {
+        PokeApi {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            encounters: Cache::new(DEFAULT_CACHE_SIZE, None),
+            names: Cache::new(1, None),
+            pokedexes: Cache::new(DEFAULT_CACHE_SIZE, None),
+            generations: Cache::new(1, None),
+            mode: PokeApiMode::Online,
+            dataset: Dataset::default(),
+            pokemon: Cache::new(DEFAULT_CACHE_SIZE, None),
+            fetching: InFlight::default(),
+        }
+    }
+
+    /// Bounds each cache to `size` entries, kept for at most `ttl`. A size of 0 turns caching off.
+    pub fn set_cache(&mut self, size: usize, ttl: Option<Duration>) {
+        self.encounters = Cache::new(size, ttl);
+        self.names = Cache::new(size.min(1), ttl);
+        self.pokedexes = Cache::new(size, ttl);
+        self.generations = Cache::new(size.min(1), ttl);
+        self.pokemon = Cache::new(size, ttl);
+    }
+
+    /// Whether pokemon can be served without reaching PokeAPI, from the cache or the dataset.
+    pub fn is_warm(&self) -> bool {
+        !self.pokemon.is_empty() || self.dataset.pokemon_count() > 0
+    }
+
+    pub fn set_dataset(&mut self, mode: PokeApiMode, dataset: Dataset) {
+        self.mode = mode;
+        self.dataset = dataset;
+    }
+
+    /// Looks up each of `ids` ahead of time, at most `concurrency` at once, so later lookups are
+    /// served from the cache. Returns the ids that couldn't be fetched.
+    pub async fn prefetch(&self, ids: &[u32], concurrency: usize) -> Vec<u32> {
+        use futures::future::join_all;
+        use tokio::sync::Semaphore;
+
+        let permits = Semaphore::new(concurrency.max(1));
+        let mut lookups = vec![];
+        for &id in ids {
+            let permits = &permits;
+            lookups.push(async move {
+                let _permit = permits.acquire().await;
+                (id, self.get_pokemon(id).await.is_ok())
+            });
+        }
+
+        join_all(lookups)
+            .await
+            .into_iter()
+            .filter(|(_, ok)| !ok)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub async fn is_battle_only(&self, _name: &str) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    #[cfg(test)]
+    pub async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub async fn get_item(&self, name: &str) -> Result<Option<Item>, Error> {
+        Ok(Some(Item {
+            name: name.to_string(),
+            category: "held-items".to_string(),
+        }))
+    }
+
+    #[cfg(test)]
+    pub async fn get_generations(&self) -> Result<Vec<Generation>, Error> {
+        Ok(vec![Generation {
+            name: "generation-i".to_string(),
+            species_ids: vec![1, 2, 3],
+        }])
+    }
+
+    #[cfg(test)]
+    pub async fn get_move(&self, name: &str) -> Result<MoveSlot, Error> {
+        Ok(MoveSlot {
+            name: name.to_string(),
+            pp: 15,
+            max_pp: 15,
+        })
+    }
+
+    #[cfg(test)]
+    pub async fn get_battle_move(&self, name: &str) -> Result<BattleMove, Error> {
+        Ok(BattleMove {
+            name: name.to_string(),
+            type_: Some(Type::Normal),
+            power: Some(40),
+            accuracy: Some(100),
+            pp: 35,
+            priority: 0,
+            damage_class: DamageClass::Physical,
+        })
+    }
+
+    #[cfg(test)]
+    pub async fn get_name_index(&self) -> Result<Arc<NameIndex>, Error> {
+        Ok(Arc::new(NameIndex::new(
+            [
+                ("kabuto", 140),
+                ("kabutops", 141),
+                ("giratina-origin", 10007),
+            ]
+            .iter()
+            .map(|(name, id)| (name.to_string(), *id)),
+        )))
+    }
+
+    #[cfg(test)]
+    pub async fn get_encounters(&self, _area: &str) -> Result<Vec<EncounterSlot>, Error> {
+        Ok([("kabuto", 140, 60), ("omanyte", 138, 40)]
+            .iter()
+            .map(|(pokemon, pokemon_id, chance)| EncounterSlot {
+                version: "red".to_string(),
+                method: "walk".to_string(),
+                pokemon_id: *pokemon_id,
+                pokemon: pokemon.to_string(),
+                min_level: 20,
+                max_level: 30,
+                chance: *chance,
+            })
+            .collect())
+    }
 
-    let res = req.send().await;
+    /// Pokemon `id`, from PokeAPI or the imported dataset depending on the mode.
+    pub async fn get_pokemon(&self, id: u32) -> Result<Pokemon, Error> {
+        use anyhow::anyhow;
+        use log::warn;
 
-    match res {
-        Ok(body) => {
-            let body = body.text().await.unwrap();
-            let parsed = serde_json::from_str::<PokemonFromApi>(&body);
+        let from_dataset = || {
+            self.dataset
+                .get_pokemon(id)
+                .ok_or_else(|| anyhow!("pokemon {} isn't in the offline dataset", id))
+        };
 
-            match parsed {
-                Ok(resp) => Ok(resp),
+        match self.mode {
+            PokeApiMode::Online => self.fetch_pokemon(id).await,
+            PokeApiMode::Offline => from_dataset(),
+            PokeApiMode::Fallback => match self.fetch_pokemon(id).await {
+                Ok(pokemon) => Ok(pokemon),
                 Err(e) => {
-                    error!("pokemon api parsing error: {:#?}", e);
-                    Err(e.into())
+                    warn!("Serving pokemon {} from the offline dataset: {}", id, e);
+                    from_dataset().map_err(|_| e)
                 }
+            },
+        }
+    }
+
+    /// Pokemon `id` from the cache, or from PokeAPI the first time it's asked for.
+    async fn fetch_pokemon(&self, id: u32) -> Result<Pokemon, Error> {
+        use crate::dataset;
+
+        let cached = self.pokemon.get(&id);
+        let (pokemon, species) = match cached {
+            Some(records) => records,
+            None => {
+                self.fetching
+                    .run(id, || async move {
+                        // Whoever was fetching it before this caller became the leader may have
+                        // just finished.
+                        if let Some(records) = self.pokemon.get(&id) {
+                            return Ok(records);
+                        }
+
+                        let records = self.fetch_records(id).await?;
+                        self.pokemon.insert(id, records.clone());
+
+                        Ok(records)
+                    })
+                    .await?
+            }
+        };
+
+        Ok(dataset::to_pokemon(pokemon, species))
+    }
+
+    async fn fetch_records(&self, id: u32) -> Result<(PokemonRecord, SpeciesRecord), Error> {
+        // Unless `id` is an alternate form's, the species most likely shares it, so fetch both at
+        // once rather than waiting for the pokemon to say which species it is.
+        let (pokemon, species) = if id < FIRST_FORM_ID {
+            let (pokemon, species) = tokio::join!(
+                self.get_pokemon_from_api(id),
+                self.get_pokemon_species_from_api(id)
+            );
+            (pokemon?, Some(species))
+        } else {
+            (self.get_pokemon_from_api(id).await?, None)
+        };
+
+        let pokemon = PokemonRecord::from(pokemon);
+        let species = match species {
+            Some(species) if pokemon.species_id == id => species?,
+            _ => {
+                self.get_pokemon_species_from_api(pokemon.species_id)
+                    .await?
+            }
+        };
+
+        Ok((pokemon, species.into()))
+    }
+
+    /// The pokemon that make up species `id`, e.g. its alternate forms.
+    pub async fn get_varieties(&self, id: u32) -> Result<Vec<Variety>, Error> {
+        let species = self.get_pokemon_species_from_api(id).await?;
+
+        Ok(species
+            .varieties
+            .into_iter()
+            .filter_map(|variety| {
+                Some(Variety {
+                    pokemon_id: id_from_url(&variety.pokemon.url)?,
+                    name: variety.pokemon.name,
+                    is_default: variety.is_default,
+                })
+            })
+            .collect())
+    }
+
+    /// Whether the pokemon form called `name` only exists during battle, e.g. a mega evolution.
+    #[cfg(not(test))]
+    pub async fn is_battle_only(&self, name: &str) -> Result<bool, Error> {
+        let form = self
+            .get_json::<PokemonFormFromApi>(&format!("pokemon-form/{}", name))
+            .await?;
+
+        Ok(form.is_battle_only)
+    }
+
+    /// Every way species `id` can learn a move in `version_group`.
+    pub async fn get_learnset(
+        &self,
+        id: u32,
+        version_group: &str,
+    ) -> Result<Vec<LearnableMove>, Error> {
+        let pokemon = self.get_pokemon_from_api(id).await?;
+
+        Ok(pokemon
+            .moves
+            .into_iter()
+            .flat_map(|pokemon_move| {
+                let name = pokemon_move.move_.name;
+
+                pokemon_move
+                    .version_group_details
+                    .into_iter()
+                    .filter(|detail| detail.version_group.name == version_group)
+                    .map(move |detail| LearnableMove {
+                        name: name.clone(),
+                        method: detail.move_learn_method.name,
+                        level: detail.level_learned_at,
+                    })
+            })
+            .collect())
+    }
+
+    /// A full-PP slot for the move called `name`.
+    #[cfg(not(test))]
+    pub async fn get_move(&self, name: &str) -> Result<MoveSlot, Error> {
+        let resp = self
+            .get_json::<MoveFromApi>(&format!("move/{}", name))
+            .await?;
+
+        Ok(MoveSlot {
+            name: resp.name,
+            pp: resp.pp.unwrap_or(0),
+            max_pp: resp.pp.unwrap_or(0),
+        })
+    }
+
+    /// What breeding species `id` involves: its egg groups, hatch time and the species at the
+    /// start of its evolution chain.
+    pub async fn get_breeding(&self, id: u32) -> Result<Breeding, Error> {
+        let species = self.get_pokemon_species_from_api(id).await?;
+        let chain_id = species
+            .evolution_chain
+            .as_ref()
+            .and_then(|chain| id_from_url(&chain.url));
+        let baby = match chain_id {
+            Some(chain_id) => {
+                let chain = self
+                    .get_json::<EvolutionChainFromApi>(&format!("evolution-chain/{}", chain_id))
+                    .await?
+                    .chain;
+
+                id_from_url(&chain.species.url).map(|baby_id| (baby_id, chain.species.name))
             }
+            None => None,
+        };
+        let (baby_species_id, baby_species) = match baby {
+            Some(baby) => baby,
+            None => (id, self.get_pokemon_from_api(id).await?.name),
+        };
+
+        Ok(Breeding {
+            egg_groups: species
+                .egg_groups
+                .into_iter()
+                .map(|group| group.name)
+                .collect(),
+            baby_species_id,
+            baby_species,
+            hatch_counter: species.hatch_counter.unwrap_or(0),
+        })
+    }
+
+    /// How the move called `name` behaves in battle.
+    #[cfg(not(test))]
+    pub async fn get_battle_move(&self, name: &str) -> Result<BattleMove, Error> {
+        self.get_json::<MoveFromApi>(&format!("move/{}", name))
+            .await
+            .map(MoveFromApi::into_battle_move)
+    }
+
+    /// Every species and pokemon name PokeAPI knows. Species names stand for their default form,
+    /// which shares the species' id.
+    #[cfg(not(test))]
+    pub async fn get_name_index(&self) -> Result<Arc<NameIndex>, Error> {
+        if let Some(index) = self.names.get(&()) {
+            return Ok(index);
         }
-        Err(e) => {
-            error!("pokemon api error: {:#?}", e);
-            Err(e.into())
+
+        let index = match self.mode {
+            PokeApiMode::Online => self.fetch_name_index().await?,
+            PokeApiMode::Offline => self.dataset.name_index(),
+            PokeApiMode::Fallback => match self.fetch_name_index().await {
+                Ok(index) => index,
+                // Not cached, so PokeAPI's full list is used once it's reachable again.
+                Err(_) => return Ok(Arc::new(self.dataset.name_index())),
+            },
+        };
+        let index = Arc::new(index);
+        self.names.insert((), index.clone());
+
+        Ok(index)
+    }
+
+    #[cfg(not(test))]
+    async fn fetch_name_index(&self) -> Result<NameIndex, Error> {
+        let mut names = vec![];
+        for resource in &["pokemon-species", "pokemon"] {
+            let list = self
+                .get_json::<ResourceListFromApi>(&format!("{}?limit=100000", resource))
+                .await?;
+            names.extend(
+                list.results
+                    .into_iter()
+                    .filter_map(|named| Some((named.name, id_from_url(&named.url)?))),
+            );
         }
+        Ok(NameIndex::new(names))
     }
-}
 
-#[cfg(not(test))]
-async fn get_pokemon_species_from_api(id: u32) -> Result<PokemonSepeciesFromApi, Error> {
-    use log::error;
-    use reqwest::header::CONTENT_TYPE;
+    /// Every pokemon that can be encountered in the location area called `area`, in any version.
+    #[cfg(not(test))]
+    pub async fn get_encounters(&self, area: &str) -> Result<Vec<EncounterSlot>, Error> {
+        if let Some(slots) = self.encounters.get(&area.to_string()) {
+            return Ok(slots);
+        }
 
-    let client = reqwest::Client::new();
-    let req = client
-        .get(&format!("https://pokeapi.co/api/v2/pokemon-species/{}", id))
-        .header(CONTENT_TYPE, "application/json; charset=utf-8");
+        let location_area = self
+            .get_json::<LocationAreaFromApi>(&format!("location-area/{}", area))
+            .await?;
+        let slots = location_area
+            .pokemon_encounters
+            .into_iter()
+            .flat_map(|encounter| {
+                let pokemon = encounter.pokemon;
+                let pokemon_id = id_from_url(&pokemon.url);
 
-    let res = req.send().await;
+                encounter
+                    .version_details
+                    .into_iter()
+                    .flat_map(|version| {
+                        let name = version.version.name;
 
-    match res {
-        Ok(body) => {
-            let body = body.text().await.unwrap();
-            let parsed = serde_json::from_str::<PokemonSepeciesFromApi>(&body);
+                        version
+                            .encounter_details
+                            .into_iter()
+                            .map(move |detail| (name.clone(), detail))
+                    })
+                    .filter_map(move |(version, detail)| {
+                        Some(EncounterSlot {
+                            version,
+                            method: detail.method.name,
+                            pokemon_id: pokemon_id?,
+                            pokemon: pokemon.name.clone(),
+                            min_level: detail.min_level,
+                            max_level: detail.max_level,
+                            chance: detail.chance,
+                        })
+                    })
+            })
+            .collect::<Vec<EncounterSlot>>();
 
-            match parsed {
-                Ok(resp) => Ok(resp),
-                Err(e) => {
-                    error!("pokemon api parsing error: {:#?}", e);
-                    Err(e.into())
-                }
+        self.encounters.insert(area.to_string(), slots.clone());
+
+        Ok(slots)
+    }
+
+    /// The species that species `id` can evolve into next, from its evolution chain.
+    pub async fn get_evolutions(&self, id: u32) -> Result<Vec<Evolution>, Error> {
+        let species = self.get_pokemon_species_from_api(id).await?;
+        let chain_id = match species
+            .evolution_chain
+            .and_then(|chain| id_from_url(&chain.url))
+        {
+            Some(chain_id) => chain_id,
+            None => return Ok(vec![]),
+        };
+
+        let chain = self
+            .get_json::<EvolutionChainFromApi>(&format!("evolution-chain/{}", chain_id))
+            .await?
+            .chain;
+
+        Ok(find_link(chain, id)
+            .map(|link| link.evolves_to)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|next| {
+                Some(Evolution {
+                    species_id: id_from_url(&next.species.url)?,
+                    species: next.species.name,
+                    methods: next
+                        .evolution_details
+                        .into_iter()
+                        .map(EvolutionDetailFromApi::into_method)
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+
+    /// The entries of the pokedex called `name`, e.g. `national` or `kanto`, in pokedex order, or
+    /// `None` if PokeAPI doesn't have one called that.
+    pub async fn get_pokedex(&self, name: &str) -> Result<Option<Vec<DexEntry>>, Error> {
+        if let Some(entries) = self.pokedexes.get(&name.to_string()) {
+            return Ok(Some(entries));
+        }
+
+        let pokedex = match self
+            .find_json::<PokedexFromApi>(&format!("pokedex/{}", name))
+            .await?
+        {
+            Some(pokedex) => pokedex,
+            None => return Ok(None),
+        };
+
+        let mut entries = pokedex
+            .pokemon_entries
+            .into_iter()
+            .filter_map(|entry| {
+                Some(DexEntry {
+                    number: entry.entry_number,
+                    species_id: id_from_url(&entry.pokemon_species.url)?,
+                    species: entry.pokemon_species.name,
+                })
+            })
+            .collect::<Vec<DexEntry>>();
+        entries.sort_by_key(|entry| entry.number);
+        self.pokedexes.insert(name.to_string(), entries.clone());
+
+        Ok(Some(entries))
+    }
+
+    /// Every generation and the species it introduced, oldest first.
+    #[cfg(not(test))]
+    pub async fn get_generations(&self) -> Result<Vec<Generation>, Error> {
+        use futures::future::try_join_all;
+
+        if let Some(generations) = self.generations.get(&()) {
+            return Ok(generations);
+        }
+
+        let list = self
+            .get_json::<ResourceListFromApi>("generation?limit=100")
+            .await?;
+
+        let mut generations = try_join_all(
+            list.results
+                .iter()
+                .filter_map(|resource| id_from_url(&resource.url))
+                .map(|id| async move {
+                    self.get_json::<GenerationFromApi>(&format!("generation/{}", id))
+                        .await
+                }),
+        )
+        .await?;
+        generations.sort_by_key(|generation| generation.id);
+
+        let generations = generations
+            .into_iter()
+            .map(|generation| Generation {
+                name: generation.name,
+                species_ids: generation
+                    .pokemon_species
+                    .iter()
+                    .filter_map(|species| id_from_url(&species.url))
+                    .collect(),
+            })
+            .collect::<Vec<Generation>>();
+        self.generations.insert((), generations.clone());
+
+        Ok(generations)
+    }
+
+    /// Looks up an item by name, or `None` if PokeAPI doesn't know of one called `name`.
+    #[cfg(not(test))]
+    pub async fn get_item(&self, name: &str) -> Result<Option<Item>, Error> {
+        let item = self
+            .find_json::<ItemFromApi>(&format!("item/{}", name))
+            .await?;
+
+        Ok(item.map(|item| Item {
+            name: item.name,
+            category: item.category.name,
+        }))
+    }
+
+    #[cfg(not(test))]
+    pub async fn ping(&self) -> Result<(), Error> {
+        use log::error;
+        use std::time::Duration;
+
+        // Nothing is fetched from PokeAPI in offline mode, so it doesn't matter if it's down.
+        if self.mode == PokeApiMode::Offline {
+            return Ok(());
+        }
+
+        let res = self.get("").timeout(Duration::from_secs(2)).send().await;
+
+        match res.and_then(|res| res.error_for_status()) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("pokemon api ping error: {:#?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Offline, only what the dataset holds is served, and nothing else is fetched from PokeAPI:
+    /// `path` fails straight away instead.
+    #[cfg(not(test))]
+    fn check_online(&self, path: &str) -> Result<(), Error> {
+        use anyhow::anyhow;
+
+        if self.mode == PokeApiMode::Offline {
+            return Err(anyhow!(
+                "{} isn't in the offline dataset, and PokeAPI isn't used offline",
+                path
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fetches `path` and parses the body as `T`, logging whatever goes wrong.
+    #[cfg(not(test))]
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        use log::error;
+
+        self.check_online(path)?;
+        let res = self.get(path).send().await;
+
+        match res.and_then(|res| res.error_for_status()) {
+            Ok(body) => {
+                let body = body.text().await?;
+
+                parse(&body)
+            }
+            Err(e) => {
+                error!("pokemon api error: {:#?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// `get_json`, but `None` when PokeAPI has nothing at `path`.
+    #[cfg(not(test))]
+    async fn find_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<T>, Error> {
+        use log::error;
+        use reqwest::StatusCode;
+
+        self.check_online(path)?;
+        let res = self.get(path).send().await;
+        if let Ok(res) = &res {
+            if res.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+        }
+
+        match res.and_then(|res| res.error_for_status()) {
+            Ok(body) => parse(&body.text().await?).map(Some),
+            Err(e) => {
+                error!("pokemon api error: {:#?}", e);
+                Err(e.into())
             }
         }
-        Err(e) => {
-            error!("pokemon api error: {:#?}", e);
-            Err(e.into())
+    }
+
+    /// Serves `path` from the PokeAPI responses saved under `fixtures/pokeapi`.
+    #[cfg(test)]
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        use anyhow::anyhow;
+
+        let body = std::fs::read_to_string(fixture(path))
+            .map_err(|e| anyhow!("no PokeAPI fixture for {}: {}", path, e))?;
+
+        parse(&body)
+    }
+
+    /// `get_json`, but `None` when there's no fixture for `path`.
+    #[cfg(test)]
+    async fn find_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<T>, Error> {
+        if !fixture(path).exists() {
+            return Ok(None);
+        }
+
+        self.get_json(path).await.map(Some)
+    }
+
+    /// Builds a GET for `path` under the base URL, forwarding the id of the request we're serving
+    /// so upstream calls can be correlated with it.
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        use crate::app::REQUEST_ID_HEADER;
+        use crate::logging::current_request_id;
+        use log::debug;
+        use reqwest::header::CONTENT_TYPE;
+
+        let url = format!("{}/{}", self.base_url, path);
+        debug!("pokemon api request: GET {}", url);
+
+        let req = self
+            .client
+            .get(&url)
+            .header(CONTENT_TYPE, "application/json; charset=utf-8");
+
+        match current_request_id() {
+            Some(request_id) => req.header(REQUEST_ID_HEADER, request_id),
+            None => req,
         }
     }
+
+    async fn get_pokemon_from_api(&self, id: u32) -> Result<PokemonFromApi, Error> {
+        self.get_json(&format!("pokemon/{}", id)).await
+    }
+
+    async fn get_pokemon_species_from_api(&self, id: u32) -> Result<PokemonSepeciesFromApi, Error> {
+        self.get_json(&format!("pokemon-species/{}", id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::REQUEST_ID_HEADER;
+    use crate::logging::REQUEST_ID;
+
+    #[tokio::test]
+    async fn pokemon_are_put_together_from_pokeapi_responses() {
+        let kabutops = PokeApi::default().get_pokemon(141).await.unwrap();
+
+        assert_eq!(kabutops.pokeAPI_id, 141);
+        assert_eq!(kabutops.name, "kabutops");
+        assert_eq!(kabutops.base_experience, 173);
+        assert_eq!(kabutops.base_happiness, 50);
+        assert_eq!(kabutops.growth_rate, GrowthRate::Medium);
+        // PokeAPI doesn't promise any order, so types and abilities are sorted by slot.
+        assert_eq!(kabutops.types, vec!["rock", "water"]);
+        assert_eq!(
+            kabutops
+                .abilities
+                .iter()
+                .map(|ability| (ability.name.as_str(), ability.is_hidden))
+                .collect::<Vec<_>>(),
+            vec![
+                ("swift-swim", false),
+                ("battle-armor", false),
+                ("weak-armor", true)
+            ]
+        );
+        assert_eq!(kabutops.base_stats.attack, 115);
+        assert_eq!(kabutops.base_stats.special_defense, 70);
+        assert!(kabutops
+            .sprites
+            .back_shiny
+            .unwrap()
+            .ends_with("/back/shiny/141.png"));
+    }
+
+    #[tokio::test]
+    async fn alternate_forms_take_their_species_from_the_pokemon() {
+        let giratina = PokeApi::default().get_pokemon(10007).await.unwrap();
+
+        assert_eq!(giratina.pokeAPI_id, 487);
+        assert_eq!(giratina.name, "giratina-origin");
+        assert_eq!(giratina.species, "giratina");
+        assert_eq!(giratina.instance.form.as_deref(), Some("giratina-origin"));
+        assert_eq!(giratina.types, vec!["ghost", "dragon"]);
+        assert_eq!(giratina.base_stats.hp, 150);
+        assert!(giratina.is_legendary);
+    }
+
+    #[tokio::test]
+    async fn upstream_requests_carry_the_request_id() {
+        let api = PokeApi::new("http://pokeapi.test/api/v2");
+
+        let request = REQUEST_ID
+            .scope("abc-123".to_string(), async {
+                api.get("pokemon/140").build().unwrap()
+            })
+            .await;
+        assert_eq!(
+            request.url().as_str(),
+            "http://pokeapi.test/api/v2/pokemon/140"
+        );
+        assert_eq!(request.headers()[REQUEST_ID_HEADER], "abc-123");
+
+        let request = api.get("pokemon/140").build().unwrap();
+        assert!(request.headers().get(REQUEST_ID_HEADER).is_none());
+    }
 }
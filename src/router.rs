@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use thruster::MiddlewareChain;
+
+use crate::context::Ctx;
+
+/// Matches requests against the route table in `app::routes`.
+///
+/// thruster 1.0.3 rebuilds the node behind a `:param` segment every time a route is added through
+/// it, so of `GET /pokemon/:id` and `GET /pokemon/:id/moves/learnable` only the one registered last
+/// would be reachable. Instead thruster gets a single catch-all route per method and hands the
+/// request to `app::dispatch`, which resolves it here.
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+struct Route {
+    method: &'static str,
+    path: &'static str,
+    segments: Vec<&'static str>,
+    middleware: MiddlewareChain<Ctx>,
+}
+
+pub struct MatchedRoute<'a> {
+    /// The path the route was registered with, e.g. `/pokemon/:id`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub path: &'static str,
+    pub params: HashMap<String, String>,
+    pub middleware: &'a MiddlewareChain<Ctx>,
+}
+
+impl Route {
+    fn matches(&self, request_segments: &[&str]) -> bool {
+        self.segments.len() == request_segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(request_segments.iter())
+                .all(|(segment, piece)| segment.starts_with(':') || segment == piece)
+    }
+}
+
+fn segments(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+impl Router {
+    pub fn new(routes: Vec<(&'static str, &'static str, MiddlewareChain<Ctx>)>) -> Self {
+        Router {
+            routes: routes
+                .into_iter()
+                .map(|(method, path, middleware)| Route {
+                    method,
+                    path,
+                    segments: segments(path),
+                    // thruster only builds a chain into something runnable when it's cloned.
+                    middleware: middleware.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The methods with a route for `path`, so a request using any other can be told which to
+    /// use instead of being told nothing is there.
+    pub fn allowed_methods(&self, path: &str) -> Vec<&'static str> {
+        let request_segments = segments(path);
+        let mut methods = self
+            .routes
+            .iter()
+            .filter(|route| route.matches(&request_segments))
+            .map(|route| route.method)
+            .collect::<Vec<&'static str>>();
+        methods.sort_unstable();
+        methods.dedup();
+
+        methods
+    }
+
+    /// Finds the route for a request. Where more than one matches, the one with the most literal
+    /// segments wins, so `GET /pokemon/:id/moves/learnable` is preferred over a route like
+    /// `GET /pokemon/:id/moves/:move`.
+    pub fn resolve(&self, method: &str, path: &str) -> Option<MatchedRoute<'_>> {
+        let request_segments = segments(path);
+
+        self.routes
+            .iter()
+            .filter(|route| route.method == method && route.matches(&request_segments))
+            .max_by_key(|route| {
+                route
+                    .segments
+                    .iter()
+                    .filter(|segment| !segment.starts_with(':'))
+                    .count()
+            })
+            .map(|route| MatchedRoute {
+                path: route.path,
+                params: route
+                    .segments
+                    .iter()
+                    .zip(request_segments.iter())
+                    .filter_map(|(segment, piece)| {
+                        segment
+                            .strip_prefix(':')
+                            .map(|name| (name.to_string(), piece.to_string()))
+                    })
+                    .collect(),
+                middleware: &route.middleware,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app;
+
+    #[test]
+    fn the_most_specific_route_wins() {
+        let router = Router::new(app::routes());
+
+        let matched = router.resolve("GET", "/pokemon/7/moves/learnable").unwrap();
+        assert_eq!(matched.path, "/pokemon/:id/moves/learnable");
+        assert_eq!(matched.params["id"], "7");
+
+        let matched = router.resolve("DELETE", "/pokemon/7/moves/surf").unwrap();
+        assert_eq!(matched.path, "/pokemon/:id/moves/:move");
+        assert_eq!(matched.params["move"], "surf");
+    }
+
+    #[test]
+    fn other_methods_on_a_known_path_are_listed() {
+        let router = Router::new(app::routes());
+
+        assert!(router.resolve("PUT", "/parties").is_none());
+        assert_eq!(router.allowed_methods("/parties"), vec!["GET"]);
+        assert!(router.allowed_methods("/no-such-route").is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::pokemon::Pokemon;
+
+/// The format a league runs, checked against the party whenever it changes. Every rule is off
+/// unless configured.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /// No two party members of the same species.
+    pub species_clause: bool,
+    /// No two party members holding the same item.
+    pub item_clause: bool,
+    pub level_cap: Option<u32>,
+    /// Bans legendary and mythical species.
+    pub ban_legendaries: bool,
+    /// Species names that aren't allowed in the party, e.g. `mewtwo`.
+    pub banned_species: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    DuplicateSpecies(String),
+    DuplicateItem(String),
+    OverLevelCap { name: String, level: u32, cap: u32 },
+    Legendary(String),
+    BannedSpecies(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DuplicateSpecies(name) => {
+                write!(f, "species clause: more than one {} in the party", name)
+            }
+            Violation::DuplicateItem(item) => {
+                write!(f, "item clause: more than one party member holds {}", item)
+            }
+            Violation::OverLevelCap { name, level, cap } => write!(
+                f,
+                "{} is level {}, over the level cap of {}",
+                name, level, cap
+            ),
+            Violation::Legendary(name) => {
+                write!(f, "{} is legendary and legendaries are banned", name)
+            }
+            Violation::BannedSpecies(name) => write!(f, "{} is banned", name),
+        }
+    }
+}
+
+/// What the rules look at in a party member. Storage builds these from stored pokemon and adjusts
+/// them to check a change before making it.
+#[derive(Clone, Debug)]
+pub struct Member<'a> {
+    pub id: u32,
+    pub species_id: u32,
+    pub species: &'a str,
+    pub level: u32,
+    pub held_item: Option<&'a str>,
+    pub legendary: bool,
+}
+
+impl<'a> Member<'a> {
+    pub fn of(pokemon: &'a Pokemon) -> Self {
+        Member {
+            id: pokemon.id(),
+            species_id: pokemon.pokeAPI_id,
+            species: pokemon.species_name(),
+            level: pokemon.instance.level,
+            held_item: pokemon
+                .instance
+                .held_item
+                .as_ref()
+                .map(|item| item.name.as_str()),
+            legendary: pokemon.is_legendary || pokemon.is_mythical,
+        }
+    }
+}
+
+impl Rules {
+    /// Everything about `party` that breaks the rules, in party order. Empty when it's legal.
+    pub fn check(&self, party: &[Member]) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut species = HashSet::new();
+        let mut items = HashSet::new();
+
+        for member in party {
+            if self
+                .banned_species
+                .iter()
+                .any(|name| name == member.species)
+            {
+                violations.push(Violation::BannedSpecies(member.species.to_string()));
+            }
+            if self.ban_legendaries && member.legendary {
+                violations.push(Violation::Legendary(member.species.to_string()));
+            }
+            if let Some(cap) = self.level_cap {
+                if member.level > cap {
+                    violations.push(Violation::OverLevelCap {
+                        name: member.species.to_string(),
+                        level: member.level,
+                        cap,
+                    });
+                }
+            }
+            if self.species_clause && !species.insert(member.species_id) {
+                violations.push(Violation::DuplicateSpecies(member.species.to_string()));
+            }
+            if let Some(item) = member.held_item {
+                if self.item_clause && !items.insert(item) {
+                    violations.push(Violation::DuplicateItem(item.to_string()));
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(
+        id: u32,
+        species: &'static str,
+        level: u32,
+        held_item: Option<&'static str>,
+    ) -> Member<'static> {
+        Member {
+            id,
+            species_id: match species {
+                "kabuto" => 140,
+                "mewtwo" => 150,
+                _ => 1,
+            },
+            species,
+            level,
+            held_item,
+            legendary: species == "mewtwo",
+        }
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let rules = Rules {
+            species_clause: true,
+            item_clause: true,
+            level_cap: Some(50),
+            ban_legendaries: true,
+            banned_species: vec!["bulbasaur".to_string()],
+        };
+        let party = [
+            member(1, "kabuto", 50, Some("leftovers")),
+            member(2, "kabuto", 51, Some("leftovers")),
+            member(3, "mewtwo", 70, None),
+            member(4, "bulbasaur", 5, None),
+        ];
+
+        assert_eq!(
+            rules.check(&party),
+            vec![
+                Violation::OverLevelCap {
+                    name: "kabuto".to_string(),
+                    level: 51,
+                    cap: 50
+                },
+                Violation::DuplicateSpecies("kabuto".to_string()),
+                Violation::DuplicateItem("leftovers".to_string()),
+                Violation::Legendary("mewtwo".to_string()),
+                Violation::OverLevelCap {
+                    name: "mewtwo".to_string(),
+                    level: 70,
+                    cap: 50
+                },
+                Violation::BannedSpecies("bulbasaur".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn allows_everything_by_default() {
+        let party = [
+            member(1, "kabuto", 100, Some("leftovers")),
+            member(2, "kabuto", 100, Some("leftovers")),
+            member(3, "mewtwo", 100, None),
+        ];
+
+        assert!(Rules::default().check(&party).is_empty());
+    }
+}
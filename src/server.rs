@@ -0,0 +1,171 @@
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Server;
+use log::{info, warn};
+use std::future::Future;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use thruster::context::hyper_request::HyperRequest;
+use thruster::App;
+use tokio::sync::oneshot;
+
+use crate::context::{AppState, Ctx};
+
+/// Serves `app` on `listener` until `shutdown` resolves. Once it does we stop accepting new
+/// connections and give in-flight requests up to `drain_timeout` to finish before returning.
+pub async fn serve<F>(
+    mut app: App<HyperRequest, Ctx, Arc<AppState>>,
+    listener: TcpListener,
+    shutdown: F,
+    drain_timeout: Duration,
+) -> Result<(), hyper::Error>
+where
+    F: Future<Output = ()>,
+{
+    app._route_parser.optimize();
+    let app = Arc::new(app);
+
+    let make_service = make_service_fn(move |stream: &AddrStream| {
+        let app = app.clone();
+        let ip = stream.remote_addr().ip();
+
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |request| {
+                let matched = app.resolve_from_method_and_path(
+                    request.method().as_str(),
+                    &request.uri().path_and_query().unwrap().to_string(),
+                );
+
+                let mut request = HyperRequest::new(request);
+                request.ip = Some(ip);
+                app.resolve(request, matched)
+            }))
+        }
+    });
+
+    let (draining_tx, draining_rx) = oneshot::channel::<()>();
+    let server = Server::from_tcp(listener)?
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            shutdown.await;
+            info!("Shutting down, draining in-flight requests");
+            let _ = draining_tx.send(());
+        });
+
+    let deadline = async move {
+        match draining_rx.await {
+            Ok(()) => tokio::time::delay_for(drain_timeout).await,
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    tokio::select! {
+        result = server => result,
+        _ = deadline => {
+            warn!(
+                "In-flight requests did not drain within {}s, abandoning them",
+                drain_timeout.as_secs()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Resolves on SIGINT or SIGTERM, whichever comes first.
+#[cfg(not(test))]
+pub async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+        _ = terminate.recv() => info!("Received SIGTERM"),
+    }
+}
+
+/// Runs the app on a free local port for tests that go through HTTP.
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use crate::app;
+    use std::net::SocketAddr;
+    use tokio::task::JoinHandle;
+
+    pub struct TestServer {
+        pub addr: SocketAddr,
+        pub server: JoinHandle<Result<(), hyper::Error>>,
+    }
+
+    impl TestServer {
+        pub fn url(&self, path: &str) -> String {
+            format!("http://{}{}", self.addr, path)
+        }
+
+        /// Waits for the server to finish once it's been told to shut down.
+        pub async fn stopped(self) {
+            self.server.await.unwrap().unwrap();
+        }
+    }
+
+    /// Serves `state` until `shutdown` resolves.
+    pub async fn start_with<F>(state: Arc<AppState>, shutdown: F) -> TestServer
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let app = app::create(state).await;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve(app, listener, shutdown, Duration::from_secs(5)));
+
+        TestServer { addr, server }
+    }
+
+    /// Serves `state` until the returned sender is used.
+    pub async fn start(state: Arc<AppState>) -> (TestServer, oneshot::Sender<()>) {
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let server = start_with(state, async move {
+            let _ = shutdown_rx.await;
+        })
+        .await;
+
+        (server, shutdown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn move_in_progress_completes_during_shutdown() {
+        let state = Arc::new(AppState::default());
+        let (server, shutdown) = testing::start(state.clone()).await;
+
+        // Hold the storage lock so the move is guaranteed to still be running when we shut down.
+        let storage_guard = state.storage_mut().await;
+        let request = tokio::spawn(
+            reqwest::Client::new()
+                .post(&server.url("/parties/pokemon"))
+                .body(r#"{"pokeAPI_id": 141}"#)
+                .send(),
+        );
+        tokio::time::delay_for(Duration::from_millis(200)).await;
+
+        shutdown.send(()).unwrap();
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        drop(storage_guard);
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("\"pokeAPI_id\":141"));
+
+        server.stopped().await;
+        assert_eq!(state.storage().await.get_party().unwrap().len(), 1);
+    }
+}
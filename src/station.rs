@@ -0,0 +1,297 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::instance::{MAX_LEVEL, MIN_LEVEL};
+use crate::names;
+use crate::pokemon::Pokemon;
+
+#[derive(Debug, PartialEq)]
+pub enum StationError {
+    /// There's no listing with that id.
+    NotFound,
+    /// Only boxed pokemon can be deposited.
+    NotBoxed(u32),
+    NotYours,
+    OwnListing,
+    Levels(Option<u32>, Option<u32>),
+    Unwanted(Wanted),
+}
+
+impl fmt::Display for StationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StationError::NotFound => write!(f, "there's no listing with that id"),
+            StationError::NotBoxed(id) => write!(
+                f,
+                "pokemon {} isn't in a box, only boxed pokemon can be deposited",
+                id
+            ),
+            StationError::NotYours => write!(f, "only the trainer who deposited it can withdraw it"),
+            StationError::OwnListing => write!(f, "trainers can't fulfil their own listings"),
+            StationError::Levels(min, max) => write!(
+                f,
+                "wanted levels have to be between {} and {} with the minimum first, not {:?} to {:?}",
+                MIN_LEVEL, MAX_LEVEL, min, max
+            ),
+            StationError::Unwanted(wanted) => write!(f, "the listing wants {}", wanted),
+        }
+    }
+}
+
+/// What a depositor will take for their pokemon.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Wanted {
+    pub species_id: u32,
+    pub species: String,
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+}
+
+impl Wanted {
+    pub fn new(
+        species: &Pokemon,
+        min_level: Option<u32>,
+        max_level: Option<u32>,
+    ) -> Result<Self, StationError> {
+        let in_range =
+            |level: Option<u32>| level.is_none_or(|level| (MIN_LEVEL..=MAX_LEVEL).contains(&level));
+        let ordered = match (min_level, max_level) {
+            (Some(min), Some(max)) => min <= max,
+            _ => true,
+        };
+        if !in_range(min_level) || !in_range(max_level) || !ordered {
+            return Err(StationError::Levels(min_level, max_level));
+        }
+
+        Ok(Wanted {
+            species_id: species.pokeAPI_id,
+            species: species.species_name().to_string(),
+            min_level,
+            max_level,
+        })
+    }
+
+    pub fn accepts(&self, pokemon: &Pokemon) -> bool {
+        let level = pokemon.instance.level;
+
+        pokemon.pokeAPI_id == self.species_id
+            && self.min_level.is_none_or(|min| level >= min)
+            && self.max_level.is_none_or(|max| level <= max)
+    }
+}
+
+impl fmt::Display for Wanted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {}", self.species)?;
+        match (self.min_level, self.max_level) {
+            (Some(min), Some(max)) => write!(f, " of level {} to {}", min, max),
+            (Some(min), None) => write!(f, " of level {} or more", min),
+            (None, Some(max)) => write!(f, " of level {} or less", max),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// A pokemon on offer to anyone with what its trainer wants. It's out of their storage while it's
+/// listed, so nothing can change it until it's withdrawn or traded.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Listing {
+    pub id: u32,
+    pub trainer: String,
+    /// As it was when deposited, `id` being the one it had in its trainer's storage.
+    pub pokemon: Pokemon,
+    /// The box it was deposited from, which it goes back to if there's room.
+    #[serde(rename = "box")]
+    pub box_: usize,
+    pub wanted: Wanted,
+    pub deposited: NaiveDateTime,
+}
+
+/// What to narrow the listings down to, any of it left out matching everything.
+#[derive(Debug, Default)]
+pub struct Search {
+    /// The species on offer.
+    pub species: Option<String>,
+    /// The species wanted for it.
+    pub wanted: Option<String>,
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+    pub trainer: Option<String>,
+}
+
+impl Search {
+    pub fn matches(&self, listing: &Listing) -> bool {
+        let named = |filter: &Option<String>, name: &str| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| names::normalize(filter) == names::normalize(name))
+        };
+        let level = listing.pokemon.instance.level;
+
+        named(&self.species, listing.pokemon.species_name())
+            && named(&self.wanted, &listing.wanted.species)
+            && self.min_level.is_none_or(|min| level >= min)
+            && self.max_level.is_none_or(|max| level <= max)
+            && self
+                .trainer
+                .as_ref()
+                .is_none_or(|trainer| *trainer == listing.trainer)
+    }
+}
+
+fn first_listing_id() -> u32 {
+    1
+}
+
+/// The global trade station, where trainers leave pokemon for anyone to trade for.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Station {
+    listings: Vec<Listing>,
+    #[serde(default = "first_listing_id")]
+    next_listing_id: u32,
+}
+
+impl Default for Station {
+    fn default() -> Self {
+        Station {
+            listings: vec![],
+            next_listing_id: first_listing_id(),
+        }
+    }
+}
+
+impl Station {
+    pub fn list(
+        &mut self,
+        trainer: &str,
+        pokemon: Pokemon,
+        box_: usize,
+        wanted: Wanted,
+        now: NaiveDateTime,
+    ) -> &Listing {
+        let id = self.next_listing_id;
+        self.next_listing_id += 1;
+        self.listings.push(Listing {
+            id,
+            trainer: trainer.to_string(),
+            pokemon,
+            box_,
+            wanted,
+            deposited: now,
+        });
+
+        self.listings.last().unwrap()
+    }
+
+    /// The listings matching `search`, oldest first.
+    pub fn search(&self, search: &Search) -> Vec<&Listing> {
+        self.listings
+            .iter()
+            .filter(|listing| search.matches(listing))
+            .collect()
+    }
+
+    pub fn get(&self, listing_id: u32) -> Result<&Listing, StationError> {
+        self.listings
+            .iter()
+            .find(|listing| listing.id == listing_id)
+            .ok_or(StationError::NotFound)
+    }
+
+    pub fn remove(&mut self, listing_id: u32) -> Result<Listing, StationError> {
+        let index = self
+            .listings
+            .iter()
+            .position(|listing| listing.id == listing_id)
+            .ok_or(StationError::NotFound)?;
+
+        Ok(self.listings.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn pokemon(species_id: u32, name: &str, level: u32) -> Pokemon {
+        serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": species_id,
+            "name": name,
+            "height": 5,
+            "weight": 115,
+            "base_happiness": 70,
+            "level": level,
+        }))
+        .unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn wanted_pokemon_match_species_and_levels() {
+        let pikachu = pokemon(25, "pikachu", 5);
+        assert_eq!(
+            Wanted::new(&pikachu, Some(20), Some(10)),
+            Err(StationError::Levels(Some(20), Some(10)))
+        );
+        assert!(Wanted::new(&pikachu, None, Some(MAX_LEVEL + 1)).is_err());
+
+        let wanted = Wanted::new(&pikachu, Some(10), Some(20)).unwrap();
+        assert!(wanted.accepts(&pokemon(25, "pikachu", 15)));
+        assert!(!wanted.accepts(&pokemon(25, "pikachu", 5)));
+        assert!(!wanted.accepts(&pokemon(26, "raichu", 15)));
+        assert_eq!(wanted.to_string(), "a pikachu of level 10 to 20");
+    }
+
+    #[test]
+    fn listings_are_searched_by_what_they_offer_and_want() {
+        let mut station = Station::default();
+        let eevee = Wanted::new(&pokemon(133, "eevee", 1), None, None).unwrap();
+        let pikachu = Wanted::new(&pokemon(25, "pikachu", 1), Some(30), None).unwrap();
+        let first = station
+            .list("ash", pokemon(1, "bulbasaur", 12), 0, eevee, now())
+            .id;
+        let second = station
+            .list("misty", pokemon(120, "staryu", 30), 1, pikachu, now())
+            .id;
+
+        let ids = |search: Search| {
+            station
+                .search(&search)
+                .iter()
+                .map(|listing| listing.id)
+                .collect::<Vec<u32>>()
+        };
+        assert_eq!(ids(Search::default()), vec![first, second]);
+        assert_eq!(
+            ids(Search {
+                species: Some("Bulbasaur".to_string()),
+                ..Search::default()
+            }),
+            vec![first]
+        );
+        assert_eq!(
+            ids(Search {
+                wanted: Some("pikachu".to_string()),
+                min_level: Some(20),
+                ..Search::default()
+            }),
+            vec![second]
+        );
+        assert!(ids(Search {
+            trainer: Some("brock".to_string()),
+            ..Search::default()
+        })
+        .is_empty());
+
+        assert_eq!(station.remove(first).unwrap().trainer, "ash");
+        assert_eq!(station.get(first).err(), Some(StationError::NotFound));
+    }
+}
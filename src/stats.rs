@@ -0,0 +1,122 @@
+use crate::instance::{Nature, Stat};
+use crate::pokemon::{Pokemon, Stats};
+
+/// Shedinja always has exactly 1 HP, whatever the formula says.
+const SHEDINJA: u32 = 292;
+
+/// Multiplier applied by `nature` to `stat`, as a percentage.
+fn nature_percent(nature: Nature, stat: Stat) -> u32 {
+    match nature.modifiers() {
+        Some((raised, _)) if raised == stat => 110,
+        Some((_, lowered)) if lowered == stat => 90,
+        _ => 100,
+    }
+}
+
+/// Computes a single stat using the mainline (generation III onwards) formulas. Every division
+/// rounds down, including the nature multiplier at the end.
+pub fn calculate_stat(stat: Stat, base: u32, iv: u32, ev: u32, level: u32, nature: Nature) -> u32 {
+    let scaled = (2 * base + iv + ev / 4) * level / 100;
+
+    match stat {
+        Stat::Hp => scaled + level + 10,
+        _ => (scaled + 5) * nature_percent(nature, stat) / 100,
+    }
+}
+
+/// The actual stats of an owned pokemon, derived from its species' base stats and its own level,
+/// IVs, EVs and nature.
+pub fn calculate(pokemon: &Pokemon) -> Stats {
+    let instance = &pokemon.instance;
+    let stat = |stat: Stat| {
+        calculate_stat(
+            stat,
+            pokemon.base_stats.get(stat),
+            instance.ivs.get(stat),
+            instance.evs.get(stat),
+            instance.level,
+            instance.nature,
+        )
+    };
+
+    Stats {
+        hp: if pokemon.pokeAPI_id == SHEDINJA {
+            1
+        } else {
+            stat(Stat::Hp)
+        },
+        attack: stat(Stat::Attack),
+        defense: stat(Stat::Defense),
+        special_attack: stat(Stat::SpecialAttack),
+        special_defense: stat(Stat::SpecialDefense),
+        speed: stat(Stat::Speed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculates_known_values() {
+        // (stat, base, iv, ev, level, nature, expected)
+        let cases = [
+            // Bulbapedia's worked example: a level 78 Adamant Garchomp.
+            (Stat::Hp, 108, 24, 74, 78, Nature::Adamant, 289),
+            (Stat::Attack, 130, 12, 190, 78, Nature::Adamant, 278),
+            (Stat::Defense, 95, 30, 91, 78, Nature::Adamant, 193),
+            (Stat::SpecialAttack, 80, 16, 48, 78, Nature::Adamant, 135),
+            (Stat::SpecialDefense, 85, 23, 84, 78, Nature::Adamant, 171),
+            (Stat::Speed, 102, 5, 23, 78, Nature::Adamant, 171),
+            // Maxed out level 100 Blissey.
+            (Stat::Hp, 255, 31, 252, 100, Nature::Bold, 714),
+            (Stat::Defense, 10, 31, 252, 100, Nature::Bold, 130),
+            (Stat::Attack, 10, 31, 0, 100, Nature::Bold, 50),
+            // Level 50 Timid Pikachu with maxed speed.
+            (Stat::Speed, 90, 31, 252, 50, Nature::Timid, 156),
+            (Stat::Attack, 55, 31, 0, 50, Nature::Timid, 67),
+            // Neutral natures change nothing.
+            (Stat::Speed, 90, 31, 252, 50, Nature::Hardy, 142),
+            // The floor of everything at level 1.
+            (Stat::Hp, 1, 0, 0, 1, Nature::Hardy, 11),
+            (Stat::Attack, 5, 0, 0, 1, Nature::Hardy, 5),
+        ];
+
+        for (stat, base, iv, ev, level, nature, expected) in cases.iter() {
+            assert_eq!(
+                calculate_stat(*stat, *base, *iv, *ev, *level, *nature),
+                *expected,
+                "{:?} with base {}, IV {}, EV {} at level {} ({:?})",
+                stat,
+                base,
+                iv,
+                ev,
+                level,
+                nature
+            );
+        }
+    }
+
+    #[test]
+    fn shedinja_always_has_one_hp() {
+        let mut pokemon: Pokemon = serde_json::from_str(
+            r#"{
+                "pokeAPI_id": 292,
+                "name": "shedinja",
+                "height": 8,
+                "weight": 12,
+                "base_happiness": 0,
+                "base_stats": { "hp": 1, "attack": 90, "defense": 45, "special_attack": 30, "special_defense": 30, "speed": 40 }
+            }"#,
+        )
+        .unwrap();
+        pokemon.instance.level = 100;
+        pokemon.instance.ivs.hp = 31;
+        pokemon.instance.evs.hp = 252;
+
+        let stats = calculate(&pokemon);
+
+        assert_eq!(stats.hp, 1);
+        assert_eq!(stats.attack, 185);
+    }
+}
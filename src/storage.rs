@@ -1,6 +1,22 @@
-use std::collections::HashMap;
+use chrono::Utc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 
+use crate::day_care::{self, Breeding, Clock, DayCare, DayCareError, Egg, SystemClock};
+use crate::evolution::{self, Evolution, EvolutionError, Trigger};
+use crate::forms;
+use crate::instance::Instance;
+use crate::items::{Bag, BagError, Item};
+use crate::moves::{self, LearnableMove, MoveError, MoveSlot};
+use crate::nuzlocke::{Encounter, NuzlockeError, Outcome, Run, Status};
+use crate::pokedex::Pokedex;
 use crate::pokemon::Pokemon;
+use crate::rules::{Member, Rules, Violation};
+use crate::station::StationError;
+use crate::trading::TradeError;
+use crate::wild::{self, Throw, WildEncounter, WildError};
 
 const DEFAULT_MAX_PARTY_SIZE: usize = 6;
 const DEFAULT_MAX_BOX_SIZE: usize = 30;
@@ -8,6 +24,10 @@ const DEFAULT_MAX_BOX_SIZE: usize = 30;
 pub enum StorageDestination {
     Party,
     Box(usize),
+    /// Carries what the day care needs to know to breed the pokemon.
+    DayCare(Breeding),
+    /// Wherever there's room, see `auto_destination`.
+    Auto,
 }
 
 #[derive(Debug)]
@@ -15,36 +35,325 @@ pub enum StorageError {
     ContainerIsFull,
     BoxDoesNotExist,
     PokemonNotFound,
+    AlreadyHoldingItem,
+    NotHoldingItem,
+    Bag(BagError),
+    /// The change would break the party rules in these ways.
+    IllegalParty(Vec<Violation>),
+    Nuzlocke(NuzlockeError),
+    DayCare(DayCareError),
+    Wild(WildError),
+    Evolution(EvolutionError),
+    Move(MoveError),
+    Trade(TradeError),
+    Station(StationError),
+    /// The pokemon changed while the request was being worked on, e.g. it evolved.
+    Changed,
 }
 
-#[derive(Debug)]
+impl From<BagError> for StorageError {
+    fn from(error: BagError) -> Self {
+        StorageError::Bag(error)
+    }
+}
+
+impl From<NuzlockeError> for StorageError {
+    fn from(error: NuzlockeError) -> Self {
+        StorageError::Nuzlocke(error)
+    }
+}
+
+impl From<DayCareError> for StorageError {
+    fn from(error: DayCareError) -> Self {
+        StorageError::DayCare(error)
+    }
+}
+
+impl From<WildError> for StorageError {
+    fn from(error: WildError) -> Self {
+        StorageError::Wild(error)
+    }
+}
+
+impl From<EvolutionError> for StorageError {
+    fn from(error: EvolutionError) -> Self {
+        StorageError::Evolution(error)
+    }
+}
+
+impl From<MoveError> for StorageError {
+    fn from(error: MoveError) -> Self {
+        StorageError::Move(error)
+    }
+}
+
+impl From<TradeError> for StorageError {
+    fn from(error: TradeError) -> Self {
+        StorageError::Trade(error)
+    }
+}
+
+impl From<StationError> for StorageError {
+    fn from(error: StationError) -> Self {
+        StorageError::Station(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 enum ContainerLocation {
     Party,
     Box(usize),
+    DayCare,
 }
 
-#[derive(Debug)]
+/// Where a pokemon is kept, as reported to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Location {
+    Party,
+    Box(usize),
+    DayCare,
+}
+
+impl From<ContainerLocation> for Location {
+    fn from(location: ContainerLocation) -> Self {
+        match location {
+            ContainerLocation::Party => Location::Party,
+            ContainerLocation::Box(i) => Location::Box(i),
+            ContainerLocation::DayCare => Location::DayCare,
+        }
+    }
+}
+
+fn first_pokemon_id() -> u32 {
+    1
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Storage {
     party: Container,
     boxes: Vec<Container>,
     max_party_size: usize,
     max_box_size: usize,
     pokemon_locations: HashMap<u32, ContainerLocation>,
+    #[serde(default = "first_pokemon_id")]
+    next_pokemon_id: u32,
+    #[serde(default)]
+    bag: Bag,
+    #[serde(default)]
+    pokedex: Pokedex,
+    #[serde(default)]
+    nuzlocke: Option<Run>,
+    #[serde(default)]
+    day_care: DayCare,
+    /// Collected from the day care and waiting to hatch.
+    #[serde(default)]
+    eggs: Vec<Egg>,
+    #[serde(default = "first_pokemon_id")]
+    next_egg_id: u32,
+    #[serde(default)]
+    wild: Option<WildEncounter>,
+    /// Pokemon held for a trade, which can't be changed, moved or released until it's done.
+    #[serde(default)]
+    escrow: BTreeSet<u32>,
+    /// Comes from config rather than snapshots, see `set_rules`.
+    #[serde(skip)]
+    rules: Rules,
+    /// Whether `Auto` adds a box when every box is full. Comes from config too.
+    #[serde(skip)]
+    auto_create_boxes: bool,
+    /// What the day care measures time by, see `set_clock`.
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for Storage {
     fn default() -> Self {
+        Storage::new(DEFAULT_MAX_PARTY_SIZE, DEFAULT_MAX_BOX_SIZE)
+    }
+}
+
+impl Storage {
+    pub fn new(max_party_size: usize, max_box_size: usize) -> Self {
         Storage {
-            party: Container::new(DEFAULT_MAX_PARTY_SIZE),
+            party: Container::new(max_party_size),
             boxes: vec![],
-            max_party_size: DEFAULT_MAX_PARTY_SIZE,
-            max_box_size: DEFAULT_MAX_BOX_SIZE,
+            max_party_size,
+            max_box_size,
             pokemon_locations: HashMap::new(),
+            next_pokemon_id: first_pokemon_id(),
+            bag: Bag::default(),
+            pokedex: Pokedex::default(),
+            nuzlocke: None,
+            day_care: DayCare::default(),
+            eggs: vec![],
+            next_egg_id: first_pokemon_id(),
+            wild: None,
+            escrow: BTreeSet::new(),
+            rules: Rules::default(),
+            auto_create_boxes: true,
+            clock: system_clock(),
         }
     }
-}
 
-impl Storage {
+    /// Snapshots written before pokemon had their own ids keyed everything by species. Give each of
+    /// those pokemon an id and re-key storage around it.
+    pub fn assign_missing_ids(&mut self) {
+        let mut next_pokemon_id = self.next_pokemon_id;
+        let mut pokemon_locations = HashMap::new();
+
+        let containers = std::iter::once((ContainerLocation::Party, &mut self.party))
+            .chain(
+                self.boxes
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, bx)| (ContainerLocation::Box(i), bx)),
+            )
+            .chain(std::iter::once((
+                ContainerLocation::DayCare,
+                &mut self.day_care.pokemon,
+            )));
+        for (location, container) in containers {
+            container.pokemon = container
+                .pokemon
+                .drain()
+                .map(|(_, mut pokemon)| {
+                    if pokemon.instance.id == 0 {
+                        pokemon.instance.id = next_pokemon_id;
+                        next_pokemon_id += 1;
+                    }
+                    pokemon_locations.insert(pokemon.id(), location);
+
+                    (pokemon.id(), pokemon)
+                })
+                .collect();
+        }
+
+        self.next_pokemon_id = next_pokemon_id;
+        self.pokemon_locations = pokemon_locations;
+    }
+
+    /// Snapshots written before the pokedex was tracked have it empty. Register the species of
+    /// everything still in storage so at least those count as caught.
+    pub fn register_stored_species(&mut self) {
+        let species_ids = std::iter::once(&self.party)
+            .chain(self.boxes.iter())
+            .chain(std::iter::once(&self.day_care.pokemon))
+            .flat_map(|container| container.pokemon.values())
+            .map(|pokemon| pokemon.pokeAPI_id)
+            .collect::<Vec<u32>>();
+
+        for species_id in species_ids {
+            self.pokedex.register_caught(species_id);
+        }
+    }
+
+    pub fn max_party_size(&self) -> usize {
+        self.max_party_size
+    }
+
+    pub fn max_box_size(&self) -> usize {
+        self.max_box_size
+    }
+
+    /// Changes how many pokemon the party and each box hold, e.g. to the configured sizes after
+    /// restoring a snapshot. A container already over its new size keeps what's in it but takes
+    /// nothing more. The graveyard stays unlimited.
+    pub fn set_limits(&mut self, max_party_size: usize, max_box_size: usize) {
+        let graveyard = self.nuzlocke.as_ref().map(|run| run.graveyard);
+
+        self.max_party_size = max_party_size;
+        self.max_box_size = max_box_size;
+        self.party.max_size = max_party_size;
+        for (i, container) in self.boxes.iter_mut().enumerate() {
+            if graveyard != Some(i) {
+                container.max_size = max_box_size;
+            }
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    pub fn set_auto_create_boxes(&mut self, enabled: bool) {
+        self.auto_create_boxes = enabled;
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn in_party(&self, pokemon_id: u32) -> bool {
+        self.pokemon_locations.get(&pokemon_id) == Some(&ContainerLocation::Party)
+    }
+
+    fn party_members(&self) -> Vec<Member<'_>> {
+        let mut members = self
+            .party
+            .pokemon
+            .values()
+            .map(Member::of)
+            .collect::<Vec<Member>>();
+        members.sort_by_key(|member| member.id);
+
+        members
+    }
+
+    /// Checks the party as it would be with `changed` in it, either in place of the member with the
+    /// same id or added to the end. Only violations the change introduces are reported, so
+    /// tightening the rules doesn't lock up a party that already breaks them. Violations are
+    /// counted, so a party with two of a species under the species clause can't take a third.
+    fn check_party(&self, changed: Member) -> Result<(), StorageError> {
+        let current = self.party_members();
+        let mut before = self.rules.check(&current);
+
+        let mut proposed = current;
+        match proposed.iter_mut().find(|member| member.id == changed.id) {
+            Some(member) => *member = changed,
+            None => proposed.push(changed),
+        }
+
+        let introduced = self
+            .rules
+            .check(&proposed)
+            .into_iter()
+            .filter(
+                |violation| match before.iter().position(|existing| existing == violation) {
+                    Some(index) => {
+                        before.swap_remove(index);
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .collect::<Vec<Violation>>();
+        if introduced.is_empty() {
+            Ok(())
+        } else {
+            Err(StorageError::IllegalParty(introduced))
+        }
+    }
+
+    /// Checks a proposed party of stored pokemon against the rules without changing anything,
+    /// returning everything that would be wrong with it.
+    pub fn validate_party(&self, pokemon_ids: &[u32]) -> Result<Vec<Violation>, StorageError> {
+        let members = pokemon_ids
+            .iter()
+            .map(|id| self.get_pokemon(*id).map(Member::of))
+            .collect::<Result<Vec<Member>, StorageError>>()?;
+
+        Ok(self.rules.check(&members))
+    }
+
     pub fn add_box(&mut self) -> Result<usize, ()> {
         self.boxes.push(Container::new(self.max_box_size));
 
@@ -63,91 +372,876 @@ impl Storage {
         Ok(self.party.get_pokemon())
     }
 
+    /// Every pokemon in every box.
+    pub fn boxed_pokemon(&self) -> Vec<&Pokemon> {
+        self.boxes
+            .iter()
+            .flat_map(|container| container.get_pokemon())
+            .collect()
+    }
+
+    /// Where `destination` is, as long as pokemon can be put there. `Auto` isn't anywhere until
+    /// `auto_destination` has picked a real destination for it, so it's refused like a missing box.
+    fn resolve(&self, destination: &StorageDestination) -> Result<ContainerLocation, StorageError> {
+        let location = match destination {
+            StorageDestination::Party => ContainerLocation::Party,
+            StorageDestination::Box(i) if *i < self.boxes.len() => ContainerLocation::Box(*i),
+            StorageDestination::Box(_) | StorageDestination::Auto => {
+                return Err(StorageError::BoxDoesNotExist)
+            }
+            StorageDestination::DayCare(_) => ContainerLocation::DayCare,
+        };
+        if self.is_graveyard(location) {
+            return Err(NuzlockeError::InGraveyard.into());
+        }
+
+        Ok(location)
+    }
+
+    /// The party if it has room and `allowed_in_party`, otherwise the first box with space.
+    fn find_room(&self, allowed_in_party: bool) -> Option<StorageDestination> {
+        if allowed_in_party && self.party.has_space() {
+            return Some(StorageDestination::Party);
+        }
+
+        (0..self.boxes.len())
+            .find(|i| !self.is_graveyard(ContainerLocation::Box(*i)) && self.boxes[*i].has_space())
+            .map(StorageDestination::Box)
+    }
+
+    /// Picks where `Auto` puts a pokemon, as a game would: see `find_room`. When every box is full
+    /// a new one is added, unless that's been turned off.
+    fn auto_destination(
+        &mut self,
+        allowed_in_party: bool,
+    ) -> Result<StorageDestination, StorageError> {
+        if let Some(destination) = self.find_room(allowed_in_party) {
+            return Ok(destination);
+        }
+        if !self.auto_create_boxes {
+            return Err(StorageError::ContainerIsFull);
+        }
+
+        self.boxes.push(Container::new(self.max_box_size));
+
+        Ok(StorageDestination::Box(self.boxes.len() - 1))
+    }
+
+    pub fn location(&self, pokemon_id: u32) -> Result<Location, StorageError> {
+        self.pokemon_locations
+            .get(&pokemon_id)
+            .map(|location| Location::from(*location))
+            .ok_or(StorageError::PokemonNotFound)
+    }
+
+    fn is_graveyard(&self, location: ContainerLocation) -> bool {
+        match &self.nuzlocke {
+            Some(run) => location == ContainerLocation::Box(run.graveyard),
+            None => false,
+        }
+    }
+
+    /// Fails for pokemon buried in a nuzlocke graveyard, which stay as they were when they fainted,
+    /// and for pokemon held for a trade.
+    fn check_changeable(&self, pokemon_id: u32) -> Result<(), StorageError> {
+        let location = self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+        if self.is_graveyard(*location) {
+            return Err(NuzlockeError::InGraveyard.into());
+        }
+        if self.escrow.contains(&pokemon_id) {
+            return Err(TradeError::InEscrow(pokemon_id).into());
+        }
+
+        Ok(())
+    }
+
+    fn container(&self, location: ContainerLocation) -> &Container {
+        match location {
+            ContainerLocation::Party => &self.party,
+            ContainerLocation::Box(i) => &self.boxes[i],
+            ContainerLocation::DayCare => &self.day_care.pokemon,
+        }
+    }
+
+    fn container_mut(&mut self, location: ContainerLocation) -> &mut Container {
+        match location {
+            ContainerLocation::Party => &mut self.party,
+            ContainerLocation::Box(i) => &mut self.boxes[i],
+            ContainerLocation::DayCare => &mut self.day_care.pokemon,
+        }
+    }
+
+    /// Puts a pokemon at a destination `resolve` has found room for.
+    fn place(
+        &mut self,
+        pokemon: Pokemon,
+        destination: StorageDestination,
+        location: ContainerLocation,
+    ) -> Result<(), StorageError> {
+        match destination {
+            StorageDestination::DayCare(breeding) => {
+                self.day_care.admit(pokemon, breeding, self.clock.now())
+            }
+            _ => self.container_mut(location).push(pokemon),
+        }
+    }
+
+    /// Takes a pokemon out of wherever it's stored. Pokemon leaving the day care have grown.
+    fn take(
+        &mut self,
+        pokemon_id: u32,
+        location: ContainerLocation,
+    ) -> Result<Pokemon, StorageError> {
+        match location {
+            ContainerLocation::DayCare => self.day_care.release(pokemon_id, self.clock.now()),
+            _ => self.container_mut(location).remove(pokemon_id),
+        }
+    }
+
+    pub fn get_pokemon(&self, pokemon_id: u32) -> Result<&Pokemon, StorageError> {
+        let location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+
+        self.container(location).get_pokemon_ref(pokemon_id)
+    }
+
+    pub fn get_pokemon_mut(&mut self, pokemon_id: u32) -> Result<&mut Pokemon, StorageError> {
+        let location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+
+        self.container_mut(location).get_pokemon_mut(pokemon_id)
+    }
+
+    /// Stores a newly caught pokemon, giving it an id in the process. During a nuzlocke run new
+    /// pokemon come from `record_encounter` instead.
     pub fn add_pokemon(
         &mut self,
         pokemon: Pokemon,
         destination: StorageDestination,
     ) -> Result<&Pokemon, StorageError> {
-        match destination {
-            StorageDestination::Party => {
-                let id = pokemon.id();
-                self.party.push(pokemon)?;
-                self.pokemon_locations.insert(id, ContainerLocation::Party);
-                Ok(self.party.get_pokemon_ref(id)?)
+        if self.nuzlocke.is_some() {
+            return Err(NuzlockeError::EncounterRequired.into());
+        }
+
+        self.store_pokemon(pokemon, destination)
+    }
+
+    fn store_pokemon(
+        &mut self,
+        mut pokemon: Pokemon,
+        mut destination: StorageDestination,
+    ) -> Result<&Pokemon, StorageError> {
+        if let StorageDestination::Auto = destination {
+            let allowed_in_party = self.check_party(Member::of(&pokemon)).is_ok();
+            destination = self.auto_destination(allowed_in_party)?;
+        }
+        let location = self.resolve(&destination)?;
+        let id = self.next_pokemon_id;
+        let species_id = pokemon.pokeAPI_id;
+        pokemon.instance.id = id;
+        if location == ContainerLocation::Party {
+            self.check_party(Member::of(&pokemon))?;
+        }
+        self.place(pokemon, destination, location)?;
+        self.next_pokemon_id += 1;
+        self.pokemon_locations.insert(id, location);
+        self.pokedex.register_caught(species_id);
+
+        self.container(location).get_pokemon_ref(id)
+    }
+
+    pub fn bag(&self) -> &Bag {
+        &self.bag
+    }
+
+    pub fn bag_mut(&mut self) -> &mut Bag {
+        &mut self.bag
+    }
+
+    pub fn pokedex(&self) -> &Pokedex {
+        &self.pokedex
+    }
+
+    /// Has a pokemon hold one `item_name` from the bag. Unless `swap` is set it mustn't already be
+    /// holding something; if it is, that item goes back in the bag.
+    pub fn give_item(
+        &mut self,
+        pokemon_id: u32,
+        item_name: &str,
+        swap: bool,
+    ) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let held = self.get_pokemon(pokemon_id)?.instance.held_item.clone();
+        if held.is_some() && !swap {
+            return Err(StorageError::AlreadyHoldingItem);
+        }
+        if self.bag.count(item_name) == 0 {
+            return Err(BagError::NotInBag(item_name.to_string()).into());
+        }
+        if let Some(held) = &held {
+            if held.name != item_name {
+                self.bag.check_room(held, 1)?;
             }
-            StorageDestination::Box(i) => match self.boxes.get_mut(i) {
-                Some(bx) => {
-                    let id = pokemon.id();
-                    bx.push(pokemon)?;
-                    self.pokemon_locations.insert(id, ContainerLocation::Box(i));
-                    Ok(bx.get_pokemon_ref(id)?)
-                }
-                None => Err(StorageError::BoxDoesNotExist),
-            },
         }
+        if self.in_party(pokemon_id) {
+            self.check_party(Member {
+                held_item: Some(item_name),
+                ..Member::of(self.get_pokemon(pokemon_id)?)
+            })?;
+        }
+
+        let item = self.bag.take(item_name)?;
+        if let Some(held) = held {
+            self.bag.add(&held, 1)?;
+        }
+
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        pokemon.instance.held_item = Some(item);
+
+        Ok(pokemon)
     }
 
+    /// Puts a pokemon's held item back in the bag.
+    pub fn take_item(&mut self, pokemon_id: u32) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let held = self
+            .get_pokemon(pokemon_id)?
+            .instance
+            .held_item
+            .clone()
+            .ok_or(StorageError::NotHoldingItem)?;
+
+        self.bag.add(&held, 1)?;
+
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        pokemon.instance.held_item = None;
+
+        Ok(pokemon)
+    }
+
+    /// Evolves a pokemon into `species` where it's stored, registering the new species as caught.
+    /// `evolutions` are those of `from_species_id`, and have to still pick `species` for the
+    /// pokemon as it is now. An item used is taken from the bag and a required held item is used up.
+    pub fn evolve_pokemon(
+        &mut self,
+        pokemon_id: u32,
+        from_species_id: u32,
+        evolutions: &[Evolution],
+        into: Option<&str>,
+        trigger: &Trigger,
+        species: Pokemon,
+    ) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let pokemon = self.get_pokemon(pokemon_id)?;
+        if pokemon.pokeAPI_id != from_species_id {
+            return Err(StorageError::Changed);
+        }
+        // Checked again now nothing else can get at the pokemon, which may have lost its held item
+        // or a level since the caller looked.
+        let (target, method) = evolution::choose(evolutions, pokemon, into, trigger)?;
+        if target.species_id != species.pokeAPI_id {
+            return Err(StorageError::Changed);
+        }
+        let consume_held_item = method.held_item.is_some();
+
+        if self.in_party(pokemon_id) {
+            self.check_party(Member {
+                species_id: species.pokeAPI_id,
+                species: species.species_name(),
+                legendary: species.is_legendary || species.is_mythical,
+                held_item: if consume_held_item {
+                    None
+                } else {
+                    Member::of(pokemon).held_item
+                },
+                ..Member::of(pokemon)
+            })?;
+        }
+        if let Some(item) = &method.item {
+            self.bag.take(item)?;
+        }
+        self.pokedex.register_caught(species.pokeAPI_id);
+
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        if consume_held_item {
+            pokemon.instance.held_item = None;
+        }
+        evolution::evolve(pokemon, species, trigger.name);
+
+        Ok(pokemon)
+    }
+
+    /// Changes a pokemon into another form of its species where it's stored, `form` being that
+    /// form's data. The pokemon has to still be that species, and not have evolved in the meantime.
+    pub fn change_form(
+        &mut self,
+        pokemon_id: u32,
+        form: Pokemon,
+    ) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        if pokemon.pokeAPI_id != form.pokeAPI_id {
+            return Err(StorageError::Changed);
+        }
+        forms::change(pokemon, form);
+
+        Ok(pokemon)
+    }
+
+    /// Teaches a pokemon the move in `slot`, forgetting `replace` to make room if given.
+    /// `learnset` is that of `species_id` in `form`, which the pokemon has to still be, and is
+    /// checked again against the pokemon's level now nothing else can get at it.
+    pub fn teach_move(
+        &mut self,
+        pokemon_id: u32,
+        species_id: u32,
+        form: Option<&str>,
+        learnset: &[LearnableMove],
+        slot: MoveSlot,
+        replace: Option<&str>,
+    ) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        if pokemon.pokeAPI_id != species_id || pokemon.instance.form.as_deref() != form {
+            return Err(StorageError::Changed);
+        }
+        moves::check_learnable(learnset, &slot.name, pokemon.instance.level)?;
+        moves::teach(&mut pokemon.instance.moves, slot, replace)?;
+
+        Ok(pokemon)
+    }
+
+    /// Makes a pokemon forget the move called `name`.
+    pub fn forget_move(&mut self, pokemon_id: u32, name: &str) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        moves::forget(&mut pokemon.instance.moves, name)?;
+
+        Ok(pokemon)
+    }
+
+    /// Replaces a pokemon's instance data with an edited copy, as long as the party stays legal.
+    pub fn edit_pokemon(
+        &mut self,
+        pokemon_id: u32,
+        instance: Instance,
+    ) -> Result<&Pokemon, StorageError> {
+        self.check_changeable(pokemon_id)?;
+        if self.in_party(pokemon_id) {
+            self.check_party(Member {
+                level: instance.level,
+                ..Member::of(self.get_pokemon(pokemon_id)?)
+            })?;
+        }
+
+        let pokemon = self.get_pokemon_mut(pokemon_id)?;
+        pokemon.instance = instance;
+
+        Ok(pokemon)
+    }
+
+    /// Removes a pokemon from storage for good. Anything it was holding goes back in the bag and is
+    /// returned alongside it.
+    pub fn release_pokemon(
+        &mut self,
+        pokemon_id: u32,
+    ) -> Result<(Pokemon, Option<Item>), StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+        if let Some(held) = &self.get_pokemon(pokemon_id)?.instance.held_item {
+            self.bag.check_room(held, 1)?;
+        }
+
+        let mut pokemon = self.take(pokemon_id, location)?;
+        self.pokemon_locations.remove(&pokemon_id);
+
+        let held = pokemon.instance.held_item.take();
+        if let Some(held) = &held {
+            self.bag.add(held, 1)?;
+        }
+
+        Ok((pokemon, held))
+    }
+
+    /// The party member a pokemon would make, at the level it'll be once out of the day care.
+    fn incoming_member(
+        &self,
+        pokemon_id: u32,
+        storage_location: ContainerLocation,
+    ) -> Result<Member<'_>, StorageError> {
+        let mut member = Member::of(self.get_pokemon(pokemon_id)?);
+        if storage_location == ContainerLocation::DayCare {
+            let (_, level) = self.day_care.growth(pokemon_id, self.clock.now())?;
+            member.level = member.level.max(level);
+        }
+
+        Ok(member)
+    }
+
+    /// Moves a stored pokemon. `Auto` brings a pokemon into the party if it can join, and
+    /// otherwise leaves it where it is unless it's leaving the day care.
     pub fn move_pokemon(
         &mut self,
         pokemon_id: u32,
+        mut destination: StorageDestination,
+    ) -> Result<&Pokemon, StorageError> {
+        let storage_location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+        if self.is_graveyard(storage_location) {
+            return Err(NuzlockeError::InGraveyard.into());
+        }
+        if self.escrow.contains(&pokemon_id) {
+            return Err(TradeError::InEscrow(pokemon_id).into());
+        }
+        if let StorageDestination::Auto = destination {
+            let member = self.incoming_member(pokemon_id, storage_location)?;
+            let joins_party = self.party.has_space() && self.check_party(member).is_ok();
+            destination = match storage_location {
+                ContainerLocation::Party => StorageDestination::Party,
+                ContainerLocation::Box(i) if !joins_party => StorageDestination::Box(i),
+                _ => self.auto_destination(joins_party)?,
+            };
+        }
+        let location = self.resolve(&destination)?;
+        if storage_location == location {
+            return self.container(location).get_pokemon_ref(pokemon_id);
+        }
+        if !self.container(location).has_space() {
+            return Err(StorageError::ContainerIsFull);
+        }
+        if location == ContainerLocation::Party {
+            self.check_party(self.incoming_member(pokemon_id, storage_location)?)?;
+        }
+
+        let pokemon = self.take(pokemon_id, storage_location)?;
+
+        self.place(pokemon, destination, location)?;
+        self.pokemon_locations.insert(pokemon_id, location);
+
+        self.container(location).get_pokemon_ref(pokemon_id)
+    }
+
+    /// Starts a nuzlocke run, adding the box its fainted pokemon will be moved to. The graveyard
+    /// has no size limit.
+    pub fn start_nuzlocke(&mut self, dupes_clause: bool) -> Result<Status<'_>, StorageError> {
+        if self.nuzlocke.is_some() {
+            return Err(NuzlockeError::AlreadyRunning.into());
+        }
+
+        self.boxes.push(Container::new(usize::MAX));
+        self.nuzlocke = Some(Run::new(self.boxes.len() - 1, dupes_clause));
+
+        self.nuzlocke_status()
+    }
+
+    /// Ends the run. Its graveyard stays behind as an ordinary box.
+    pub fn end_nuzlocke(&mut self) -> Result<Run, StorageError> {
+        Ok(self.nuzlocke.take().ok_or(NuzlockeError::NotRunning)?)
+    }
+
+    pub fn nuzlocke_status(&self) -> Result<Status<'_>, StorageError> {
+        let run = self.nuzlocke.as_ref().ok_or(NuzlockeError::NotRunning)?;
+
+        let caught = run.caught().collect::<Vec<u32>>();
+        let alive = caught
+            .iter()
+            .filter(|id| !run.fainted.contains(id) && self.pokemon_locations.contains_key(id))
+            .count();
+
+        Ok(Status {
+            started: run.started,
+            dupes_clause: run.dupes_clause,
+            graveyard_box: run.graveyard,
+            areas: run
+                .encounters
+                .iter()
+                .filter(|encounter| encounter.outcome != Outcome::Dupe)
+                .count(),
+            caught: caught.len(),
+            fainted: run.fainted.len(),
+            alive,
+            wiped: !caught.is_empty() && alive == 0,
+            encounters: &run.encounters,
+        })
+    }
+
+    /// Records the run's encounter in `area` with `pokemon`, storing it at `destination` if it was
+    /// caught. Every encounter counts as the species being seen.
+    pub fn record_encounter(
+        &mut self,
+        area: &str,
+        pokemon: Pokemon,
+        caught: bool,
         destination: StorageDestination,
+    ) -> Result<(Encounter, Option<&Pokemon>), StorageError> {
+        let run = self.nuzlocke.as_ref().ok_or(NuzlockeError::NotRunning)?;
+        let outcome = run.check_encounter(
+            area,
+            pokemon.pokeAPI_id,
+            caught,
+            pokemon.instance.nickname.as_deref(),
+        )?;
+
+        let mut encounter = Encounter {
+            area: area.to_string(),
+            species: pokemon.species_name().to_string(),
+            species_id: pokemon.pokeAPI_id,
+            outcome,
+            pokemon_id: None,
+            date: Utc::now().naive_utc().date(),
+        };
+        self.pokedex.register_seen(pokemon.pokeAPI_id);
+        if outcome == Outcome::Caught {
+            encounter.pokemon_id = Some(self.store_pokemon(pokemon, destination)?.id());
+        }
+
+        if let Some(run) = self.nuzlocke.as_mut() {
+            run.encounters.push(encounter.clone());
+        }
+
+        let pokemon = match encounter.pokemon_id {
+            Some(id) => Some(self.get_pokemon(id)?),
+            None => None,
+        };
+
+        Ok((encounter, pokemon))
+    }
+
+    /// Marks a pokemon as fainted during a nuzlocke run, burying it in the graveyard for good.
+    pub fn faint_pokemon(&mut self, pokemon_id: u32) -> Result<&Pokemon, StorageError> {
+        let graveyard = ContainerLocation::Box(
+            self.nuzlocke
+                .as_ref()
+                .ok_or(NuzlockeError::NotRunning)?
+                .graveyard,
+        );
+        let location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+        if location == graveyard {
+            return Err(NuzlockeError::InGraveyard.into());
+        }
+        if self.escrow.contains(&pokemon_id) {
+            return Err(TradeError::InEscrow(pokemon_id).into());
+        }
+
+        let pokemon = self.take(pokemon_id, location)?;
+        self.container_mut(graveyard).push(pokemon)?;
+        self.pokemon_locations.insert(pokemon_id, graveyard);
+        if let Some(run) = self.nuzlocke.as_mut() {
+            run.fainted.push(pokemon_id);
+        }
+
+        self.container(graveyard).get_pokemon_ref(pokemon_id)
+    }
+
+    /// Fails for pokemon that can't be traded: ones that aren't changeable, and ones at the day
+    /// care.
+    pub fn check_tradeable(&self, pokemon_id: u32) -> Result<(), StorageError> {
+        self.check_changeable(pokemon_id)?;
+        if self.pokemon_locations.get(&pokemon_id) == Some(&ContainerLocation::DayCare) {
+            return Err(TradeError::AtDayCare(pokemon_id).into());
+        }
+
+        Ok(())
+    }
+
+    /// Holds pokemon for a trade. They have to be tradeable, and none of them can be held already.
+    pub fn escrow(&mut self, pokemon_ids: &[u32]) -> Result<(), StorageError> {
+        for pokemon_id in pokemon_ids {
+            self.check_tradeable(*pokemon_id)?;
+        }
+        self.escrow.extend(pokemon_ids);
+
+        Ok(())
+    }
+
+    /// Lets go of pokemon held for a trade that didn't happen.
+    pub fn release_escrow(&mut self, pokemon_ids: &[u32]) {
+        for pokemon_id in pokemon_ids {
+            self.escrow.remove(pokemon_id);
+        }
+    }
+
+    /// Takes a pokemon out of storage altogether, to hand it to someone else.
+    fn hand_over(&mut self, pokemon_id: u32) -> Result<Pokemon, StorageError> {
+        let location = *self
+            .pokemon_locations
+            .get(&pokemon_id)
+            .ok_or(StorageError::PokemonNotFound)?;
+        let pokemon = self.take(pokemon_id, location)?;
+        self.pokemon_locations.remove(&pokemon_id);
+
+        Ok(pokemon)
+    }
+
+    /// Takes pokemon held for a trade out of storage to hand them over.
+    pub fn trade_away(&mut self, pokemon_ids: &[u32]) -> Result<Vec<Pokemon>, StorageError> {
+        let mut traded = vec![];
+        for pokemon_id in pokemon_ids {
+            if !self.escrow.remove(pokemon_id) {
+                return Err(StorageError::Changed);
+            }
+            traded.push(self.hand_over(*pokemon_id)?);
+        }
+
+        Ok(traded)
+    }
+
+    /// Takes a tradeable pokemon out of storage to fulfil a trade station listing with.
+    pub fn trade_for_listing(&mut self, pokemon_id: u32) -> Result<Pokemon, StorageError> {
+        self.check_tradeable(pokemon_id)?;
+
+        self.hand_over(pokemon_id)
+    }
+
+    /// Takes a boxed pokemon out of storage to list it on the trade station, returning it and the
+    /// box it was in.
+    pub fn deposit(&mut self, pokemon_id: u32) -> Result<(Pokemon, usize), StorageError> {
+        self.check_changeable(pokemon_id)?;
+        let box_ = match self.pokemon_locations.get(&pokemon_id) {
+            Some(ContainerLocation::Box(i)) => *i,
+            _ => return Err(StationError::NotBoxed(pokemon_id).into()),
+        };
+
+        Ok((self.hand_over(pokemon_id)?, box_))
+    }
+
+    /// Where a pokemon coming back from the trade station goes: `preferred` if it has room,
+    /// otherwise the first box that does. It never goes in the party.
+    pub fn box_destination(
+        &mut self,
+        preferred: usize,
+    ) -> Result<StorageDestination, StorageError> {
+        let location = ContainerLocation::Box(preferred);
+        if preferred < self.boxes.len()
+            && !self.is_graveyard(location)
+            && self.boxes[preferred].has_space()
+        {
+            return Ok(StorageDestination::Box(preferred));
+        }
+
+        self.auto_destination(false)
+    }
+
+    /// Puts a withdrawn deposit back, under the id it had before. Its species is already
+    /// registered as caught.
+    pub fn withdraw_deposit(
+        &mut self,
+        pokemon: Pokemon,
+        box_: usize,
     ) -> Result<&Pokemon, StorageError> {
-        match destination {
-            StorageDestination::Party => {
-                if !self.party.has_space() {
-                    Err(StorageError::ContainerIsFull)
-                } else {
-                    let storage_location = self
-                        .pokemon_locations
-                        .remove(&pokemon_id)
-                        .ok_or(StorageError::PokemonNotFound)?;
-                    let pokemon = match storage_location {
-                        ContainerLocation::Party => self.party.remove(pokemon_id),
-                        ContainerLocation::Box(i) => {
-                            self.boxes.get_mut(i).unwrap().remove(pokemon_id)
-                        }
-                    }?;
-
-                    self.party.push(pokemon)?;
-
-                    self.pokemon_locations
-                        .insert(pokemon_id, ContainerLocation::Party);
-
-                    Ok(self.party.get_pokemon_ref(pokemon_id)?)
-                }
+        let destination = self.box_destination(box_)?;
+        let location = self.resolve(&destination)?;
+        let id = pokemon.id();
+        self.place(pokemon, destination, location)?;
+        self.pokemon_locations.insert(id, location);
+
+        self.container(location).get_pokemon_ref(id)
+    }
+
+    /// Stores a pokemon traded from `from`, giving it an id here and registering its species as
+    /// caught. Whoever first caught it stays its original trainer. Trainers on a nuzlocke run only
+    /// get pokemon from encounters, see `in_nuzlocke`.
+    pub fn receive_traded(
+        &mut self,
+        mut pokemon: Pokemon,
+        from: &str,
+        destination: StorageDestination,
+    ) -> Result<&Pokemon, StorageError> {
+        if pokemon.instance.original_trainer.is_none() {
+            pokemon.instance.original_trainer = Some(from.to_string());
+        }
+
+        self.store_pokemon(pokemon, destination)
+    }
+
+    pub fn in_nuzlocke(&self) -> bool {
+        self.nuzlocke.is_some()
+    }
+
+    /// An empty storage for a new trainer, with the same sizes, rules and settings as this one.
+    pub fn empty_like(&self) -> Storage {
+        Storage {
+            rules: self.rules.clone(),
+            auto_create_boxes: self.auto_create_boxes,
+            clock: self.clock.clone(),
+            ..Storage::new(self.max_party_size, self.max_box_size)
+        }
+    }
+
+    pub fn day_care(&self) -> day_care::Status<'_> {
+        self.day_care.status(self.clock.now())
+    }
+
+    /// Collects the egg the day care's pair has left.
+    pub fn collect_egg(&mut self) -> Result<&Egg, StorageError> {
+        let egg = self
+            .day_care
+            .collect_egg(self.next_egg_id, self.clock.now())?;
+        self.next_egg_id += 1;
+        self.eggs.push(egg);
+
+        Ok(self.eggs.last().unwrap())
+    }
+
+    pub fn eggs(&self) -> &[Egg] {
+        &self.eggs
+    }
+
+    pub fn get_egg(&self, egg_id: u32) -> Result<&Egg, StorageError> {
+        Ok(self
+            .eggs
+            .iter()
+            .find(|egg| egg.id == egg_id)
+            .ok_or(DayCareError::EggNotFound)?)
+    }
+
+    /// Counts `steps` towards hatching every egg.
+    pub fn walk(&mut self, steps: u32) -> &[Egg] {
+        for egg in self.eggs.iter_mut() {
+            egg.walk(steps);
+        }
+
+        &self.eggs
+    }
+
+    /// Replaces an egg that's done its steps with `pokemon`, which hatched from it. During a
+    /// nuzlocke run a hatched pokemon isn't an encounter: it came from pokemon already caught.
+    pub fn hatch_egg(
+        &mut self,
+        egg_id: u32,
+        pokemon: Pokemon,
+        destination: StorageDestination,
+    ) -> Result<&Pokemon, StorageError> {
+        let steps = self.get_egg(egg_id)?.steps;
+        if steps > 0 {
+            return Err(DayCareError::EggNotReady(steps).into());
+        }
+
+        let id = self.store_pokemon(pokemon, destination)?.id();
+        self.eggs.retain(|egg| egg.id != egg_id);
+
+        self.get_pokemon(id)
+    }
+
+    /// Starts an encounter with a wild pokemon, leaving any earlier one behind. Its species counts
+    /// as seen. During a nuzlocke run it's the area's encounter, missed unless it's caught, so the
+    /// area has to still be unused.
+    pub fn encounter_wild(
+        &mut self,
+        encounter: WildEncounter,
+    ) -> Result<&WildEncounter, StorageError> {
+        let pokemon = &encounter.pokemon;
+        if let Some(run) = self.nuzlocke.as_mut() {
+            let outcome = run.check_encounter(&encounter.area, pokemon.pokeAPI_id, false, None)?;
+            run.encounters.push(Encounter {
+                area: encounter.area.clone(),
+                species: pokemon.species_name().to_string(),
+                species_id: pokemon.pokeAPI_id,
+                outcome,
+                pokemon_id: None,
+                date: Utc::now().naive_utc().date(),
+            });
+        }
+        self.pokedex.register_seen(pokemon.pokeAPI_id);
+
+        Ok(self.wild.insert(encounter))
+    }
+
+    pub fn wild_encounter(&self) -> Result<&WildEncounter, StorageError> {
+        Ok(self.wild.as_ref().ok_or(WildError::NoEncounter)?)
+    }
+
+    /// Throws `ball` from the bag at the wild pokemon. If it's caught it becomes `instance` and is
+    /// stored wherever there's room, as with `Auto`; during a nuzlocke run that turns the area's
+    /// encounter into a catch. A pokemon that breaks free can have another ball thrown at it.
+    pub fn catch_wild<R: Rng>(
+        &mut self,
+        ball: &str,
+        instance: Instance,
+        rng: &mut R,
+    ) -> Result<(Throw, Option<&Pokemon>), StorageError> {
+        let wild = self.wild.as_ref().ok_or(WildError::NoEncounter)?;
+        let category = self
+            .bag
+            .category(ball)
+            .ok_or_else(|| BagError::NotInBag(ball.to_string()))?;
+        if !wild::is_ball(category) {
+            return Err(WildError::NotABall(ball.to_string()).into());
+        }
+        // Where the run recorded this pokemon appearing, and whether catching it would be a dupe.
+        let pending = self.nuzlocke.as_ref().and_then(|run| {
+            let index = run.pending_encounter(&wild.area, wild.pokemon.pokeAPI_id)?;
+            Some((index, run.encounters[index].outcome == Outcome::Dupe))
+        });
+        match (&self.nuzlocke, pending) {
+            (Some(_), Some((_, false))) if instance.nickname.is_none() => {
+                return Err(NuzlockeError::NicknameRequired.into());
             }
-            StorageDestination::Box(i) => match self.boxes.get_mut(i) {
-                Some(bx) => {
-                    if !bx.has_space() {
-                        Err(StorageError::ContainerIsFull)
-                    } else {
-                        let storage_location = self
-                            .pokemon_locations
-                            .remove(&pokemon_id)
-                            .ok_or(StorageError::PokemonNotFound)?;
-                        let pokemon = match storage_location {
-                            ContainerLocation::Party => self.party.remove(pokemon_id),
-                            ContainerLocation::Box(i) => {
-                                self.boxes.get_mut(i).unwrap().remove(pokemon_id)
-                            }
-                        }?;
-
-                        let bx = self.boxes.get_mut(i).unwrap();
-                        bx.push(pokemon)?;
-
-                        self.pokemon_locations
-                            .insert(pokemon_id, ContainerLocation::Box(i));
-
-                        Ok(self.party.get_pokemon_ref(pokemon_id)?)
-                    }
-                }
-                None => Err(StorageError::BoxDoesNotExist),
-            },
+            // It appeared before the run started, so it hasn't been recorded.
+            (Some(run), None) => {
+                run.check_encounter(
+                    &wild.area,
+                    wild.pokemon.pokeAPI_id,
+                    true,
+                    instance.nickname.as_deref(),
+                )?;
+            }
+            _ => {}
         }
+        let allowed_in_party = self.check_party(Member::of(&wild.pokemon)).is_ok();
+        if self.find_room(allowed_in_party).is_none() && !self.auto_create_boxes {
+            return Err(StorageError::ContainerIsFull);
+        }
+        let capture_rate = wild.pokemon.capture_rate;
+
+        self.bag.take(ball)?;
+        let throw = wild::throw(capture_rate, ball, rng);
+        if !throw.caught {
+            return Ok((throw, None));
+        }
+
+        let mut wild = self.wild.take().unwrap();
+        wild.pokemon.instance = instance;
+        let pokemon = match pending {
+            Some((_, true)) => None,
+            Some((index, false)) => {
+                let id = self
+                    .store_pokemon(wild.pokemon, StorageDestination::Auto)?
+                    .id();
+                if let Some(run) = self.nuzlocke.as_mut() {
+                    run.encounters[index].outcome = Outcome::Caught;
+                    run.encounters[index].pokemon_id = Some(id);
+                }
+
+                Some(self.get_pokemon(id)?)
+            }
+            None if self.nuzlocke.is_some() => {
+                self.record_encounter(&wild.area, wild.pokemon, true, StorageDestination::Auto)?
+                    .1
+            }
+            None => Some(self.add_pokemon(wild.pokemon, StorageDestination::Auto)?),
+        };
+
+        Ok((throw, pokemon))
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Container {
     pokemon: HashMap<u32, Pokemon>,
     max_size: usize,
@@ -187,4 +1281,273 @@ impl Container {
     pub fn get_pokemon_ref(&self, id: u32) -> Result<&Pokemon, StorageError> {
         self.pokemon.get(&id).ok_or(StorageError::PokemonNotFound)
     }
+
+    pub fn get_pokemon_mut(&mut self, id: u32) -> Result<&mut Pokemon, StorageError> {
+        self.pokemon
+            .get_mut(&id)
+            .ok_or(StorageError::PokemonNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pokemon(species_id: u32) -> Pokemon {
+        serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": species_id,
+            "name": "kabuto",
+            "height": 5,
+            "weight": 115,
+            "base_happiness": 50
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn auto_overflows_into_boxes_as_they_fill() {
+        let mut storage = Storage::new(1, 1);
+        let mut locations = vec![];
+        for _ in 0..3 {
+            let id = storage
+                .add_pokemon(pokemon(140), StorageDestination::Auto)
+                .unwrap()
+                .id();
+            locations.push(storage.location(id).unwrap());
+        }
+        assert_eq!(
+            locations,
+            vec![Location::Party, Location::Box(0), Location::Box(1)]
+        );
+
+        // Already boxed and the party's full, so it stays put.
+        let boxed = storage.get_box(0).unwrap()[0].id();
+        storage
+            .move_pokemon(boxed, StorageDestination::Auto)
+            .unwrap();
+        assert_eq!(storage.location(boxed).unwrap(), Location::Box(0));
+
+        storage.set_auto_create_boxes(false);
+        assert!(matches!(
+            storage.add_pokemon(pokemon(140), StorageDestination::Auto),
+            Err(StorageError::ContainerIsFull)
+        ));
+        assert!(matches!(
+            storage.resolve(&StorageDestination::Auto),
+            Err(StorageError::BoxDoesNotExist)
+        ));
+    }
+
+    #[test]
+    fn a_party_already_breaking_a_rule_cant_break_it_more() {
+        let mut storage = Storage::new(6, 1);
+        for _ in 0..2 {
+            storage
+                .add_pokemon(pokemon(140), StorageDestination::Party)
+                .unwrap();
+        }
+        storage.set_rules(Rules {
+            species_clause: true,
+            ..Rules::default()
+        });
+
+        // The existing pair is tolerated, but not a third.
+        assert!(matches!(
+            storage.add_pokemon(pokemon(140), StorageDestination::Party),
+            Err(StorageError::IllegalParty(violations))
+                if violations == vec![Violation::DuplicateSpecies("kabuto".to_string())]
+        ));
+        storage
+            .add_pokemon(pokemon(138), StorageDestination::Party)
+            .unwrap();
+    }
+
+    #[test]
+    fn a_nuzlocke_wild_encounter_uses_up_its_area() {
+        let mut storage = Storage::new(6, 1);
+        storage
+            .bag_mut()
+            .add(
+                &Item {
+                    name: "master-ball".to_string(),
+                    category: "standard-balls".to_string(),
+                },
+                1,
+            )
+            .unwrap();
+        storage.start_nuzlocke(false).unwrap();
+        let wild = |species_id| WildEncounter {
+            area: "mt-moon-b1f".to_string(),
+            version: "red".to_string(),
+            method: "walk".to_string(),
+            pokemon: pokemon(species_id),
+        };
+
+        // Meeting it is the area's encounter, even before a ball is thrown.
+        storage.encounter_wild(wild(140)).unwrap();
+        let outcomes = |storage: &Storage| {
+            storage
+                .nuzlocke_status()
+                .unwrap()
+                .encounters
+                .iter()
+                .map(|encounter| (encounter.outcome, encounter.pokemon_id))
+                .collect::<Vec<(Outcome, Option<u32>)>>()
+        };
+        assert_eq!(outcomes(&storage), vec![(Outcome::Missed, None)]);
+        assert!(matches!(
+            storage.encounter_wild(wild(138)),
+            Err(StorageError::Nuzlocke(NuzlockeError::AreaUsed { .. }))
+        ));
+
+        let nicknamed = Instance {
+            nickname: Some("Shell".to_string()),
+            ..Instance::default()
+        };
+        let (throw, pokemon) = storage
+            .catch_wild("master-ball", nicknamed, &mut rand::thread_rng())
+            .unwrap();
+        assert!(throw.caught);
+        let id = pokemon.unwrap().id();
+        assert_eq!(outcomes(&storage), vec![(Outcome::Caught, Some(id))]);
+    }
+
+    #[test]
+    fn restored_storage_takes_the_configured_sizes() {
+        let mut storage = Storage::new(1, 1);
+        storage
+            .add_pokemon(pokemon(140), StorageDestination::Party)
+            .unwrap();
+        storage
+            .add_pokemon(pokemon(140), StorageDestination::Auto)
+            .unwrap();
+
+        storage.set_limits(2, 2);
+        assert_eq!((storage.max_party_size(), storage.max_box_size()), (2, 2));
+        let locations = (0..2)
+            .map(|_| {
+                let id = storage
+                    .add_pokemon(pokemon(140), StorageDestination::Auto)
+                    .unwrap()
+                    .id();
+                storage.location(id).unwrap()
+            })
+            .collect::<Vec<Location>>();
+        assert_eq!(locations, vec![Location::Party, Location::Box(0)]);
+
+        // Shrinking leaves what's there but stops anything else going in.
+        storage.set_limits(1, 2);
+        assert!(matches!(
+            storage.add_pokemon(pokemon(140), StorageDestination::Party),
+            Err(StorageError::ContainerIsFull)
+        ));
+        assert_eq!(storage.get_party().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn a_move_is_only_taught_to_the_pokemon_it_was_checked_for() {
+        let mut storage = Storage::new(6, 1);
+        let id = storage
+            .add_pokemon(pokemon(140), StorageDestination::Party)
+            .unwrap()
+            .id();
+        let learnset = vec![LearnableMove {
+            name: "slash".to_string(),
+            method: "level-up".to_string(),
+            level: 40,
+        }];
+        let slash = || MoveSlot {
+            name: "slash".to_string(),
+            pp: 20,
+            max_pp: 20,
+        };
+
+        // Edited back down a level while the move was being looked up.
+        storage.get_pokemon_mut(id).unwrap().instance.level = 39;
+        assert!(matches!(
+            storage.teach_move(id, 140, None, &learnset, slash(), None),
+            Err(StorageError::Move(MoveError::LevelTooLow { .. }))
+        ));
+
+        storage.get_pokemon_mut(id).unwrap().instance.level = 40;
+        assert!(matches!(
+            storage.teach_move(id, 141, None, &learnset, slash(), None),
+            Err(StorageError::Changed)
+        ));
+        assert!(matches!(
+            storage.teach_move(id, 140, Some("kabuto-fossil"), &learnset, slash(), None),
+            Err(StorageError::Changed)
+        ));
+
+        let taught = storage
+            .teach_move(id, 140, None, &learnset, slash(), None)
+            .unwrap();
+        assert_eq!(taught.instance.moves, vec![slash()]);
+    }
+
+    #[test]
+    fn fainted_pokemon_stay_as_they_were() {
+        let mut storage = Storage::new(6, 1);
+        let berry = Item {
+            name: "oran-berry".to_string(),
+            category: "medicine".to_string(),
+        };
+        storage.bag_mut().add(&berry, 2).unwrap();
+        let id = storage
+            .add_pokemon(pokemon(140), StorageDestination::Party)
+            .unwrap()
+            .id();
+        storage.give_item(id, "oran-berry", false).unwrap();
+        storage.start_nuzlocke(true).unwrap();
+        storage.faint_pokemon(id).unwrap();
+
+        let buried = |result: Result<&Pokemon, StorageError>| {
+            matches!(
+                result,
+                Err(StorageError::Nuzlocke(NuzlockeError::InGraveyard))
+            )
+        };
+        assert!(buried(storage.give_item(id, "oran-berry", true)));
+        assert!(buried(storage.take_item(id)));
+        let level = storage.get_pokemon(id).unwrap().instance.level;
+        let mut instance = storage.get_pokemon(id).unwrap().instance.clone();
+        instance.level = 100;
+        assert!(buried(storage.edit_pokemon(id, instance)));
+        assert!(matches!(
+            storage.release_pokemon(id),
+            Err(StorageError::Nuzlocke(NuzlockeError::InGraveyard))
+        ));
+
+        let pokemon = storage.get_pokemon(id).unwrap();
+        assert_eq!(pokemon.instance.held_item, Some(berry));
+        assert_eq!(pokemon.instance.level, level);
+        assert_eq!(storage.bag().count("oran-berry"), 1);
+    }
+
+    #[test]
+    fn eggs_hatch_during_a_nuzlocke_run() {
+        let mut storage = Storage::new(6, 1);
+        storage.eggs.push(Egg {
+            id: 1,
+            species_id: 140,
+            species: "kabuto".to_string(),
+            steps: 10,
+            laid: Utc::now().naive_utc().date(),
+        });
+        storage.start_nuzlocke(true).unwrap();
+
+        assert!(matches!(
+            storage.hatch_egg(1, pokemon(140), StorageDestination::Auto),
+            Err(StorageError::DayCare(DayCareError::EggNotReady(10)))
+        ));
+        storage.walk(10);
+        let id = storage
+            .hatch_egg(1, pokemon(140), StorageDestination::Auto)
+            .unwrap()
+            .id();
+
+        assert_eq!(storage.location(id).unwrap(), Location::Party);
+        assert!(storage.eggs().is_empty());
+        assert!(storage.nuzlocke_status().unwrap().encounters.is_empty());
+    }
 }
@@ -0,0 +1,342 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::evolution::Evolution;
+use crate::pokemon::Pokemon;
+
+/// How long an offer stays open when it doesn't say, a day.
+pub const DEFAULT_OFFER_SECS: i64 = 24 * 60 * 60;
+/// The longest an offer can stay open, a week.
+pub const MAX_OFFER_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, PartialEq)]
+pub enum TradeError {
+    /// There's no open trade with that id involving the trainer.
+    NotFound,
+    WithSelf,
+    NoPokemon,
+    Repeated(u32),
+    AtDayCare(u32),
+    InEscrow(u32),
+    UnknownTrainer(String),
+    NuzlockeRunning(String),
+    Expiry(i64),
+}
+
+impl fmt::Display for TradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradeError::NotFound => write!(f, "there's no open trade with that id"),
+            TradeError::WithSelf => write!(f, "trainers can't trade with themselves"),
+            TradeError::NoPokemon => write!(f, "each side of a trade needs at least one pokemon"),
+            TradeError::Repeated(id) => write!(f, "pokemon {} is listed more than once", id),
+            TradeError::AtDayCare(id) => write!(
+                f,
+                "pokemon {} is at the day care, withdraw it before trading it",
+                id
+            ),
+            TradeError::InEscrow(id) => write!(
+                f,
+                "pokemon {} is held for a trade until it's confirmed, cancelled or expires",
+                id
+            ),
+            TradeError::UnknownTrainer(name) => write!(f, "there's no trainer called {}", name),
+            TradeError::NuzlockeRunning(trainer) => write!(
+                f,
+                "{} is on a nuzlocke run, where pokemon only come from encounters",
+                trainer
+            ),
+            TradeError::Expiry(secs) => write!(
+                f,
+                "offers can stay open for between 1 and {} seconds, not {}",
+                MAX_OFFER_SECS, secs
+            ),
+        }
+    }
+}
+
+/// Where a trainer wants the pokemon they receive: a box, or wherever there's room as with
+/// `StorageDestination::Auto`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Confirmation {
+    #[serde(rename = "box")]
+    pub box_: Option<usize>,
+}
+
+/// An open trade between two trainers. The offered pokemon are held in escrow from the moment it's
+/// made, and the requested ones once `to` confirms, so neither side can change them before the
+/// swap.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Offer {
+    pub id: u32,
+    pub from: String,
+    pub to: String,
+    /// Ids in `from`'s storage.
+    pub offered: Vec<u32>,
+    /// Ids in `to`'s storage.
+    pub requested: Vec<u32>,
+    pub created: NaiveDateTime,
+    pub expires: NaiveDateTime,
+    pub from_confirmed: Option<Confirmation>,
+    pub to_confirmed: Option<Confirmation>,
+}
+
+impl Offer {
+    pub fn involves(&self, trainer: &str) -> bool {
+        self.from == trainer || self.to == trainer
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Completed,
+    Cancelled,
+    Expired,
+}
+
+/// A pokemon that changed hands, under its id in the storage of the trainer who received it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Received {
+    pub trainer: String,
+    pub pokemon_id: u32,
+    pub species: String,
+    /// What trading it made it evolve into.
+    pub evolved_into: Option<String>,
+}
+
+/// A trade that's no longer open, as kept in the history.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Closed {
+    #[serde(flatten)]
+    pub offer: Offer,
+    pub outcome: Outcome,
+    pub closed: NaiveDateTime,
+    /// Which trainer cancelled it, for `Cancelled`.
+    pub cancelled_by: Option<String>,
+    /// For `Completed`, what each trainer received.
+    pub received: Vec<Received>,
+}
+
+/// A trade as reported to its trainers, whether it's still open or not.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Trade<'a> {
+    Open(&'a Offer),
+    Closed(&'a Closed),
+}
+
+/// Checks the pokemon listed for one side of an offer, before anything is held for it.
+pub fn check_listed(pokemon_ids: &[u32]) -> Result<(), TradeError> {
+    if pokemon_ids.is_empty() {
+        return Err(TradeError::NoPokemon);
+    }
+    for (i, id) in pokemon_ids.iter().enumerate() {
+        if pokemon_ids[..i].contains(id) {
+            return Err(TradeError::Repeated(*id));
+        }
+    }
+
+    Ok(())
+}
+
+/// When an offer made at `now` expires, `secs` later or after the default.
+pub fn expiry(now: NaiveDateTime, secs: Option<i64>) -> Result<NaiveDateTime, TradeError> {
+    match secs.unwrap_or(DEFAULT_OFFER_SECS) {
+        secs if !(1..=MAX_OFFER_SECS).contains(&secs) => Err(TradeError::Expiry(secs)),
+        secs => Ok(now + Duration::seconds(secs)),
+    }
+}
+
+fn first_trade_id() -> u32 {
+    1
+}
+
+/// Every trade between the server's trainers, open and closed.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TradeBook {
+    open: Vec<Offer>,
+    /// In the order they closed.
+    history: Vec<Closed>,
+    #[serde(default = "first_trade_id")]
+    next_trade_id: u32,
+}
+
+impl Default for TradeBook {
+    fn default() -> Self {
+        TradeBook {
+            open: vec![],
+            history: vec![],
+            next_trade_id: first_trade_id(),
+        }
+    }
+}
+
+impl TradeBook {
+    pub fn next_id(&mut self) -> u32 {
+        let id = self.next_trade_id;
+        self.next_trade_id += 1;
+
+        id
+    }
+
+    pub fn open(&mut self, offer: Offer) -> &Offer {
+        self.open.push(offer);
+
+        self.open.last().unwrap()
+    }
+
+    /// The open trades `trainer` is part of, oldest first.
+    pub fn involving(&self, trainer: &str) -> Vec<&Offer> {
+        self.open
+            .iter()
+            .filter(|offer| offer.involves(trainer))
+            .collect()
+    }
+
+    /// The closed trades `trainer` was part of, in the order they closed.
+    pub fn history(&self, trainer: &str) -> Vec<&Closed> {
+        self.history
+            .iter()
+            .filter(|closed| closed.offer.involves(trainer))
+            .collect()
+    }
+
+    /// An open trade `trainer` is part of. Other trainers' trades aren't found, so their ids give
+    /// nothing away.
+    pub fn get(&self, trainer: &str, trade_id: u32) -> Result<&Offer, TradeError> {
+        self.open
+            .iter()
+            .find(|offer| offer.id == trade_id && offer.involves(trainer))
+            .ok_or(TradeError::NotFound)
+    }
+
+    pub fn get_mut(&mut self, trainer: &str, trade_id: u32) -> Result<&mut Offer, TradeError> {
+        self.open
+            .iter_mut()
+            .find(|offer| offer.id == trade_id && offer.involves(trainer))
+            .ok_or(TradeError::NotFound)
+    }
+
+    pub fn open_offer(&self, trade_id: u32) -> Option<&Offer> {
+        self.open.iter().find(|offer| offer.id == trade_id)
+    }
+
+    pub fn get_closed(&self, trainer: &str, trade_id: u32) -> Option<&Closed> {
+        self.history
+            .iter()
+            .find(|closed| closed.offer.id == trade_id && closed.offer.involves(trainer))
+    }
+
+    /// Moves an open trade into the history.
+    pub fn close(
+        &mut self,
+        trade_id: u32,
+        outcome: Outcome,
+        now: NaiveDateTime,
+        cancelled_by: Option<String>,
+        received: Vec<Received>,
+    ) -> &Closed {
+        let index = self
+            .open
+            .iter()
+            .position(|offer| offer.id == trade_id)
+            .expect("only open trades are closed");
+        let offer = self.open.remove(index);
+        self.history.push(Closed {
+            offer,
+            outcome,
+            closed: now,
+            cancelled_by,
+            received,
+        });
+
+        self.history.last().unwrap()
+    }
+
+    /// The ids of open trades that have expired by `now`.
+    pub fn expired(&self, now: NaiveDateTime) -> Vec<u32> {
+        self.open
+            .iter()
+            .filter(|offer| offer.expires <= now)
+            .map(|offer| offer.id)
+            .collect()
+    }
+}
+
+/// What PokeAPI says about how the pokemon in a trade evolve, fetched before the swap so it can
+/// happen without waiting on PokeAPI.
+#[derive(Debug, Default)]
+pub struct TradeEvolutions {
+    /// The evolutions of every species in the trade, by species id.
+    pub evolutions: HashMap<u32, Vec<Evolution>>,
+    /// Every species any of them can become by trading, by species id.
+    pub species: HashMap<u32, Pokemon>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(secs: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            + Duration::seconds(secs)
+    }
+
+    fn offer(id: u32, from: &str, to: &str, expires: NaiveDateTime) -> Offer {
+        Offer {
+            id,
+            from: from.to_string(),
+            to: to.to_string(),
+            offered: vec![1],
+            requested: vec![2],
+            created: at(0),
+            expires,
+            from_confirmed: None,
+            to_confirmed: None,
+        }
+    }
+
+    #[test]
+    fn offers_expire_within_limits() {
+        assert_eq!(expiry(at(0), None), Ok(at(DEFAULT_OFFER_SECS)));
+        assert_eq!(expiry(at(0), Some(60)), Ok(at(60)));
+        assert_eq!(expiry(at(0), Some(0)), Err(TradeError::Expiry(0)));
+        assert_eq!(
+            expiry(at(0), Some(MAX_OFFER_SECS + 1)),
+            Err(TradeError::Expiry(MAX_OFFER_SECS + 1))
+        );
+        assert_eq!(check_listed(&[]), Err(TradeError::NoPokemon));
+        assert_eq!(check_listed(&[3, 4, 3]), Err(TradeError::Repeated(3)));
+    }
+
+    #[test]
+    fn trainers_only_see_their_own_trades() {
+        let mut book = TradeBook::default();
+        let first = book.next_id();
+        book.open(offer(first, "ash", "misty", at(60)));
+        let second = book.next_id();
+        book.open(offer(second, "brock", "misty", at(120)));
+
+        assert_eq!(book.involving("misty").len(), 2);
+        assert_eq!(book.involving("ash").len(), 1);
+        assert!(book.get("ash", second).is_err());
+
+        assert_eq!(book.expired(at(90)), vec![first]);
+        book.close(first, Outcome::Expired, at(90), None, vec![]);
+
+        assert!(book.get("ash", first).is_err());
+        assert_eq!(
+            book.get_closed("ash", first).map(|closed| closed.outcome),
+            Some(Outcome::Expired)
+        );
+        assert!(book.get_closed("brock", first).is_none());
+    }
+}
@@ -0,0 +1,802 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::day_care::{Clock, SystemClock};
+use crate::evolution::{self, Trigger};
+use crate::pokemon::Pokemon;
+use crate::station::{Listing, Search, Station, StationError, Wanted};
+use crate::storage::{Storage, StorageDestination, StorageError};
+use crate::trading::{
+    self, Closed, Confirmation, Offer, Outcome, Received, Trade, TradeBook, TradeError,
+    TradeEvolutions,
+};
+
+/// The trainer requests are for when they don't say, and whose storage older snapshots hold.
+pub const DEFAULT_TRAINER: &str = "default";
+
+tokio::task_local! {
+    /// The trainer the current task is serving, set by the `trainer` middleware.
+    pub static TRAINER: String;
+}
+
+/// The trainer being served, or the default one outside of a request.
+pub fn current_trainer() -> String {
+    TRAINER
+        .try_with(|name| name.clone())
+        .unwrap_or_else(|_| DEFAULT_TRAINER.to_string())
+}
+
+/// Trainer names end up in headers and paths, so keep them short and plain.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TrainerError {
+    InvalidName(String),
+    AlreadyExists(String),
+}
+
+impl fmt::Display for TrainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrainerError::InvalidName(name) => write!(
+                f,
+                "{:?} isn't a valid trainer name, use up to 32 letters, digits, - and _",
+                name
+            ),
+            TrainerError::AlreadyExists(name) => {
+                write!(f, "there's already a trainer called {}", name)
+            }
+        }
+    }
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Everyone with pokemon on the server, each with their own storage, the trades between them and
+/// the pokemon they've left on the trade station.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Trainers {
+    trainers: BTreeMap<String, Storage>,
+    #[serde(default)]
+    trades: TradeBook,
+    #[serde(default)]
+    station: Station,
+    /// What trades expire by, see `set_clock`.
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Trainers {
+    fn default() -> Self {
+        Trainers::new(Storage::default())
+    }
+}
+
+impl Trainers {
+    /// Just the default trainer, with `storage`.
+    pub fn new(storage: Storage) -> Self {
+        let mut trainers = BTreeMap::new();
+        trainers.insert(DEFAULT_TRAINER.to_string(), storage);
+
+        Trainers {
+            trainers,
+            trades: TradeBook::default(),
+            station: Station::default(),
+            clock: system_clock(),
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.trainers.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.trainers.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Storage> {
+        self.trainers.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Storage> {
+        self.trainers.get_mut(name)
+    }
+
+    pub fn storages_mut(&mut self) -> impl Iterator<Item = (&str, &mut Storage)> {
+        self.trainers
+            .iter_mut()
+            .map(|(name, storage)| (name.as_str(), storage))
+    }
+
+    /// Trainers are never removed, so anyone named in a trade is still around.
+    fn storage(&self, name: &str) -> &Storage {
+        self.get(name).expect("trainers are never removed")
+    }
+
+    fn storage_mut(&mut self, name: &str) -> &mut Storage {
+        self.get_mut(name).expect("trainers are never removed")
+    }
+
+    /// Adds a trainer with empty storage, sized and configured like the default trainer's.
+    pub fn add(&mut self, name: &str) -> Result<&Storage, TrainerError> {
+        if !is_valid_name(name) {
+            return Err(TrainerError::InvalidName(name.to_string()));
+        }
+        if self.contains(name) {
+            return Err(TrainerError::AlreadyExists(name.to_string()));
+        }
+
+        let storage = self.storage(DEFAULT_TRAINER).empty_like();
+
+        Ok(self.trainers.entry(name.to_string()).or_insert(storage))
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn check_can_trade(&self, trainer: &str) -> Result<(), TradeError> {
+        match self.get(trainer) {
+            None => Err(TradeError::UnknownTrainer(trainer.to_string())),
+            Some(storage) if storage.in_nuzlocke() => {
+                Err(TradeError::NuzlockeRunning(trainer.to_string()))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Closes every open trade that's expired, letting go of the pokemon held for it.
+    pub fn expire_trades(&mut self) {
+        let now = self.clock.now();
+        for trade_id in self.trades.expired(now) {
+            self.release(trade_id);
+            self.trades
+                .close(trade_id, Outcome::Expired, now, None, vec![]);
+        }
+    }
+
+    /// Lets go of everything held for an open trade.
+    fn release(&mut self, trade_id: u32) {
+        let offer = match self.trades.open_offer(trade_id) {
+            Some(offer) => offer.clone(),
+            None => return,
+        };
+        self.storage_mut(&offer.from).release_escrow(&offer.offered);
+        if offer.to_confirmed.is_some() {
+            self.storage_mut(&offer.to).release_escrow(&offer.requested);
+        }
+    }
+
+    /// Offers `to` the `offered` pokemon of `from` for their `requested` ones. The offered pokemon
+    /// are held until the trade is done, for `expires_in_secs` at most.
+    pub fn offer_trade(
+        &mut self,
+        from: &str,
+        to: &str,
+        offered: Vec<u32>,
+        requested: Vec<u32>,
+        expires_in_secs: Option<i64>,
+    ) -> Result<&Offer, StorageError> {
+        self.expire_trades();
+        if from == to {
+            return Err(TradeError::WithSelf.into());
+        }
+        trading::check_listed(&offered)?;
+        trading::check_listed(&requested)?;
+        let now = self.clock.now();
+        let expires = trading::expiry(now, expires_in_secs)?;
+        self.check_can_trade(from)?;
+        self.check_can_trade(to)?;
+        for pokemon_id in &requested {
+            self.storage(to).get_pokemon(*pokemon_id)?;
+        }
+
+        self.storage_mut(from).escrow(&offered)?;
+        let id = self.trades.next_id();
+
+        Ok(self.trades.open(Offer {
+            id,
+            from: from.to_string(),
+            to: to.to_string(),
+            offered,
+            requested,
+            created: now,
+            expires,
+            from_confirmed: None,
+            to_confirmed: None,
+        }))
+    }
+
+    /// The species of every pokemon in a trade, for looking up how they evolve before `trainer`
+    /// confirms it. Empty unless their confirming would complete it.
+    pub fn trade_species(&self, trainer: &str, trade_id: u32) -> Result<Vec<u32>, StorageError> {
+        let offer = self.trades.get(trainer, trade_id)?;
+        let other_confirmed = if trainer == offer.from {
+            offer.to_confirmed
+        } else {
+            offer.from_confirmed
+        };
+        if other_confirmed.is_none() {
+            return Ok(vec![]);
+        }
+
+        let (from, to) = (self.storage(&offer.from), self.storage(&offer.to));
+        offer
+            .offered
+            .iter()
+            .map(|pokemon_id| from.get_pokemon(*pokemon_id))
+            .chain(
+                offer
+                    .requested
+                    .iter()
+                    .map(|pokemon_id| to.get_pokemon(*pokemon_id)),
+            )
+            .map(|pokemon| pokemon.map(|pokemon| pokemon.pokeAPI_id))
+            .collect()
+    }
+
+    /// Confirms `trainer`'s side of a trade, which for the trainer it was offered to holds their
+    /// pokemon too. Confirming again changes where what they receive goes. Once both sides have
+    /// confirmed the pokemon are swapped, all at once or not at all, and any that evolve by
+    /// trading do, using `evolutions`.
+    pub fn confirm_trade(
+        &mut self,
+        trainer: &str,
+        trade_id: u32,
+        confirmation: Confirmation,
+        evolutions: &TradeEvolutions,
+    ) -> Result<Trade<'_>, StorageError> {
+        self.expire_trades();
+        let mut offer = self.trades.get(trainer, trade_id)?.clone();
+        self.check_can_trade(&offer.from)?;
+        self.check_can_trade(&offer.to)?;
+
+        let newly_held = trainer == offer.to && offer.to_confirmed.is_none();
+        if newly_held {
+            self.storage_mut(&offer.to).escrow(&offer.requested)?;
+        }
+        if trainer == offer.from {
+            offer.from_confirmed = Some(confirmation);
+        } else {
+            offer.to_confirmed = Some(confirmation);
+        }
+
+        let (from_confirmed, to_confirmed) = match (offer.from_confirmed, offer.to_confirmed) {
+            (Some(from_confirmed), Some(to_confirmed)) => (from_confirmed, to_confirmed),
+            _ => {
+                let open = self.trades.get_mut(trainer, trade_id)?;
+                *open = offer;
+
+                return Ok(Trade::Open(open));
+            }
+        };
+        let received = match self.swap(&offer, from_confirmed, to_confirmed, evolutions) {
+            Ok(received) => received,
+            Err(e) => {
+                if newly_held {
+                    self.storage_mut(&offer.to).release_escrow(&offer.requested);
+                }
+                return Err(e);
+            }
+        };
+
+        *self.trades.get_mut(trainer, trade_id)? = offer;
+        let now = self.clock.now();
+
+        Ok(Trade::Closed(self.trades.close(
+            trade_id,
+            Outcome::Completed,
+            now,
+            None,
+            received,
+        )))
+    }
+
+    /// Swaps the pokemon in a trade both sides have confirmed. It's worked out on copies of both
+    /// storages, which only replace the real ones if every pokemon found room.
+    fn swap(
+        &mut self,
+        offer: &Offer,
+        from_confirmed: Confirmation,
+        to_confirmed: Confirmation,
+        evolutions: &TradeEvolutions,
+    ) -> Result<Vec<Received>, StorageError> {
+        let mut from = self.storage(&offer.from).clone();
+        let mut to = self.storage(&offer.to).clone();
+
+        let offered = from.trade_away(&offer.offered)?;
+        let requested = to.trade_away(&offer.requested)?;
+        let offered_for = traded_for(&requested);
+        let requested_for = traded_for(&offered);
+
+        let mut received = receive(
+            &mut to,
+            (&offer.to, &offer.from),
+            offered,
+            to_confirmed,
+            offered_for.as_deref(),
+            evolutions,
+        )?;
+        received.extend(receive(
+            &mut from,
+            (&offer.from, &offer.to),
+            requested,
+            from_confirmed,
+            requested_for.as_deref(),
+            evolutions,
+        )?);
+
+        *self.storage_mut(&offer.from) = from;
+        *self.storage_mut(&offer.to) = to;
+
+        Ok(received)
+    }
+
+    /// Calls off an open trade, which either side can do.
+    pub fn cancel_trade(&mut self, trainer: &str, trade_id: u32) -> Result<&Closed, StorageError> {
+        self.expire_trades();
+        self.trades.get(trainer, trade_id)?;
+        self.release(trade_id);
+        let now = self.clock.now();
+
+        Ok(self.trades.close(
+            trade_id,
+            Outcome::Cancelled,
+            now,
+            Some(trainer.to_string()),
+            vec![],
+        ))
+    }
+
+    /// The open trades `trainer` is part of, oldest first.
+    pub fn open_trades(&mut self, trainer: &str) -> Vec<&Offer> {
+        self.expire_trades();
+
+        self.trades.involving(trainer)
+    }
+
+    /// A trade `trainer` is or was part of.
+    pub fn trade(&mut self, trainer: &str, trade_id: u32) -> Result<Trade<'_>, StorageError> {
+        self.expire_trades();
+        if let Some(closed) = self.trades.get_closed(trainer, trade_id) {
+            return Ok(Trade::Closed(closed));
+        }
+
+        Ok(Trade::Open(self.trades.get(trainer, trade_id)?))
+    }
+
+    /// The trades `trainer` was part of that are done, in the order they closed.
+    pub fn trade_history(&mut self, trainer: &str) -> Vec<&Closed> {
+        self.expire_trades();
+
+        self.trades.history(trainer)
+    }
+
+    /// Lists one of `trainer`'s boxed pokemon on the trade station for anyone with what they want.
+    /// It leaves their storage until it's withdrawn or traded.
+    pub fn deposit(
+        &mut self,
+        trainer: &str,
+        pokemon_id: u32,
+        wanted: Wanted,
+    ) -> Result<&Listing, StorageError> {
+        self.check_can_trade(trainer)?;
+        let (pokemon, box_) = self.storage_mut(trainer).deposit(pokemon_id)?;
+        let now = self.clock.now();
+
+        Ok(self.station.list(trainer, pokemon, box_, wanted, now))
+    }
+
+    /// Every listing on the trade station matching `search`, oldest first.
+    pub fn listings(&self, search: &Search) -> Vec<&Listing> {
+        self.station.search(search)
+    }
+
+    pub fn listing(&self, listing_id: u32) -> Result<&Listing, StorageError> {
+        Ok(self.station.get(listing_id)?)
+    }
+
+    /// Takes one of `trainer`'s listings off the trade station, putting the pokemon back in the
+    /// box it came from or the first with room.
+    pub fn withdraw_listing(
+        &mut self,
+        trainer: &str,
+        listing_id: u32,
+    ) -> Result<&Pokemon, StorageError> {
+        let listing = self.station.get(listing_id)?;
+        if listing.trainer != trainer {
+            return Err(StationError::NotYours.into());
+        }
+        let mut storage = self.storage(trainer).clone();
+        let pokemon_id = storage
+            .withdraw_deposit(listing.pokemon.clone(), listing.box_)?
+            .id();
+
+        self.station.remove(listing_id)?;
+        *self.storage_mut(trainer) = storage;
+
+        self.storage(trainer).get_pokemon(pokemon_id)
+    }
+
+    /// The species of a listed pokemon and the one `trainer` would fulfil the listing with, for
+    /// looking up how they evolve beforehand.
+    pub fn listing_species(
+        &self,
+        trainer: &str,
+        listing_id: u32,
+        pokemon_id: u32,
+    ) -> Result<Vec<u32>, StorageError> {
+        let listing = self.station.get(listing_id)?;
+        let pokemon = self.storage(trainer).get_pokemon(pokemon_id)?;
+
+        Ok(vec![listing.pokemon.pokeAPI_id, pokemon.pokeAPI_id])
+    }
+
+    /// Trades `trainer`'s pokemon for a listed one, if it's what the depositor wants. Like a trade
+    /// it's worked out on copies of both storages, so either both pokemon move or neither does,
+    /// and any that evolve by trading do, using `evolutions`.
+    pub fn fulfil_listing(
+        &mut self,
+        trainer: &str,
+        listing_id: u32,
+        pokemon_id: u32,
+        confirmation: Confirmation,
+        evolutions: &TradeEvolutions,
+    ) -> Result<Vec<Received>, StorageError> {
+        let listing = self.station.get(listing_id)?.clone();
+        if listing.trainer == trainer {
+            return Err(StationError::OwnListing.into());
+        }
+        self.check_can_trade(trainer)?;
+        self.check_can_trade(&listing.trainer)?;
+        if !listing
+            .wanted
+            .accepts(self.storage(trainer).get_pokemon(pokemon_id)?)
+        {
+            return Err(StationError::Unwanted(listing.wanted).into());
+        }
+
+        let mut fulfiller = self.storage(trainer).clone();
+        let mut depositor = self.storage(&listing.trainer).clone();
+        let offered = fulfiller.trade_for_listing(pokemon_id)?;
+        let offered_species = offered.species_name().to_string();
+        let listed_species = listing.pokemon.species_name().to_string();
+
+        let destination = depositor.box_destination(listing.box_)?;
+        let received = vec![
+            receive_one(
+                &mut fulfiller,
+                (trainer, &listing.trainer),
+                listing.pokemon,
+                destination_of(confirmation),
+                Some(&offered_species),
+                evolutions,
+            )?,
+            receive_one(
+                &mut depositor,
+                (&listing.trainer, trainer),
+                offered,
+                destination,
+                Some(&listed_species),
+                evolutions,
+            )?,
+        ];
+
+        self.station.remove(listing_id)?;
+        *self.storage_mut(trainer) = fulfiller;
+        *self.storage_mut(&listing.trainer) = depositor;
+
+        Ok(received)
+    }
+}
+
+/// The species a side of a trade is being traded for, which some evolutions depend on. Only a
+/// single pokemon is traded for anything in particular.
+fn traded_for(other_side: &[Pokemon]) -> Option<String> {
+    match other_side {
+        [pokemon] => Some(pokemon.species_name().to_string()),
+        _ => None,
+    }
+}
+
+/// Stores the pokemon `trainer` receives from `from`, evolving any that evolve by trading.
+fn receive(
+    storage: &mut Storage,
+    (trainer, from): (&str, &str),
+    pokemon: Vec<Pokemon>,
+    confirmation: Confirmation,
+    traded_for: Option<&str>,
+    evolutions: &TradeEvolutions,
+) -> Result<Vec<Received>, StorageError> {
+    pokemon
+        .into_iter()
+        .map(|pokemon| {
+            receive_one(
+                storage,
+                (trainer, from),
+                pokemon,
+                destination_of(confirmation),
+                traded_for,
+                evolutions,
+            )
+        })
+        .collect()
+}
+
+/// Where a trainer's confirmation says what they receive goes.
+fn destination_of(confirmation: Confirmation) -> StorageDestination {
+    match confirmation.box_ {
+        Some(i) => StorageDestination::Box(i),
+        None => StorageDestination::Auto,
+    }
+}
+
+/// Stores a single pokemon `trainer` receives from `from` at `destination`, evolving it if it
+/// evolves by trading.
+fn receive_one(
+    storage: &mut Storage,
+    (trainer, from): (&str, &str),
+    pokemon: Pokemon,
+    destination: StorageDestination,
+    traded_for: Option<&str>,
+    evolutions: &TradeEvolutions,
+) -> Result<Received, StorageError> {
+    let species_id = pokemon.pokeAPI_id;
+    let species = pokemon.species_name().to_string();
+    let pokemon_id = storage.receive_traded(pokemon, from, destination)?.id();
+    let evolved_into = trade_evolve(storage, pokemon_id, species_id, traded_for, evolutions)?;
+
+    Ok(Received {
+        trainer: trainer.to_string(),
+        pokemon_id,
+        species,
+        evolved_into,
+    })
+}
+
+/// Evolves a pokemon that's just been traded, if trading makes it evolve into exactly one species.
+/// It stays as it is if it's unclear which, or evolving would break the party rules.
+fn trade_evolve(
+    storage: &mut Storage,
+    pokemon_id: u32,
+    species_id: u32,
+    traded_for: Option<&str>,
+    evolutions: &TradeEvolutions,
+) -> Result<Option<String>, StorageError> {
+    // Everything in the trade was looked up beforehand, so a species that wasn't changed since.
+    let choices = evolutions
+        .evolutions
+        .get(&species_id)
+        .ok_or(StorageError::Changed)?;
+    let trigger = Trigger {
+        name: "trade",
+        item: None,
+        traded_for,
+    };
+    let target = match evolution::choose(choices, storage.get_pokemon(pokemon_id)?, None, &trigger)
+    {
+        Ok((target, _)) => target,
+        Err(_) => return Ok(None),
+    };
+    let species = evolutions
+        .species
+        .get(&target.species_id)
+        .ok_or(StorageError::Changed)?
+        .clone();
+
+    Ok(storage
+        .evolve_pokemon(pokemon_id, species_id, choices, None, &trigger, species)
+        .ok()
+        .map(|pokemon| pokemon.species_name().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::{Evolution, EvolutionMethod};
+    use crate::storage::Location;
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct FixedClock(Mutex<NaiveDateTime>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> NaiveDateTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn pokemon(species_id: u32, name: &str) -> Pokemon {
+        serde_json::from_value(serde_json::json!({
+            "pokeAPI_id": species_id,
+            "name": name,
+            "height": 5,
+            "weight": 115,
+            "base_happiness": 70,
+        }))
+        .unwrap()
+    }
+
+    /// Ash and misty, with a kadabra and a poliwag each boxed.
+    fn trainers() -> (Trainers, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock(Mutex::new(
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+        )));
+        let mut trainers = Trainers::default();
+        trainers.set_clock(clock.clone());
+        trainers.add("ash").unwrap();
+        trainers.add("misty").unwrap();
+        for (name, species) in [
+            ("ash", pokemon(64, "kadabra")),
+            ("misty", pokemon(60, "poliwag")),
+        ] {
+            let storage = trainers.get_mut(name).unwrap();
+            storage.add_box().unwrap();
+            storage
+                .add_pokemon(species, StorageDestination::Box(0))
+                .unwrap();
+        }
+
+        (trainers, clock)
+    }
+
+    fn evolutions() -> TradeEvolutions {
+        let mut evolutions = TradeEvolutions::default();
+        evolutions.evolutions.insert(
+            64,
+            vec![Evolution {
+                species: "alakazam".to_string(),
+                species_id: 65,
+                methods: vec![EvolutionMethod {
+                    trigger: "trade".to_string(),
+                    ..EvolutionMethod::default()
+                }],
+            }],
+        );
+        evolutions.evolutions.insert(60, vec![]);
+        evolutions.species.insert(65, pokemon(65, "alakazam"));
+
+        evolutions
+    }
+
+    #[test]
+    fn confirmed_trades_swap_and_evolve() {
+        let (mut trainers, _) = trainers();
+        let trade_id = trainers
+            .offer_trade("ash", "misty", vec![1], vec![1], None)
+            .unwrap()
+            .id;
+        assert_eq!(
+            trainers
+                .get_mut("ash")
+                .unwrap()
+                .release_pokemon(1)
+                .err()
+                .map(|e| format!("{:?}", e)),
+            Some("Trade(InEscrow(1))".to_string())
+        );
+
+        let confirmation = Confirmation::default();
+        match trainers.confirm_trade("ash", trade_id, confirmation, &evolutions()) {
+            Ok(Trade::Open(offer)) => assert!(offer.to_confirmed.is_none()),
+            other => panic!("expected the trade to stay open, got {:?}", other.err()),
+        }
+        match trainers.confirm_trade("misty", trade_id, confirmation, &evolutions()) {
+            Ok(Trade::Closed(closed)) => assert_eq!(closed.outcome, Outcome::Completed),
+            other => panic!("expected the trade to complete, got {:?}", other.err()),
+        }
+
+        let ash = trainers.get("ash").unwrap();
+        let poliwag = ash.get_pokemon(2).unwrap();
+        assert_eq!(poliwag.name, "poliwag");
+        assert_eq!(poliwag.instance.original_trainer.as_deref(), Some("misty"));
+        assert!(ash.get_pokemon(1).is_err());
+
+        let misty = trainers.get("misty").unwrap();
+        let alakazam = misty.get_pokemon(2).unwrap();
+        assert_eq!(alakazam.name, "alakazam");
+        assert_eq!(alakazam.instance.evolutions[0].trigger, "trade");
+        assert_eq!(misty.location(2).unwrap(), Location::Party);
+        assert!(misty.pokedex().has_caught(65));
+
+        let history = trainers.trade_history("misty");
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].received[0].evolved_into.as_deref(),
+            Some("alakazam")
+        );
+    }
+
+    #[test]
+    fn trades_that_dont_fit_change_nothing_and_expired_ones_let_go() {
+        let (mut trainers, clock) = trainers();
+        let trade_id = trainers
+            .offer_trade("ash", "misty", vec![1], vec![1], Some(60))
+            .unwrap()
+            .id;
+        trainers
+            .confirm_trade("ash", trade_id, Confirmation::default(), &evolutions())
+            .unwrap();
+        // Misty's only box is the one their poliwag leaves, and there's no such box 3.
+        let full = Confirmation { box_: Some(3) };
+        assert!(trainers
+            .confirm_trade("misty", trade_id, full, &evolutions())
+            .is_err());
+        assert_eq!(
+            trainers.get("ash").unwrap().get_pokemon(1).unwrap().name,
+            "kadabra"
+        );
+        assert_eq!(
+            trainers.get("misty").unwrap().get_pokemon(1).unwrap().name,
+            "poliwag"
+        );
+        // Misty's poliwag isn't held for a confirmation that didn't happen.
+        assert!(trainers
+            .get_mut("misty")
+            .unwrap()
+            .release_pokemon(1)
+            .is_ok());
+
+        *clock.0.lock().unwrap() += Duration::seconds(61);
+        assert!(trainers.open_trades("ash").is_empty());
+        assert_eq!(trainers.trade_history("ash")[0].outcome, Outcome::Expired);
+        assert!(trainers.get_mut("ash").unwrap().release_pokemon(1).is_ok());
+    }
+
+    #[test]
+    fn listings_leave_storage_until_withdrawn_or_traded() {
+        let (mut trainers, _) = trainers();
+        let poliwag = Wanted::new(&pokemon(60, "poliwag"), None, None).unwrap();
+        let listing_id = trainers.deposit("ash", 1, poliwag.clone()).unwrap().id;
+        assert!(trainers.get("ash").unwrap().get_pokemon(1).is_err());
+        assert!(trainers.get("ash").unwrap().get_box(0).unwrap().is_empty());
+
+        assert!(matches!(
+            trainers.withdraw_listing("misty", listing_id),
+            Err(StorageError::Station(StationError::NotYours))
+        ));
+        assert_eq!(
+            trainers.withdraw_listing("ash", listing_id).unwrap().id(),
+            1
+        );
+        assert_eq!(
+            trainers.get("ash").unwrap().location(1).unwrap(),
+            Location::Box(0)
+        );
+        assert!(trainers.listing(listing_id).is_err());
+
+        let listing_id = trainers.deposit("ash", 1, poliwag).unwrap().id;
+        let received = trainers
+            .fulfil_listing(
+                "misty",
+                listing_id,
+                1,
+                Confirmation::default(),
+                &evolutions(),
+            )
+            .unwrap();
+        assert_eq!(received[0].evolved_into.as_deref(), Some("alakazam"));
+        assert_eq!(
+            trainers.get("misty").unwrap().get_pokemon(2).unwrap().name,
+            "alakazam"
+        );
+        let ash = trainers.get("ash").unwrap();
+        assert_eq!(ash.get_pokemon(2).unwrap().name, "poliwag");
+        assert_eq!(ash.location(2).unwrap(), Location::Box(0));
+        assert!(trainers.listings(&Search::default()).is_empty());
+    }
+}
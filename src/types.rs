@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Type {
+    Normal,
+    Fire,
+    Water,
+    Electric,
+    Grass,
+    Ice,
+    Fighting,
+    Poison,
+    Ground,
+    Flying,
+    Psychic,
+    Bug,
+    Rock,
+    Ghost,
+    Dragon,
+    Dark,
+    Steel,
+    Fairy,
+}
+
+impl Type {
+    pub const ALL: [Type; 18] = [
+        Type::Normal,
+        Type::Fire,
+        Type::Water,
+        Type::Electric,
+        Type::Grass,
+        Type::Ice,
+        Type::Fighting,
+        Type::Poison,
+        Type::Ground,
+        Type::Flying,
+        Type::Psychic,
+        Type::Bug,
+        Type::Rock,
+        Type::Ghost,
+        Type::Dragon,
+        Type::Dark,
+        Type::Steel,
+        Type::Fairy,
+    ];
+
+    /// Looks up a type by its PokeAPI name. PokeAPI's `unknown` and `shadow` types have no place in
+    /// the chart and come back as `None`.
+    pub fn from_name(name: &str) -> Option<Type> {
+        Type::ALL
+            .iter()
+            .copied()
+            .find(|type_| serde_json::to_value(type_).unwrap() == name)
+    }
+
+    /// The types this one is (super effective against, not very effective against, no effect on)
+    /// when attacking, as of generation VI.
+    fn matchups(self) -> (&'static [Type], &'static [Type], &'static [Type]) {
+        use Type::*;
+
+        match self {
+            Normal => (&[], &[Rock, Steel], &[Ghost]),
+            Fire => (&[Grass, Ice, Bug, Steel], &[Fire, Water, Rock, Dragon], &[]),
+            Water => (&[Fire, Ground, Rock], &[Water, Grass, Dragon], &[]),
+            Electric => (&[Water, Flying], &[Electric, Grass, Dragon], &[Ground]),
+            Grass => (
+                &[Water, Ground, Rock],
+                &[Fire, Grass, Poison, Flying, Bug, Dragon, Steel],
+                &[],
+            ),
+            Ice => (
+                &[Grass, Ground, Flying, Dragon],
+                &[Fire, Water, Ice, Steel],
+                &[],
+            ),
+            Fighting => (
+                &[Normal, Ice, Rock, Dark, Steel],
+                &[Poison, Flying, Psychic, Bug, Fairy],
+                &[Ghost],
+            ),
+            Poison => (&[Grass, Fairy], &[Poison, Ground, Rock, Ghost], &[Steel]),
+            Ground => (
+                &[Fire, Electric, Poison, Rock, Steel],
+                &[Grass, Bug],
+                &[Flying],
+            ),
+            Flying => (&[Grass, Fighting, Bug], &[Electric, Rock, Steel], &[]),
+            Psychic => (&[Fighting, Poison], &[Psychic, Steel], &[Dark]),
+            Bug => (
+                &[Grass, Psychic, Dark],
+                &[Fire, Fighting, Poison, Flying, Ghost, Steel, Fairy],
+                &[],
+            ),
+            Rock => (&[Fire, Ice, Flying, Bug], &[Fighting, Ground, Steel], &[]),
+            Ghost => (&[Psychic, Ghost], &[Dark], &[Normal]),
+            Dragon => (&[Dragon], &[Steel], &[Fairy]),
+            Dark => (&[Psychic, Ghost], &[Fighting, Dark, Fairy], &[]),
+            Steel => (&[Ice, Rock, Fairy], &[Fire, Water, Electric, Steel], &[]),
+            Fairy => (&[Fighting, Dragon, Dark], &[Fire, Poison, Steel], &[]),
+        }
+    }
+
+    /// The damage multiplier for a move of this type hitting `defending`.
+    pub fn effectiveness(self, defending: Type) -> f64 {
+        let (super_effective, not_very_effective, no_effect) = self.matchups();
+
+        if super_effective.contains(&defending) {
+            2.0
+        } else if not_very_effective.contains(&defending) {
+            0.5
+        } else if no_effect.contains(&defending) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// The damage multiplier against a pokemon with all of `defending`'s types.
+    pub fn effectiveness_against(self, defending: &[Type]) -> f64 {
+        defending
+            .iter()
+            .map(|type_| self.effectiveness(*type_))
+            .product()
+    }
+}
+
+/// The chart types among a species' PokeAPI type names.
+pub fn parse_types(names: &[String]) -> Vec<Type> {
+    names
+        .iter()
+        .filter_map(|name| Type::from_name(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_known_matchups() {
+        // (attacking, defending, expected)
+        let cases: &[(Type, &[Type], f64)] = &[
+            (Type::Water, &[Type::Fire], 2.0),
+            (Type::Fire, &[Type::Water], 0.5),
+            (Type::Normal, &[Type::Ghost], 0.0),
+            (Type::Dragon, &[Type::Fairy], 0.0),
+            (Type::Psychic, &[Type::Dark], 0.0),
+            (Type::Normal, &[Type::Normal], 1.0),
+            // Dual types multiply.
+            (Type::Ice, &[Type::Ground, Type::Dragon], 4.0),
+            (Type::Electric, &[Type::Water, Type::Flying], 4.0),
+            (Type::Grass, &[Type::Rock, Type::Water], 4.0),
+            (Type::Fire, &[Type::Rock, Type::Water], 0.25),
+            (Type::Electric, &[Type::Water, Type::Ground], 0.0),
+            (Type::Fighting, &[Type::Rock, Type::Water], 2.0),
+        ];
+
+        for (attacking, defending, expected) in cases.iter() {
+            assert_eq!(
+                attacking.effectiveness_against(defending),
+                *expected,
+                "{:?} against {:?}",
+                attacking,
+                defending
+            );
+        }
+    }
+
+    #[test]
+    fn parses_pokeapi_names() {
+        assert_eq!(Type::from_name("fairy"), Some(Type::Fairy));
+        assert_eq!(Type::from_name("shadow"), None);
+        assert_eq!(
+            parse_types(&["rock".to_string(), "unknown".to_string()]),
+            vec![Type::Rock]
+        );
+    }
+}
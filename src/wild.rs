@@ -0,0 +1,180 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::pokemon::Pokemon;
+
+/// How wild pokemon are found unless another method is asked for.
+pub const DEFAULT_METHOD: &str = "walk";
+/// Item categories PokeAPI files poke balls under.
+const BALL_CATEGORIES: [&str; 3] = ["standard-balls", "special-balls", "apricorn-balls"];
+/// A ball has to pass this many shake checks for the pokemon to stay caught.
+const SHAKES: u32 = 4;
+
+/// One kind of pokemon that turns up in a location area, from PokeAPI's encounter data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncounterSlot {
+    pub version: String,
+    /// How the player finds it, e.g. `walk`, `surf` or `old-rod`.
+    pub method: String,
+    pub pokemon_id: u32,
+    pub pokemon: String,
+    pub min_level: u32,
+    pub max_level: u32,
+    /// Percent chance of an encounter using this slot.
+    pub chance: u32,
+}
+
+/// The wild pokemon the trainer is facing.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WildEncounter {
+    pub area: String,
+    pub version: String,
+    pub method: String,
+    pub pokemon: Pokemon,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WildError {
+    NoEncounter,
+    NotABall(String),
+}
+
+impl fmt::Display for WildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WildError::NoEncounter => write!(f, "there's no wild pokemon to catch"),
+            WildError::NotABall(name) => write!(f, "{} isn't a poke ball", name),
+        }
+    }
+}
+
+/// Picks one of `slots` weighted by its chance, and a level within its range.
+pub fn choose<'a, R: Rng>(
+    slots: &'a [EncounterSlot],
+    rng: &mut R,
+) -> Option<(&'a EncounterSlot, u32)> {
+    let total = slots.iter().map(|slot| slot.chance).sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0..total);
+    let slot = slots.iter().find(|slot| {
+        if roll < slot.chance {
+            true
+        } else {
+            roll -= slot.chance;
+            false
+        }
+    })?;
+    let level = rng.gen_range(slot.min_level..=slot.max_level.max(slot.min_level));
+
+    Some((slot, level))
+}
+
+pub fn is_ball(category: &str) -> bool {
+    BALL_CATEGORIES.contains(&category)
+}
+
+/// Gen III's catch rate bonus for `ball`, or `None` for the master ball, which never fails. Balls
+/// whose bonus depends on the situation count as ordinary poke balls.
+fn ball_bonus(ball: &str) -> Option<f64> {
+    match ball {
+        "master-ball" => None,
+        "great-ball" | "safari-ball" => Some(1.5),
+        "ultra-ball" => Some(2.0),
+        _ => Some(1.0),
+    }
+}
+
+/// How one throw went.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Throw {
+    pub ball: String,
+    /// Chance the throw had of catching the pokemon, between 0 and 1.
+    pub probability: f64,
+    pub shakes: u32,
+    pub caught: bool,
+}
+
+/// Throws `ball` at a wild pokemon with `capture_rate`, using gen III's formula for a pokemon at
+/// full health without a status condition.
+pub fn throw<R: Rng>(capture_rate: u32, ball: &str, rng: &mut R) -> Throw {
+    let modified = match ball_bonus(ball) {
+        Some(bonus) => (f64::from(capture_rate) * bonus / 3.0).floor(),
+        None => 255.0,
+    };
+    if modified >= 255.0 {
+        return Throw {
+            ball: ball.to_string(),
+            probability: 1.0,
+            shakes: SHAKES,
+            caught: true,
+        };
+    }
+    let shake_odds = if modified > 0.0 {
+        (1_048_560.0 / (16_711_680.0 / modified).sqrt().sqrt()).floor() as u32
+    } else {
+        0
+    };
+
+    let shakes = (0..SHAKES)
+        .take_while(|_| rng.gen_range(0..65536) < shake_odds)
+        .count() as u32;
+
+    Throw {
+        ball: ball.to_string(),
+        probability: (f64::from(shake_odds) / 65536.0).powi(SHAKES as i32),
+        shakes,
+        caught: shakes == SHAKES,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn slot(pokemon: &str, chance: u32) -> EncounterSlot {
+        EncounterSlot {
+            version: "red".to_string(),
+            method: "walk".to_string(),
+            pokemon_id: 1,
+            pokemon: pokemon.to_string(),
+            min_level: 20,
+            max_level: 30,
+            chance,
+        }
+    }
+
+    #[test]
+    fn chooses_slots_by_chance_within_their_levels() {
+        let slots = [slot("kabuto", 0), slot("omanyte", 100)];
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..50 {
+            let (slot, level) = choose(&slots, &mut rng).unwrap();
+            assert_eq!(slot.pokemon, "omanyte");
+            assert!((20..=30).contains(&level));
+        }
+        assert_eq!(choose(&slots[..1], &mut rng), None);
+    }
+
+    #[test]
+    fn better_balls_catch_more_often() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let odds = |ball: &str, rng: &mut StdRng| throw(45, ball, rng).probability;
+
+        let poke_ball = odds("poke-ball", &mut rng);
+        assert!((poke_ball - 0.0588).abs() < 0.001);
+        assert!(odds("great-ball", &mut rng) > poke_ball);
+        assert!(odds("ultra-ball", &mut rng) > odds("great-ball", &mut rng));
+
+        let master_ball = throw(3, "master-ball", &mut rng);
+        assert!(master_ball.caught);
+        assert_eq!(master_ball.shakes, SHAKES);
+        assert!(!throw(0, "ultra-ball", &mut rng).caught);
+    }
+}